@@ -0,0 +1,82 @@
+//! An attract mode for when nobody's around: after a while without any input the fluid is stirred
+//! up by turbulence and its settings slowly drift, until the next input puts them all back.
+
+use std::time::{Duration, Instant};
+
+use glam::Vec3;
+use rand::thread_rng;
+
+use crate::noise::Noise;
+use crate::settings::Settings;
+
+pub struct Attract {
+    // how long without any input before it starts
+    timeout: Duration,
+    last_input: Instant,
+    // the settings from before it started, to go back to, while it's running
+    saved: Option<Settings>,
+    // drives the drift
+    noise: Noise,
+}
+
+impl Attract {
+    /// How strongly the fluid is stirred once it's running (unless the turbulence was already
+    /// stronger than this)
+    const TURBULENCE: f32 = 20.0;
+    /// How long it takes to ease into the turbulence and drift, in seconds
+    const TRANSITION: f32 = 5.0;
+    /// How quickly the settings drift, in noise units per second
+    const DRIFT_SPEED: f32 = 0.05;
+    /// How far (as a power of two) each setting drifts in either direction, at most
+    const DRIFT_OCTAVES: f32 = 0.5;
+
+    pub fn new(timeout: Duration) -> Attract {
+        Attract {
+            timeout,
+            last_input: Instant::now(),
+            saved: None,
+            noise: Noise::new(&mut thread_rng()),
+        }
+    }
+
+    /// Notes that there's been some input, stopping the attract mode if it's running. Returns
+    /// whether it was, in which case the settings are back to what they were before it started
+    pub fn input(&mut self, settings: &mut Settings) -> bool {
+        self.last_input = Instant::now();
+        match self.saved.take() {
+            Some(saved) => {
+                *settings = saved;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Starts the attract mode once there's been no input for long enough, and drifts the settings
+    /// while it's running. Returns whether it's just started
+    pub fn update(&mut self, settings: &mut Settings) -> bool {
+        let Some(running) = self.last_input.elapsed().checked_sub(self.timeout) else {
+            return false;
+        };
+
+        let started = self.saved.is_none();
+        let saved = self.saved.get_or_insert_with(|| settings.clone());
+
+        // ease in, so the fluid doesn't lurch into motion
+        let t = (running.as_secs_f32() / Self::TRANSITION).min(1.0);
+        let blend = t * t * (3.0 - 2.0 * t);
+
+        *settings = saved.clone();
+        let turbulence = saved.turbulence.max(Self::TURBULENCE);
+        settings.turbulence += (turbulence - saved.turbulence) * blend;
+        let time = running.as_secs_f32() * Self::DRIFT_SPEED;
+        for (i, name) in Settings::MUTABLE.iter().enumerate() {
+            // each setting follows its own path through the noise
+            let drift = self.noise.sample(Vec3::new(time, i as f32 * 10.0, 0.5));
+            let octaves = drift * Self::DRIFT_OCTAVES * blend;
+            settings.scale(name, octaves.exp2()).unwrap();
+        }
+
+        started
+    }
+}
@@ -0,0 +1,70 @@
+//! Timing the simulation headlessly, to see how long each phase of a tick takes (e.g. to compare
+//! builds with and without the `simd` feature).
+
+use std::time::Instant;
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::material::Behaviour;
+use crate::profiler;
+use crate::settings::Settings;
+use crate::state::{Solver, State};
+
+/// How many ticks the fluid's given to settle before anything's timed, so each particle has about
+/// as many neighbours as it usually would
+const SETTLE_TICKS: u64 = 120;
+
+/// Runs the given number of ticks (starting from the given settings and config) and prints how long
+/// each one took on average, and how long each phase of them took
+pub fn run(
+    settings: &Settings,
+    config: &Config,
+    seed: Option<u64>,
+    solver: Solver,
+    base_behaviour: Behaviour,
+    particle_count: Option<usize>,
+    ticks: u64,
+) -> Result<()> {
+    let mut state = match particle_count {
+        Some(particle_count) => State::with_particle_count(settings.clone(), seed, particle_count),
+        None => State::new(settings.clone(), seed),
+    };
+    state.solver = solver;
+    state.base_behaviour = base_behaviour;
+    config.apply(&mut state);
+
+    for _ in 0..SETTLE_TICKS {
+        state.step(&[]);
+    }
+
+    // the phases are timed by the profiler, the same as it does on screen
+    profiler::set_enabled(true);
+    let started = Instant::now();
+    for _ in 0..ticks {
+        state.step(&[]);
+    }
+    let elapsed = started.elapsed();
+    let phases = profiler::summary();
+    profiler::set_enabled(false);
+
+    let simd = match cfg!(feature = "simd") {
+        true => "on",
+        false => "off",
+    };
+    println!(
+        "{} particles, {ticks} ticks with the {solver:?} solver (simd: {simd})",
+        state.positions.len()
+    );
+    println!(
+        "{:.3}ms per tick on average",
+        elapsed.as_secs_f32() * 1000.0 / ticks as f32
+    );
+    println!();
+    println!("the last few seconds of each phase, on average:");
+    for line in phases {
+        println!("{line}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+//! Rigid bodies in the fluid: balls and boxes that float or sink in it, are carried along by it, and
+//! push it out of the way. The fluid acts on them through buoyancy (they're lifted by the weight of
+//! the fluid they're sitting in) and drag (they're pulled along with the fluid around them), rather
+//! than being knocked about by each particle, which keeps them steady however hard they're hit.
+//! Dropped in at the cursor with 1 (a ball) or 2 (a box), and removed with 0.
+
+use glam::Vec2;
+
+use crate::rect::Rect;
+
+/// The shape of a body, around its centre
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Circle { radius: f32 },
+    Box { half_size: Vec2 },
+}
+
+impl Shape {
+    fn area(&self) -> f32 {
+        match *self {
+            Shape::Circle { radius } => std::f32::consts::PI * radius * radius,
+            Shape::Box { half_size } => 4.0 * half_size.x * half_size.y,
+        }
+    }
+
+    /// How hard it is to spin around its centre for each unit of its mass (i.e. its moment of
+    /// inertia divided by its mass)
+    fn inertia_per_mass(&self) -> f32 {
+        match *self {
+            Shape::Circle { radius } => radius * radius / 2.0,
+            Shape::Box { half_size } => half_size.length_squared() / 3.0,
+        }
+    }
+
+    /// How far it reaches from its centre, in any direction
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            Shape::Circle { radius } => radius,
+            Shape::Box { half_size } => half_size.length(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Body {
+    pub shape: Shape,
+    /// How heavy it is for its size, as a fraction of the fluid's density: lighter than 1 floats,
+    /// and heavier sinks
+    pub density: f32,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// How far it's turned, in radians (clockwise, since y points down)
+    pub angle: f32,
+    pub angular_velocity: f32,
+    // the position and angle as of the previous tick, for interpolating between ticks when
+    // rendering
+    previous_position: Vec2,
+    previous_angle: f32,
+}
+
+impl Body {
+    /// How much of a bounce's speed along a contact is lost to friction, as a fraction of how hard
+    /// the bounce was (so boxes roll over rather than sliding along the floor forever)
+    const FRICTION: f32 = 0.3;
+
+    /// How many points around its surface the fluid is sampled at
+    const SURFACE_SAMPLES: usize = 16;
+
+    pub fn new(shape: Shape, position: Vec2, density: f32) -> Body {
+        Body {
+            shape,
+            density,
+            position,
+            velocity: Vec2::ZERO,
+            angle: 0.0,
+            angular_velocity: 0.0,
+            previous_position: position,
+            previous_angle: 0.0,
+        }
+    }
+
+    /// Its mass, where the same area of fluid has a mass of 1 per unit of area
+    pub fn mass(&self) -> f32 {
+        self.density * self.shape.area()
+    }
+
+    /// Where it should be drawn, and at what angle: between where it was on the previous tick and
+    /// where it is now (see `State::interpolation`)
+    pub fn interpolated(&self, t: f32) -> (Vec2, f32) {
+        (
+            self.previous_position.lerp(self.position, t),
+            self.previous_angle + (self.angle - self.previous_angle) * t,
+        )
+    }
+
+    /// Moves it along by its velocities
+    pub fn integrate(&mut self, delta_time: f32) {
+        self.previous_position = self.position;
+        self.previous_angle = self.angle;
+        self.position += self.velocity * delta_time;
+        self.angle += self.angular_velocity * delta_time;
+    }
+
+    /// The velocity of the point on (or in) the body, taking its spin into account
+    pub fn velocity_at(&self, point: Vec2) -> Vec2 {
+        self.velocity + (point - self.position).perp() * self.angular_velocity
+    }
+
+    /// Speeds it up (and spins it) as if it were hit at `point`, by `impulse` per unit of its mass
+    pub fn apply_impulse(&mut self, impulse: Vec2, point: Vec2) {
+        let offset = point - self.position;
+        self.velocity += impulse;
+        self.angular_velocity += offset.perp_dot(impulse) / self.shape.inertia_per_mass();
+    }
+
+    /// How far the point is from its surface (negative inside it), and the direction out of the
+    /// surface nearest to it
+    pub fn signed_distance(&self, point: Vec2) -> (f32, Vec2) {
+        match self.shape {
+            Shape::Circle { radius } => {
+                let offset = point - self.position;
+                let dist = offset.length();
+                let normal = match dist > 0.0 {
+                    true => offset / dist,
+                    false => Vec2::NEG_Y,
+                };
+                (dist - radius, normal)
+            }
+            Shape::Box { half_size } => {
+                let local = self.to_local(point);
+                let excess = local.abs() - half_size;
+                let outside = excess.max(Vec2::ZERO);
+                let (dist, normal) = if outside != Vec2::ZERO {
+                    (outside.length(), outside.normalize() * local.signum())
+                } else if excess.x > excess.y {
+                    (excess.x, Vec2::new(local.x.signum(), 0.0))
+                } else {
+                    (excess.y, Vec2::new(0.0, local.y.signum()))
+                };
+                (dist, self.rotate(normal))
+            }
+        }
+    }
+
+    /// Points spread evenly around its surface
+    pub fn surface_points(&self) -> Vec<Vec2> {
+        match self.shape {
+            Shape::Circle { radius } => (0..Self::SURFACE_SAMPLES)
+                .map(|i| i as f32 / Self::SURFACE_SAMPLES as f32 * std::f32::consts::TAU)
+                .map(|angle| self.position + Vec2::from_angle(angle) * radius)
+                .collect(),
+            Shape::Box { half_size } => {
+                // the same number along each edge, starting from each corner
+                let per_edge = Self::SURFACE_SAMPLES / 4;
+                let corners = self.local_corners(half_size);
+                (0..4)
+                    .flat_map(|edge| {
+                        let (start, end) = (corners[edge], corners[(edge + 1) % 4]);
+                        (0..per_edge).map(move |i| start.lerp(end, i as f32 / per_edge as f32))
+                    })
+                    .map(|local| self.to_world(local))
+                    .collect()
+            }
+        }
+    }
+
+    /// Keeps it inside the walls, bouncing off them with `restitution` of its speed into them
+    pub fn collide_with_walls(&mut self, walls: Rect, restitution: f32) {
+        let limits = [
+            (Vec2::NEG_X, -walls.left()),
+            (Vec2::X, walls.right()),
+            (Vec2::NEG_Y, -walls.top()),
+            (Vec2::Y, walls.bottom()),
+        ];
+        for (normal, limit) in limits {
+            let point = self.furthest_point(normal);
+            let depth = point.dot(normal) - limit;
+            if depth > 0.0 {
+                self.position -= normal * depth;
+                // the wall doesn't move, so it's just like hitting an infinitely heavy body
+                self.bounce(
+                    -normal,
+                    point - normal * depth,
+                    Vec2::ZERO,
+                    0.0,
+                    restitution,
+                );
+            }
+        }
+    }
+
+    /// Pushes two overlapping bodies apart, and bounces them off each other with `restitution` of
+    /// the speed they're meeting at
+    pub fn collide(a: &mut Body, b: &mut Body, restitution: f32) {
+        // each body's corners (or the point on a circle that's deepest into the other one) are
+        // checked against the other's surface
+        for swap in [false, true] {
+            let (a, b) = match swap {
+                false => (&mut *a, &mut *b),
+                true => (&mut *b, &mut *a),
+            };
+            for point in a.contact_candidates(b) {
+                let (dist, normal) = b.signed_distance(point);
+                if dist >= 0.0 {
+                    continue;
+                }
+
+                // lighter bodies are moved further
+                let (mass_a, mass_b) = (a.mass(), b.mass());
+                let share = mass_b / (mass_a + mass_b);
+                a.position -= normal * dist * share;
+                b.position += normal * dist * (1.0 - share);
+
+                let mass_ratio = mass_a / mass_b;
+                let b_velocity = b.velocity_at(point);
+                let impulse = a.bounce(normal, point, b_velocity, mass_ratio, restitution);
+                b.apply_impulse(-impulse * mass_ratio, point);
+            }
+        }
+    }
+
+    /// Bounces it off a surface it's touching at `point` (which is moving at `surface_velocity`,
+    /// and faces `normal`), and returns the impulse it took (per unit of its mass). `mass_ratio` is
+    /// its mass as a fraction of whatever it's hitting's, which is 0 for a wall
+    fn bounce(
+        &mut self,
+        normal: Vec2,
+        point: Vec2,
+        surface_velocity: Vec2,
+        mass_ratio: f32,
+        restitution: f32,
+    ) -> Vec2 {
+        let relative_velocity = self.velocity_at(point) - surface_velocity;
+        let approach = relative_velocity.dot(normal);
+        if approach >= 0.0 {
+            return Vec2::ZERO;
+        }
+
+        // how much the point's speed changes for each unit of impulse, spinning included (this
+        // ignores the other body's spin, which it's only a rough approximation without)
+        let offset = point - self.position;
+        let resistance = |direction: Vec2| {
+            1.0 + offset.perp_dot(direction).powi(2) / self.shape.inertia_per_mass() + mass_ratio
+        };
+
+        let impulse = normal * -(1.0 + restitution) * approach / resistance(normal);
+        let tangent = normal.perp();
+        let slip = relative_velocity.dot(tangent);
+        let friction = (slip / resistance(tangent)).clamp(
+            -Self::FRICTION * impulse.length(),
+            Self::FRICTION * impulse.length(),
+        );
+        let impulse = impulse - tangent * friction;
+
+        self.apply_impulse(impulse, point);
+        impulse
+    }
+
+    /// The point on it furthest along `direction`
+    fn furthest_point(&self, direction: Vec2) -> Vec2 {
+        match self.shape {
+            Shape::Circle { radius } => self.position + direction * radius,
+            Shape::Box { half_size } => self
+                .local_corners(half_size)
+                .map(|corner| self.to_world(corner))
+                .into_iter()
+                .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+                .unwrap(),
+        }
+    }
+
+    /// The points on it that could be inside the other body, if they're overlapping
+    fn contact_candidates(&self, other: &Body) -> Vec<Vec2> {
+        match self.shape {
+            Shape::Circle { .. } => {
+                let (_, normal) = other.signed_distance(self.position);
+                vec![self.furthest_point(-normal)]
+            }
+            Shape::Box { half_size } => self
+                .local_corners(half_size)
+                .map(|corner| self.to_world(corner))
+                .to_vec(),
+        }
+    }
+
+    /// The corners of a box this size around its centre, clockwise from the top left
+    fn local_corners(&self, half_size: Vec2) -> [Vec2; 4] {
+        [
+            Vec2::new(-half_size.x, -half_size.y),
+            Vec2::new(half_size.x, -half_size.y),
+            Vec2::new(half_size.x, half_size.y),
+            Vec2::new(-half_size.x, half_size.y),
+        ]
+    }
+
+    fn rotate(&self, local: Vec2) -> Vec2 {
+        Vec2::from_angle(self.angle).rotate(local)
+    }
+
+    fn to_world(&self, local: Vec2) -> Vec2 {
+        self.position + self.rotate(local)
+    }
+
+    fn to_local(&self, point: Vec2) -> Vec2 {
+        Vec2::from_angle(-self.angle).rotate(point - self.position)
+    }
+}
@@ -0,0 +1,107 @@
+//! Layers of fixed particles just outside the walls, after Akinci et al.'s "Versatile Rigid-Fluid
+//! Coupling for Incompressible SPH": they count towards the density of the fluid next to them and
+//! push back on it like more fluid would. Without them, particles along the walls are missing the
+//! neighbours on the other side, so they're too light and get pulled onto the walls (where they
+//! stick). Turned off with `boundary_particles`, which just clamps particles to the walls instead.
+
+use glam::Vec2;
+
+use crate::cell_list::CellList;
+use crate::rect::Rect;
+
+pub struct Boundary {
+    positions: Vec<Vec2>,
+    // how far apart they're laid out, which is how much space each one stands in for
+    spacing: f32,
+    // for finding the ones near each fluid particle
+    cell_list: CellList,
+    // the walls and smoothing radius they were laid out for, so they're only laid out again once
+    // either changes
+    bounding_box: Rect,
+    smoothing_radius: f32,
+}
+
+impl Boundary {
+    /// How far apart they are, as a fraction of the smoothing radius: close enough that the walls
+    /// feel smooth rather than bumpy
+    const SPACING: f32 = 0.25;
+
+    pub fn new() -> Boundary {
+        Boundary {
+            positions: vec![],
+            spacing: 0.0,
+            cell_list: CellList::new(),
+            bounding_box: Rect::new(0.0, 0.0, 0.0, 0.0),
+            smoothing_radius: 0.0,
+        }
+    }
+
+    /// How much each one counts for (in the same units as the fluid particles' masses) so that
+    /// a region full of them is at `target_density`, since each stands in for the space around it
+    pub fn mass(&self, target_density: f32) -> f32 {
+        target_density * self.spacing * self.spacing
+    }
+
+    /// Lays them out again around the walls, if they or the smoothing radius have changed
+    pub fn update(&mut self, bounding_box: Rect, smoothing_radius: f32) {
+        if bounding_box == self.bounding_box && smoothing_radius == self.smoothing_radius {
+            return;
+        }
+
+        self.bounding_box = bounding_box;
+        self.smoothing_radius = smoothing_radius;
+        self.spacing = smoothing_radius * Self::SPACING;
+
+        // deep enough that a particle on the wall has a full smoothing radius of them beyond it
+        let layers = (1.0 / Self::SPACING).ceil() as usize;
+        let thickness = layers as f32 * self.spacing;
+        let depth = |layer: usize| (layer as f32 + 0.5) * self.spacing;
+
+        self.positions.clear();
+        for layer in 0..layers {
+            // the top and bottom reach past the corners, so the sides only need to cover the walls
+            let (top, bottom) = (
+                bounding_box.top() - depth(layer),
+                bounding_box.bottom() + depth(layer),
+            );
+            let (left, right) = (bounding_box.left(), bounding_box.right());
+            for x in spread(left - thickness, right + thickness, self.spacing) {
+                self.positions.push(Vec2::new(x, top));
+                self.positions.push(Vec2::new(x, bottom));
+            }
+
+            let (left, right) = (
+                bounding_box.left() - depth(layer),
+                bounding_box.right() + depth(layer),
+            );
+            for y in spread(bounding_box.top(), bounding_box.bottom(), self.spacing) {
+                self.positions.push(Vec2::new(left, y));
+                self.positions.push(Vec2::new(right, y));
+            }
+        }
+
+        let covered = Rect::new(
+            bounding_box.x - thickness,
+            bounding_box.y - thickness,
+            bounding_box.w + thickness * 2.0,
+            bounding_box.h + thickness * 2.0,
+        );
+        self.cell_list
+            .update(covered, smoothing_radius, &self.positions);
+    }
+
+    /// Those within the smoothing radius of the position
+    pub fn neighbours(&self, pos: Vec2) -> impl Iterator<Item = Vec2> + '_ {
+        self.cell_list
+            .neighbours(&self.positions, pos)
+            .map(|idx| self.positions[idx])
+    }
+}
+
+/// Evenly spaced points between `start` and `end`, as close to `spacing` apart as fits a whole
+/// number of them, each in the middle of its share of the span
+fn spread(start: f32, end: f32, spacing: f32) -> impl Iterator<Item = f32> {
+    let count = ((end - start) / spacing).round().max(1.0) as usize;
+    let step = (end - start) / count as f32;
+    (0..count).map(move |i| start + (i as f32 + 0.5) * step)
+}
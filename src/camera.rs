@@ -0,0 +1,106 @@
+//! Which part of the world is shown in the window, and how to get between world and screen space.
+
+use glam::{Mat4, Vec2, Vec4};
+use winit::dpi::PhysicalSize;
+
+use crate::rect::Rect;
+
+/// Looks at a region of the world, which fills the window when it's zoomed all the way out. It never
+/// shows anything beyond the edges of that region
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    bounds: Rect,
+    // the world position in the middle of the window
+    centre: Vec2,
+    // how many times bigger things are than when the whole of `bounds` is shown
+    zoom: f32,
+}
+
+impl Camera {
+    /// How far in the camera can zoom
+    pub const MAX_ZOOM: f32 = 8.0;
+
+    /// A camera showing the whole of `bounds`, which it can zoom in on but never see past
+    pub fn new(bounds: Rect) -> Camera {
+        Camera {
+            bounds,
+            centre: Vec2::new(bounds.x + bounds.w * 0.5, bounds.y + bounds.h * 0.5),
+            zoom: 1.0,
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// The part of the world that's in the window
+    pub fn view(&self) -> Rect {
+        let (w, h) = (self.bounds.w / self.zoom, self.bounds.h / self.zoom);
+        Rect::new(self.centre.x - w * 0.5, self.centre.y - h * 0.5, w, h)
+    }
+
+    /// Takes world space to clip space, leaving z as it is: this is the `camera` uniform that
+    /// shaders drawing things in the world transform their positions with
+    pub fn world_to_clip(&self) -> Mat4 {
+        let view = self.view();
+        let (sx, sy) = (2.0 / view.w, 2.0 / view.h);
+        // world space has y pointing down, and clip space has it pointing up
+        Mat4::from_cols(
+            Vec4::new(sx, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, -sy, 0.0, 0.0),
+            Vec4::Z,
+            Vec4::new(-1.0 - view.x * sx, 1.0 + view.y * sy, 0.0, 1.0),
+        )
+    }
+
+    /// Where the world position is on the surface, in physical pixels from its top left
+    pub fn world_to_screen(&self, surface: PhysicalSize<u32>, world_pos: Vec2) -> Vec2 {
+        let view = self.view();
+        Vec2::new(
+            (world_pos.x - view.x) / view.w * surface.width as f32,
+            (world_pos.y - view.y) / view.h * surface.height as f32,
+        )
+    }
+
+    /// The world position at the given point on the surface (in physical pixels from its top left)
+    pub fn screen_to_world(&self, surface: PhysicalSize<u32>, screen_pos: Vec2) -> Vec2 {
+        let view = self.view();
+        Vec2::new(view.x, view.y) + self.screen_len_to_world_len(surface, screen_pos)
+    }
+
+    /// Converts a distance on the surface (in physical pixels) to one in the world
+    pub fn screen_len_to_world_len(&self, surface: PhysicalSize<u32>, screen_len: Vec2) -> Vec2 {
+        let view = self.view();
+        Vec2::new(
+            screen_len.x / surface.width as f32 * view.w,
+            screen_len.y / surface.height as f32 * view.h,
+        )
+    }
+
+    /// Zooms in (or out, with a factor less than 1) while keeping the world position where it is
+    /// in the window, e.g. so what's under the cursor stays under it
+    pub fn zoom_at(&mut self, world_pos: Vec2, factor: f32) {
+        let zoom = (self.zoom * factor).clamp(1.0, Self::MAX_ZOOM);
+        self.centre = world_pos + (self.centre - world_pos) * (self.zoom / zoom);
+        self.zoom = zoom;
+        self.keep_in_bounds();
+    }
+
+    /// Moves the view by the given distance in world units
+    pub fn pan(&mut self, delta: Vec2) {
+        self.centre += delta;
+        self.keep_in_bounds();
+    }
+
+    /// Moves the view back inside the bounds if it's gone past any of their edges
+    fn keep_in_bounds(&mut self) {
+        let view = self.view();
+        let bounds = &self.bounds;
+        let half_size = Vec2::new(view.w, view.h) * 0.5;
+        let min = Vec2::new(bounds.left(), bounds.top()) + half_size;
+        let max = Vec2::new(bounds.right(), bounds.bottom()) - half_size;
+        // not `clamp`, which panics if rounding leaves `min` past `max` when the view is as big as
+        // the bounds
+        self.centre = self.centre.max(min).min(max);
+    }
+}
@@ -0,0 +1,319 @@
+//! A uniform grid over the bounding box, used to find each particle's neighbours without comparing
+//! it against every other particle.
+//!
+//! Cells are the size of the smoothing radius, so all of a particle's neighbours are within the
+//! 3x3 block of cells around it. The particles are sorted by cell with a counting sort, so each
+//! cell's particles are a contiguous slice.
+
+use std::cmp::Ordering;
+
+use glam::{IVec2, Vec2};
+
+use crate::rect::Rect;
+
+pub struct CellList {
+    cell_size: f32,
+    // the position of the first cell, and how many there are in each direction
+    origin: IVec2,
+    columns: i32,
+    rows: i32,
+    // where each cell's particles start in `particles`, with an extra entry for the end of the last
+    cell_starts: Vec<usize>,
+    // particle indices, sorted by cell
+    particles: Vec<usize>,
+}
+
+impl CellList {
+    pub fn new() -> CellList {
+        CellList {
+            cell_size: 1.0,
+            origin: IVec2::ZERO,
+            columns: 0,
+            rows: 0,
+            cell_starts: vec![0],
+            particles: vec![],
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// The cell containing the given position (which may be outside the grid)
+    pub fn cell_pos(&self, world_pos: Vec2) -> IVec2 {
+        (world_pos / self.cell_size).floor().as_ivec2()
+    }
+
+    /// The 3x3 block of cells around the one containing the given position
+    pub fn neighbour_cells(&self, world_pos: Vec2) -> [IVec2; 9] {
+        cells_around(self.cell_pos(world_pos))
+    }
+
+    /// The cell the given position is sorted into: the one containing it, or the nearest one if
+    /// it's outside the grid
+    fn nearest_cell(&self, world_pos: Vec2) -> IVec2 {
+        let last = self.origin + IVec2::new(self.columns - 1, self.rows - 1);
+        self.cell_pos(world_pos).min(last).max(self.origin)
+    }
+
+    fn cell_index(&self, cell_pos: IVec2) -> Option<usize> {
+        let pos = cell_pos - self.origin;
+        if pos.x < 0 || pos.y < 0 || pos.x >= self.columns || pos.y >= self.rows {
+            return None;
+        }
+
+        Some((pos.y * self.columns + pos.x) as usize)
+    }
+
+    /// Sorts the particles into cells covering the bounding box, particles outside of it are put
+    /// into the nearest cell
+    pub fn update(&mut self, bounding_box: Rect, cell_size: f32, positions: &[Vec2]) {
+        self.cell_size = cell_size;
+        self.origin = self.cell_pos(Vec2::new(bounding_box.left(), bounding_box.top()));
+        let last = self.cell_pos(Vec2::new(bounding_box.right(), bounding_box.bottom()));
+        self.columns = last.x - self.origin.x + 1;
+        self.rows = last.y - self.origin.y + 1;
+
+        let cell_indices = positions
+            .iter()
+            .map(|pos| self.cell_index(self.nearest_cell(*pos)).unwrap())
+            .collect::<Vec<_>>();
+
+        // count the particles in each cell, then turn that into where each cell starts
+        let cell_count = (self.columns * self.rows) as usize;
+        self.cell_starts.clear();
+        self.cell_starts.resize(cell_count + 1, 0);
+        for &cell in &cell_indices {
+            self.cell_starts[cell + 1] += 1;
+        }
+        for cell in 0..cell_count {
+            self.cell_starts[cell + 1] += self.cell_starts[cell];
+        }
+
+        // place each particle after those already placed in its cell
+        let mut next = self.cell_starts[..cell_count].to_vec();
+        self.particles.resize(positions.len(), 0);
+        for (idx, &cell) in cell_indices.iter().enumerate() {
+            self.particles[next[cell]] = idx;
+            next[cell] += 1;
+        }
+    }
+
+    /// Every particle's index, sorted by cell
+    pub fn sorted(&self) -> &[usize] {
+        &self.particles
+    }
+
+    /// The particles in the given cell
+    pub fn cell(&self, cell_pos: IVec2) -> &[usize] {
+        match self.cell_index(cell_pos) {
+            Some(cell) => &self.particles[self.cell_starts[cell]..self.cell_starts[cell + 1]],
+            None => &[],
+        }
+    }
+
+    /// The particles within a cell's size of the given position, where `positions` are the ones the
+    /// cells were last updated with. This only borrows, so it's cheap enough to call per particle
+    pub fn neighbours<'a>(
+        &'a self,
+        positions: &'a [Vec2],
+        world_pos: Vec2,
+    ) -> impl Iterator<Item = usize> + 'a {
+        // particles outside the grid are sorted into the nearest cell, so that's where to look
+        // around for positions outside of it too
+        let sqr_radius = self.cell_size * self.cell_size;
+        cells_around(self.nearest_cell(world_pos))
+            .into_iter()
+            .flat_map(move |cell_pos| self.cell(cell_pos))
+            .copied()
+            .filter(move |&idx| (positions[idx] - world_pos).length_squared() <= sqr_radius)
+    }
+
+    /// How many particles are past each edge of `rect`: left, right, top and bottom (those past a
+    /// corner count for both of its edges), where `positions` are the ones the cells were last
+    /// updated with. Whole cells on either side of an edge are counted without looking at their
+    /// particles, so only the particles in the cells an edge runs through are checked one by one
+    pub fn count_outside(&self, positions: &[Vec2], rect: Rect) -> [usize; 4] {
+        let first = self.origin;
+        let last = self.origin + IVec2::new(self.columns - 1, self.rows - 1);
+        let top_left = self.cell_pos(Vec2::new(rect.left(), rect.top()));
+        let bottom_right = self.cell_pos(Vec2::new(rect.right(), rect.bottom()));
+        let is_past = |edge, pos: Vec2| match edge {
+            0 => pos.x < rect.left(),
+            1 => pos.x > rect.right(),
+            2 => pos.y < rect.top(),
+            _ => pos.y > rect.bottom(),
+        };
+
+        let mut counts = [0; 4];
+        for y in first.y..=last.y {
+            for x in first.x..=last.x {
+                let particles = self.cell(IVec2::new(x, y));
+                if particles.is_empty() {
+                    continue;
+                }
+
+                // which side of each edge the cell's on, and which side is past it
+                let sides = [
+                    (side_of(x, top_left.x, first.x, last.x), Ordering::Less),
+                    (
+                        side_of(x, bottom_right.x, first.x, last.x),
+                        Ordering::Greater,
+                    ),
+                    (side_of(y, top_left.y, first.y, last.y), Ordering::Less),
+                    (
+                        side_of(y, bottom_right.y, first.y, last.y),
+                        Ordering::Greater,
+                    ),
+                ];
+                for (edge, (side, past)) in sides.into_iter().enumerate() {
+                    counts[edge] += match side {
+                        Ordering::Equal => particles
+                            .iter()
+                            .filter(|&&idx| is_past(edge, positions[idx]))
+                            .count(),
+                        side if side == past => particles.len(),
+                        _ => 0,
+                    };
+                }
+            }
+        }
+
+        counts
+    }
+}
+
+/// The 3x3 block of cells around the given one
+fn cells_around(center: IVec2) -> [IVec2; 9] {
+    const OFFSETS: [IVec2; 9] = [
+        IVec2::new(-1, -1),
+        IVec2::new(0, -1),
+        IVec2::new(1, -1),
+        IVec2::new(-1, 0),
+        IVec2::new(0, 0),
+        IVec2::new(1, 0),
+        IVec2::new(-1, 1),
+        IVec2::new(0, 1),
+        IVec2::new(1, 1),
+    ];
+
+    OFFSETS.map(|offset| center + offset)
+}
+
+/// Which side of the cell `edge` all the particles in the cell `cell` are on along one axis, where
+/// the grid runs from `first` to `last`, or `Equal` if they need checking one by one. The first and
+/// last cells also hold the particles past the grid on their side, so they're only ever all on the
+/// side the rest of the grid is
+fn side_of(cell: i32, edge: i32, first: i32, last: i32) -> Ordering {
+    match cell.cmp(&edge) {
+        Ordering::Less if cell != last => Ordering::Less,
+        Ordering::Greater if cell != first => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    /// Every position within `radius` of `world_pos`, by comparing against all of them
+    fn brute_force(positions: &[Vec2], world_pos: Vec2, radius: f32) -> Vec<usize> {
+        (0..positions.len())
+            .filter(|&idx| (positions[idx] - world_pos).length_squared() <= radius * radius)
+            .collect()
+    }
+
+    #[test]
+    fn neighbours_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let cases = [
+            (Rect::new(0.0, 0.0, 16.0, 9.0), 0.35),
+            (Rect::new(-2.3, -1.1, 5.0, 3.0), 0.5),
+            (Rect::new(0.0, 0.0, 1.0, 1.0), 1.0),
+        ];
+
+        for (bounding_box, cell_size) in cases {
+            // spread out past the bounding box on every side, by more than a few cells
+            let margin = cell_size * 4.0;
+            let mut random_pos = || {
+                Vec2::new(
+                    rng.gen_range(bounding_box.left() - margin..bounding_box.right() + margin),
+                    rng.gen_range(bounding_box.top() - margin..bounding_box.bottom() + margin),
+                )
+            };
+            let mut positions = (0..500).map(|_| random_pos()).collect::<Vec<_>>();
+            // and some exactly on the corners of cells, both inside and outside the bounding box
+            let cells = |start: f32, end: f32| {
+                ((start - margin) / cell_size).floor() as i32
+                    ..=((end + margin) / cell_size).ceil() as i32
+            };
+            for x in cells(bounding_box.left(), bounding_box.right()) {
+                for y in cells(bounding_box.top(), bounding_box.bottom()) {
+                    positions.push(IVec2::new(x, y).as_vec2() * cell_size);
+                }
+            }
+
+            let mut cell_list = CellList::new();
+            cell_list.update(bounding_box, cell_size, &positions);
+
+            let queries = positions
+                .clone()
+                .into_iter()
+                .chain((0..500).map(|_| random_pos()));
+            for world_pos in queries {
+                let mut neighbours = cell_list
+                    .neighbours(&positions, world_pos)
+                    .collect::<Vec<_>>();
+                neighbours.sort();
+                assert_eq!(
+                    neighbours,
+                    brute_force(&positions, world_pos, cell_size),
+                    "neighbours of {world_pos} in {bounding_box:?} with cells of {cell_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn count_outside_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let bounding_box = Rect::new(0.0, 0.0, 16.0, 9.0);
+        let cell_size = 0.7;
+        // some particles outside the bounding box too, which end up in the cells around its edges
+        let positions = (0..2000)
+            .map(|_| Vec2::new(rng.gen_range(-2.0..18.0), rng.gen_range(-2.0..11.0)))
+            .collect::<Vec<_>>();
+        let mut cell_list = CellList::new();
+        cell_list.update(bounding_box, cell_size, &positions);
+
+        let rects = [
+            bounding_box,
+            Rect::new(4.0, 2.25, 8.0, 4.5),
+            // on the cells' corners
+            Rect::new(1.4, 2.1, 7.0, 3.5),
+            // past the grid
+            Rect::new(-1.0, -1.0, 18.0, 11.0),
+            Rect::new(-5.0, 3.0, 4.0, 2.0),
+            Rect::new(17.0, 10.0, 1.0, 1.0),
+        ];
+        for rect in rects {
+            let count = |is_past: fn(Vec2, Rect) -> bool| {
+                positions.iter().filter(|pos| is_past(**pos, rect)).count()
+            };
+            let expected = [
+                count(|pos, rect| pos.x < rect.left()),
+                count(|pos, rect| pos.x > rect.right()),
+                count(|pos, rect| pos.y < rect.top()),
+                count(|pos, rect| pos.y > rect.bottom()),
+            ];
+            assert_eq!(
+                cell_list.count_outside(&positions, rect),
+                expected,
+                "particles outside {rect:?}"
+            );
+        }
+    }
+}
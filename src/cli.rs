@@ -1,7 +1,322 @@
-use clap::Parser;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Parser, Subcommand};
+use glam::Vec2;
+use winit::dpi::{LogicalSize, PhysicalPosition};
+
+use crate::colormap::Colormaps;
+use crate::config::Config;
+use crate::material::Behaviour;
+use crate::settings::{parse_vec2, Settings, Value};
+use crate::state::{Solver, State};
+use crate::window::WindowOptions;
 
 #[derive(Debug, Parser)]
 pub struct Cli {
     #[clap(short = 'V', long = "vsync")]
     pub vsync: bool,
+
+    /// Draw at most this many frames per second while vsync is off, rather than as many as possible
+    /// (cycle through caps with F)
+    #[clap(
+        long = "fps-cap",
+        value_name = "FPS",
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub fps_cap: Option<u32>,
+
+    /// Where to open the window, in physical pixels from the top left of the desktop, e.g.
+    /// `--position 100,50` (by default it's centred on its monitor)
+    #[clap(long = "position", value_name = "X,Y", value_parser = parse_position)]
+    pub position: Option<PhysicalPosition<i32>>,
+
+    /// The size of the window, in logical pixels, e.g. `--size 1280x720` (by default it's 800x450)
+    #[clap(long = "size", value_name = "WxH", value_parser = parse_size)]
+    pub size: Option<LogicalSize<u32>>,
+
+    /// Float the fluid over the desktop, with nothing else drawn (no background, text, overlays or
+    /// cursor) and no window decorations - needs a compositor that supports transparent windows
+    #[clap(long = "transparent")]
+    pub transparent: bool,
+
+    /// Keep the window above all others
+    #[clap(long = "always-on-top")]
+    pub always_on_top: bool,
+
+    /// Run as a live wallpaper, behind everything else on the desktop: the window fills its monitor,
+    /// only the fluid is drawn and input is ignored (only on X11)
+    #[clap(long = "wallpaper", conflicts_with = "screensaver")]
+    pub wallpaper: bool,
+
+    /// Run as a screensaver: fullscreen with only the fluid drawn, exiting on any key press, click,
+    /// scroll or mouse movement
+    #[clap(long = "screensaver")]
+    pub screensaver: bool,
+
+    /// Open in borderless fullscreen (toggle with F11 or Alt+Enter)
+    #[clap(long = "fullscreen")]
+    pub fullscreen: bool,
+
+    /// Which monitor to go fullscreen on, by its index in the system's list of monitors (by
+    /// default it's whichever one the window is on)
+    #[clap(long = "monitor", value_name = "INDEX")]
+    pub monitor: Option<usize>,
+
+    /// Open paused on the initial layout, so things can be set up (or stepped through a tick at a
+    /// time) before the simulation starts
+    #[clap(long = "start-paused")]
+    pub start_paused: bool,
+
+    /// Go into an attract mode after this many minutes without any input, where the fluid is stirred
+    /// up and its settings slowly drift until the next input puts them back
+    #[clap(long = "attract-after", value_name = "MINUTES", value_parser = parse_minutes)]
+    pub attract_after: Option<Duration>,
+
+    /// Keep the simulation running (more slowly) while the window isn't focused, rather than
+    /// pausing it - the same as `--set run_in_background=true`
+    #[clap(long = "run-in-background")]
+    pub run_in_background: bool,
+
+    /// The initial gravity vector, in world units per second squared (y points down)
+    #[clap(long = "gravity", value_parser = parse_vec2)]
+    pub gravity: Option<Vec2>,
+
+    /// How many times per second the simulation is updated
+    #[clap(long = "tick-rate", value_name = "HZ")]
+    pub tick_rate: Option<f32>,
+
+    /// Seed the random number generator, so the simulation (and any resets) can be repeated
+    #[clap(long = "seed")]
+    pub seed: Option<u64>,
+
+    /// How the fluid is kept at its target density: `sph` pushes particles apart with pressure
+    /// forces, and `pbf` moves them apart directly (position based fluids), which stays stable at
+    /// much lower tick rates. `flip` makes the particles' velocities divergence free on a grid
+    /// (a FLIP/PIC hybrid), which splashes more like a liquid. `grid` simulates the fluid on a grid
+    /// instead (Stam's stable fluids), and the particles just drift along with it
+    #[clap(long = "solver", value_enum, default_value_t = Solver::Sph)]
+    pub solver: Solver,
+
+    /// How the base fluid behaves: `liquid` flows, and `sand` piles up like a granular material
+    /// (materials from the config file each have their own `behaviour`)
+    #[clap(long = "material", value_enum, default_value_t = Behaviour::Liquid)]
+    pub material: Behaviour,
+
+    /// The colormap particles are colored with, either built-in (classic, viridis, inferno) or
+    /// from the config file
+    #[clap(long = "colormap")]
+    pub colormap: Option<String>,
+
+    /// Load settings and custom colormaps from a JSON config file
+    #[clap(long = "config")]
+    pub config: Option<PathBuf>,
+
+    /// Launch with the default settings and without any optional extras on the most conservative
+    /// GL path, for when a bad config or driver gets in the way. This ignores the config file
+    /// (settings and custom colormaps), `--set`, `--shader-dir` and `--attract-after`, and leaves
+    /// out MSAA, GL debug output, rain, bloom, trails and gamepads - other flags still apply
+    #[clap(long = "safe-mode")]
+    pub safe_mode: bool,
+
+    /// Where screenshots (taken with F12) are saved
+    #[clap(long = "screenshot-dir", value_name = "DIR", default_value = ".")]
+    pub screenshot_dir: PathBuf,
+
+    /// How many of the last seconds are saved as a GIF (with X)
+    #[clap(
+        long = "clip-length",
+        value_name = "SECONDS",
+        default_value = "5",
+        value_parser = parse_seconds
+    )]
+    pub clip_length: Duration,
+
+    /// Record the window to a video from launch (see F10), e.g. `--record out.mp4` - needs `ffmpeg`
+    #[clap(long = "record", value_name = "PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Read shaders from this directory (e.g. `src/renderer`) rather than using the built in ones,
+    /// and reload them whenever they change - any that aren't there are still built in
+    #[clap(long = "shader-dir", value_name = "DIR")]
+    pub shader_dir: Option<PathBuf>,
+
+    /// Override a setting, e.g. `--set tick_rate=60` (see the `settings` subcommand)
+    #[clap(long = "set", value_name = "NAME=VALUE")]
+    pub set: Vec<String>,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compare two saved state snapshots and print a summary of their differences
+    Diff { a: PathBuf, b: PathBuf },
+    /// List all available settings
+    Settings,
+    /// Run the simulation headlessly over a range of particle counts, tick rates and pressure
+    /// multipliers, and print which combinations stayed stable
+    Stability {
+        /// How many ticks each combination is run for
+        #[clap(long = "ticks", default_value_t = 300)]
+        ticks: u64,
+    },
+    /// Time the simulation headlessly, and print how long each tick (and each phase of a tick) took
+    /// on average, e.g. to compare builds with and without the `simd` feature
+    Bench {
+        /// How many ticks are timed, once the fluid's had a moment to settle
+        #[clap(long = "ticks", default_value_t = 600)]
+        ticks: u64,
+        /// How many particles there are (by default, as many as usual)
+        #[clap(long = "particles")]
+        particles: Option<usize>,
+    },
+    /// Run the simulation without showing it, drawing ticks to numbered PNGs (e.g. to be assembled
+    /// into a video)
+    Render {
+        /// How many ticks to run
+        #[clap(long = "ticks")]
+        ticks: u64,
+        /// Only draw every Nth tick, for a time-lapse of something slow (like the fluid settling)
+        #[clap(
+            long = "every",
+            value_name = "N",
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        every: u64,
+        /// The directory the frames are written to
+        #[clap(long = "out", value_name = "DIR", default_value = "frames")]
+        out: PathBuf,
+    },
+}
+
+impl Cli {
+    /// Loads the config file, if one was given (and this isn't safe mode)
+    pub fn config(&self) -> Result<Config> {
+        match &self.config {
+            Some(path) if self.safe_mode => {
+                tracing::warn!("safe mode: ignoring config in {}", path.display());
+                Ok(Config::default())
+            }
+            Some(path) => Config::load(path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// The directory to read shaders from, if one was given (and this isn't safe mode)
+    pub fn shader_dir(&self) -> Option<&PathBuf> {
+        match &self.shader_dir {
+            Some(dir) if self.safe_mode => {
+                tracing::warn!(
+                    "safe mode: using the built in shaders, not {}",
+                    dir.display()
+                );
+                None
+            }
+            dir => dir.as_ref(),
+        }
+    }
+
+    /// How long without any input until the attract mode starts, if it was asked for (and this
+    /// isn't safe mode)
+    pub fn attract_after(&self) -> Option<Duration> {
+        match self.attract_after {
+            Some(_) if self.safe_mode => {
+                tracing::warn!("safe mode: ignoring --attract-after");
+                None
+            }
+            attract_after => attract_after,
+        }
+    }
+
+    /// Builds the settings from the defaults, the config file and any overrides
+    pub fn settings(&self, config: &Config) -> Result<Settings> {
+        let mut settings = Settings::default();
+        if let Some(path) = self.config.as_ref().filter(|_| !self.safe_mode) {
+            settings
+                .restore(&config.settings)
+                .with_context(|| format!("invalid config in {}", path.display()))?;
+        }
+
+        if self.safe_mode && !self.set.is_empty() {
+            tracing::warn!("safe mode: ignoring --set {}", self.set.join(" --set "));
+        } else {
+            for assignment in &self.set {
+                settings.assign(assignment)?;
+            }
+        }
+
+        if let Some(gravity) = self.gravity {
+            settings.set("gravity", Value::Vec2(gravity))?;
+        }
+
+        if let Some(tick_rate) = self.tick_rate {
+            settings.set("tick_rate", Value::F32(tick_rate))?;
+        }
+
+        if self.run_in_background {
+            settings.set("run_in_background", Value::Bool(true))?;
+        }
+
+        if self.safe_mode {
+            settings.set("rain", Value::Bool(false))?;
+        }
+
+        Ok(settings)
+    }
+
+    /// How the window should be opened, by default at the size of the view
+    pub fn window_options(&self) -> WindowOptions {
+        WindowOptions {
+            size: self.size.unwrap_or(LogicalSize::new(
+                (State::VIEW.w * State::PIXELS_PER_UNIT) as u32,
+                (State::VIEW.h * State::PIXELS_PER_UNIT) as u32,
+            )),
+            position: self.position,
+            transparent: self.transparent,
+            always_on_top: self.always_on_top,
+            wallpaper: self.wallpaper,
+            safe_mode: self.safe_mode,
+        }
+    }
+
+    pub fn colormaps(&self, config: &Config) -> Result<Colormaps> {
+        Colormaps::new(config.colormaps()?, self.colormap.as_deref())
+    }
+}
+
+fn parse_seconds(s: &str) -> Result<Duration> {
+    let seconds: f32 = s.parse()?;
+    Duration::try_from_secs_f32(seconds)
+        .ok()
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| anyhow!("expected a positive number of seconds"))
+}
+
+fn parse_minutes(s: &str) -> Result<Duration> {
+    parse_seconds(s)
+        .map(|duration| duration * 60)
+        .map_err(|_| anyhow!("expected a positive number of minutes"))
+}
+
+fn parse_position(s: &str) -> Result<PhysicalPosition<i32>> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow!("expected a position in the form x,y"))?;
+    Ok(PhysicalPosition::new(x.trim().parse()?, y.trim().parse()?))
+}
+
+fn parse_size(s: &str) -> Result<LogicalSize<u32>> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or_else(|| anyhow!("expected a size in the form WxH"))?;
+    let (width, height): (u32, u32) = (width.trim().parse()?, height.trim().parse()?);
+    if width == 0 || height == 0 {
+        return Err(anyhow!("expected a size bigger than zero"));
+    }
+
+    Ok(LogicalSize::new(width, height))
 }
@@ -0,0 +1,89 @@
+//! Keeps the last few seconds of frames around, so they can be saved as a GIF after something
+//! interesting has already happened.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+
+use crate::gif;
+use crate::renderer::Image;
+
+pub struct Clip {
+    // downscaled frames, oldest first
+    frames: VecDeque<Image>,
+    last_capture: Option<Instant>,
+    // how much is kept, in seconds of captured frames (time spent paused doesn't count)
+    duration: Duration,
+}
+
+impl Clip {
+    /// How often a frame is captured, GIFs only have hundredths of a second to work with
+    const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+    /// The largest width frames are kept at
+    const MAX_WIDTH: u32 = 320;
+
+    pub fn new(duration: Duration) -> Clip {
+        Clip {
+            frames: VecDeque::new(),
+            last_capture: None,
+            duration,
+        }
+    }
+
+    /// Whether it's time to capture another frame
+    pub fn due(&self) -> bool {
+        self.last_capture
+            .is_none_or(|last| last.elapsed() >= Self::FRAME_INTERVAL)
+    }
+
+    pub fn push(&mut self, frame: &Image) {
+        self.last_capture = Some(Instant::now());
+
+        // start over if the window was resized, since every frame of a GIF is the same size
+        let frame = frame.downscale(Self::MAX_WIDTH);
+        if self
+            .frames
+            .back()
+            .is_some_and(|last| (last.width, last.height) != (frame.width, frame.height))
+        {
+            self.frames.clear();
+        }
+
+        let max_frames = (self.duration.as_millis() / Self::FRAME_INTERVAL.as_millis()).max(1);
+        while self.frames.len() as u128 >= max_frames {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    /// How many seconds of frames there are
+    pub fn seconds(&self) -> f32 {
+        self.frames.len() as f32 * Self::FRAME_INTERVAL.as_secs_f32()
+    }
+
+    /// Encodes the frames into a timestamped GIF in the current directory on another thread, and
+    /// returns where it'll be saved (if there are any frames)
+    pub fn save(&self) -> Result<Option<PathBuf>> {
+        if self.frames.is_empty() {
+            return Ok(None);
+        }
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let path = PathBuf::from(format!("plasma-pong-{timestamp}.gif"));
+
+        let frames = self.frames.iter().cloned().collect::<Vec<_>>();
+        let delay = (Self::FRAME_INTERVAL.as_millis() / 10) as u16;
+        thread::spawn({
+            let path = path.clone();
+            move || match gif::save(&path, &frames, delay) {
+                Ok(()) => tracing::info!("saved clip to {}", path.display()),
+                Err(e) => tracing::error!("failed to save clip: {e:#}"),
+            }
+        });
+
+        Ok(Some(path))
+    }
+}
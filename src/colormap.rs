@@ -0,0 +1,149 @@
+//! Gradients used to map values from 0 to 1 onto colors.
+
+use anyhow::{anyhow, bail, Context, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colormap {
+    pub name: String,
+    /// Evenly spaced colors, from 0 to 1
+    stops: Vec<[u8; 3]>,
+}
+
+impl Colormap {
+    /// How many colors a colormap is sampled into when it's uploaded
+    pub const RESOLUTION: usize = 256;
+
+    /// The colormaps that are always available, the first being the default
+    pub fn builtins() -> Vec<Colormap> {
+        let builtin = |name: &str, stops: &[u32]| Colormap {
+            name: name.to_string(),
+            stops: stops.iter().map(|rgb| split_rgb(*rgb)).collect(),
+        };
+
+        vec![
+            builtin("classic", &[0x00ff00, 0xff0000]),
+            builtin(
+                "viridis",
+                &[
+                    0x440154, 0x482878, 0x3e4989, 0x31688e, 0x26828e, 0x1f9e89, 0x35b779, 0x6ece58,
+                    0xb5de2b, 0xfde725,
+                ],
+            ),
+            builtin(
+                "inferno",
+                &[
+                    0x000004, 0x1b0c41, 0x4a0c6b, 0x781c6d, 0xa52c60, 0xcf4446, 0xed6925, 0xfb9b06,
+                    0xf7d13d, 0xfcffa4,
+                ],
+            ),
+            // diverging from blue through grey to red, for temperatures either side of 0
+            builtin(
+                "coolwarm",
+                &[0x3b4cc0, 0x8db0fe, 0xdddddd, 0xf49a7b, 0xb40426],
+            ),
+        ]
+    }
+
+    /// A gradient between colors in the form `#rrggbb`
+    pub fn from_hex(name: &str, colors: &[String]) -> Result<Colormap> {
+        if colors.len() < 2 {
+            bail!("colormap {name} needs at least two colors");
+        }
+
+        let stops = colors
+            .iter()
+            .map(|color| parse_hex(color))
+            .collect::<Result<_>>()
+            .with_context(|| format!("invalid color in colormap {name}"))?;
+
+        Ok(Colormap {
+            name: name.to_string(),
+            stops,
+        })
+    }
+
+    /// Linearly interpolates between the stops, `t` is clamped to 0..=1
+    pub fn sample(&self, t: f32) -> [u8; 3] {
+        let position = t.clamp(0.0, 1.0) * (self.stops.len() - 1) as f32;
+        let idx = (position as usize).min(self.stops.len() - 2);
+        let (a, b) = (self.stops[idx], self.stops[idx + 1]);
+        let t = position - idx as f32;
+        [0, 1, 2].map(|c| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * t).round() as u8)
+    }
+
+    /// The colormap sampled at `RESOLUTION` points, as RGBA
+    pub fn to_rgba(&self) -> Vec<u8> {
+        (0..Self::RESOLUTION)
+            .flat_map(|i| {
+                let [r, g, b] = self.sample(i as f32 / (Self::RESOLUTION - 1) as f32);
+                [r, g, b, 255]
+            })
+            .collect()
+    }
+}
+
+fn split_rgb(rgb: u32) -> [u8; 3] {
+    [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8]
+}
+
+/// Parses a color in the form `#rrggbb`
+pub fn parse_hex(color: &str) -> Result<[u8; 3]> {
+    let hex = color
+        .strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .ok_or_else(|| anyhow!("expected a color in the form #rrggbb, got {color}"))?;
+
+    Ok(split_rgb(u32::from_str_radix(hex, 16)?))
+}
+
+/// All available colormaps, and which one is in use
+pub struct Colormaps {
+    colormaps: Vec<Colormap>,
+    selected: usize,
+}
+
+impl Colormaps {
+    /// The built-in colormaps followed by any custom ones (which replace built-ins of the same
+    /// name), with the named one selected or the first if `None`
+    pub fn new(custom: Vec<Colormap>, selected: Option<&str>) -> Result<Colormaps> {
+        let mut colormaps = Colormap::builtins();
+        for colormap in custom {
+            match colormaps.iter_mut().find(|c| c.name == colormap.name) {
+                Some(existing) => *existing = colormap,
+                None => colormaps.push(colormap),
+            }
+        }
+
+        let selected = match selected {
+            Some(name) => colormaps
+                .iter()
+                .position(|c| c.name == name)
+                .ok_or_else(|| {
+                    let names = colormaps
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>();
+                    anyhow!(
+                        "unknown colormap: {name}, expected one of {}",
+                        names.join(", ")
+                    )
+                })?,
+            None => 0,
+        };
+
+        Ok(Colormaps {
+            colormaps,
+            selected,
+        })
+    }
+
+    pub fn current(&self) -> &Colormap {
+        &self.colormaps[self.selected]
+    }
+
+    /// Selects the next colormap, wrapping around to the first
+    pub fn next(&mut self) -> &Colormap {
+        self.selected = (self.selected + 1) % self.colormaps.len();
+        self.current()
+    }
+}
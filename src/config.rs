@@ -0,0 +1,80 @@
+//! The JSON config file: an object of setting names to values, along with any custom colormaps,
+//! what the HUD shows (see `hud.rs`), any force fields (see `force_field.rs`), the smoothing kernel
+//! (see `kernel.rs`), any materials besides the base fluid (see `material.rs`) and any heaters (see
+//! `heater.rs`).
+//!
+//! ```json
+//! {
+//!   "tick_rate": 60,
+//!   "gravity": [0, 9.8],
+//!   "colormaps": { "sunset": ["#2d1e3e", "#c0392b", "#f9d71c"] },
+//!   "hud": { "stats": ["fps", "particles", "density_error"], "corner": "top_right" }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::colormap::Colormap;
+use crate::force_field::ForceField;
+use crate::heater::Heater;
+use crate::hud::Hud;
+use crate::kernel::SmoothingKernel;
+use crate::material::Material;
+use crate::settings::Value;
+use crate::state::State;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    /// Custom colormaps, as names to lists of colors in the form `#rrggbb`
+    #[serde(default)]
+    colormaps: BTreeMap<String, Vec<String>>,
+    /// Which stats the HUD shows, and where
+    #[serde(default)]
+    pub hud: Hud,
+    /// Constant forces over regions of the world
+    #[serde(default)]
+    force_fields: Vec<ForceField>,
+    /// Which smoothing kernel the simulation uses
+    #[serde(default)]
+    kernel: SmoothingKernel,
+    /// Fluids besides the base one, which new particles can be made of
+    #[serde(default)]
+    materials: Vec<Material>,
+    /// Regions that warm up or cool down the particles in them
+    #[serde(default)]
+    heaters: Vec<Heater>,
+    /// Everything else is a setting
+    #[serde(flatten)]
+    pub settings: BTreeMap<String, Value>,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Config> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Sets up the parts of the simulation that aren't settings
+    pub fn apply(&self, state: &mut State) {
+        state.force_fields = self.force_fields.clone();
+        state.kernel = self.kernel;
+        state.materials = self.materials.clone();
+        state.heaters = self.heaters.clone();
+    }
+
+    pub fn colormaps(&self) -> Result<Vec<Colormap>> {
+        self.colormaps
+            .iter()
+            .map(|(name, colors)| Colormap::from_hex(name, colors))
+            .collect()
+    }
+}
@@ -0,0 +1,31 @@
+//! Stands in for `clip.rs` in builds without the `video` feature: nothing is ever captured, and
+//! saving a clip fails.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::renderer::Image;
+
+pub struct Clip;
+
+impl Clip {
+    pub fn new(_duration: Duration) -> Clip {
+        Clip
+    }
+
+    pub fn due(&self) -> bool {
+        false
+    }
+
+    pub fn push(&mut self, _frame: &Image) {}
+
+    pub fn seconds(&self) -> f32 {
+        0.0
+    }
+
+    pub fn save(&self) -> Result<Option<PathBuf>> {
+        bail!("clips need the `video` feature")
+    }
+}
@@ -0,0 +1,35 @@
+//! Stands in for `gamepad.rs` in builds without the `gamepad` feature: gamepads are never used.
+
+use glam::Vec2;
+
+use crate::engine::Interaction;
+use crate::rect::Rect;
+
+/// Can't be created, so none of its methods are ever called
+pub enum Gamepad {}
+
+impl Gamepad {
+    pub fn new() -> Option<Gamepad> {
+        None
+    }
+
+    pub fn connected(&self) -> bool {
+        match *self {}
+    }
+
+    pub fn cursor(&self) -> Option<Vec2> {
+        match *self {}
+    }
+
+    pub fn release(&mut self) {
+        match *self {}
+    }
+
+    pub fn update(&mut self, _delta_time: f32, _view: Rect) -> (bool, Vec<String>) {
+        match *self {}
+    }
+
+    pub fn interaction(&self) -> Option<Interaction> {
+        match *self {}
+    }
+}
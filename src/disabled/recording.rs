@@ -0,0 +1,29 @@
+//! Stands in for `recording.rs` in builds without the `video` feature: recordings can't be started.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use winit::dpi::PhysicalSize;
+
+use crate::renderer::Image;
+
+/// Can't be created, so none of its methods are ever called
+pub enum Recording {}
+
+impl Recording {
+    pub fn start(_path: PathBuf, _dimensions: PhysicalSize<u32>) -> Result<Recording> {
+        bail!("recording needs the `video` feature")
+    }
+
+    pub fn path(&self) -> &Path {
+        match *self {}
+    }
+
+    pub fn due(&self) -> bool {
+        match *self {}
+    }
+
+    pub fn push(&mut self, _frame: Image) -> Result<()> {
+        match *self {}
+    }
+}
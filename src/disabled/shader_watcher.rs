@@ -0,0 +1,19 @@
+//! Stands in for `renderer/shader_watcher.rs` in builds without the `shader-reload` feature: shaders
+//! can't be watched, so they're always built in.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+
+/// Can't be created, so none of its methods are ever called
+pub enum ShaderWatcher {}
+
+impl ShaderWatcher {
+    pub fn new(_dir: PathBuf, _on_change: impl Fn() + Send + 'static) -> Result<ShaderWatcher> {
+        bail!("watching shaders needs the `shader-reload` feature")
+    }
+
+    pub fn changed(&self) -> bool {
+        match *self {}
+    }
+}
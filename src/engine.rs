@@ -1,66 +1,334 @@
+use std::fs;
 use std::num::NonZeroU32;
-use std::time::Instant;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use glam::Vec2;
 use glutin::context::PossiblyCurrentContext;
 use glutin::prelude::*;
 use glutin::surface::{Surface, SwapInterval, WindowSurface};
 use glutin_winit::GlWindow;
-use winit::dpi::{LogicalSize, PhysicalPosition, PhysicalSize};
-use winit::event::{ElementState, Event, MouseButton, VirtualKeyCode, WindowEvent};
+use rand::thread_rng;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{
+    ElementState,
+    Event,
+    ModifiersState,
+    MouseButton,
+    MouseScrollDelta,
+    TouchPhase,
+    VirtualKeyCode,
+    WindowEvent,
+};
+use winit::window::Fullscreen;
 
+use crate::attract::Attract;
+use crate::body::Shape;
+use crate::camera::Camera;
 use crate::cli::Cli;
+use crate::clip::Clip;
+use crate::colormap::{Colormap, Colormaps};
+use crate::config::Config;
 use crate::fps::FpsCounter;
+use crate::gamepad::Gamepad;
+use crate::hud::Hud;
+use crate::recording::Recording;
 use crate::rect::Rect;
-use crate::renderer::Renderer;
+use crate::renderer::{ColorField, Image, ParticleStyle, Renderer, ShaderWatcher};
+use crate::settings::Settings;
+use crate::snapshot::{Snapshot, SNAPSHOT_EXTENSION};
 use crate::state::State;
+use crate::toast::Toast;
 use crate::window::create_window;
+use crate::window_motion::WindowMotion;
+use crate::{profiler, recent_logs};
 
+/// How much the interaction radius or strength is scaled by per line scrolled
+const SCROLL_SCALE_FACTOR: f32 = 1.1;
+
+/// How much the camera zooms in or out per line scrolled
+const ZOOM_SCALE_FACTOR: f32 = 1.2;
+
+/// How much the tick rate is scaled by with each key press
+const TICK_RATE_SCALE_FACTOR: f32 = 2.0;
+
+/// The frame rate caps cycled through with F, after which it's uncapped
+const FPS_CAPS: [u32; 4] = [30, 60, 120, 144];
+
+/// How far the cursor can move (in physical pixels) before a screensaver exits, so it isn't woken by
+/// a nudged desk
+const SCREENSAVER_CURSOR_SLACK: f64 = 10.0;
+
+/// The largest width of the thumbnails saved alongside snapshots
+const THUMBNAIL_WIDTH: u32 = 160;
+
+/// How far gravity is rotated with each key press
+const GRAVITY_ROTATION_STEP: f32 = std::f32::consts::FRAC_PI_4;
+
+/// Something pushing the fluid around, from the mouse, a finger, a gamepad and so on
+#[derive(Debug, Clone, Copy)]
 pub enum Interaction {
     Repel(Vec2),
     Suck(Vec2),
+    /// Drags particles along with the cursor: `(position, velocity)`
+    Drag(Vec2, Vec2),
+    /// Paints ink into the fluid, see `State::ink`
+    Paint(Vec2),
+    /// Spins particles clockwise around it, into a vortex
+    Swirl(Vec2),
+    /// Spawns particles of a material (as in `State::material`): `(position, material)`
+    Pour(Vec2, usize),
+}
+
+impl Interaction {
+    /// Where it is in world space
+    pub fn pos(&self) -> Vec2 {
+        match *self {
+            Interaction::Repel(pos)
+            | Interaction::Suck(pos)
+            | Interaction::Drag(pos, _)
+            | Interaction::Paint(pos)
+            | Interaction::Swirl(pos)
+            | Interaction::Pour(pos, _) => pos,
+        }
+    }
+}
+
+/// What the left mouse button (and touching the screen) does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    /// Pushes particles away
+    Push,
+    /// Paints ink into the fluid
+    Paint,
+    /// Spins particles around into a vortex
+    Swirl,
+    /// Pours in new particles, of the material picked with D
+    Pour,
+}
+
+impl Tool {
+    fn next(self) -> Tool {
+        match self {
+            Tool::Push => Tool::Paint,
+            Tool::Paint => Tool::Swirl,
+            Tool::Swirl => Tool::Pour,
+            Tool::Pour => Tool::Push,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Tool::Push => "push",
+            Tool::Paint => "paint",
+            Tool::Swirl => "swirl",
+            Tool::Pour => "pour",
+        }
+    }
+
+    fn interaction(self, pos: Vec2, material: usize) -> Interaction {
+        match self {
+            Tool::Push => Interaction::Repel(pos),
+            Tool::Paint => Interaction::Paint(pos),
+            Tool::Swirl => Interaction::Swirl(pos),
+            Tool::Pour => Interaction::Pour(pos, material),
+        }
+    }
+}
+
+/// Which debug overlays are shown
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Overlays {
+    /// The fluid's velocity, sampled on a coarse grid
+    pub velocity: bool,
+    /// The fluid's density, drawn as a heatmap behind the particles
+    pub density: bool,
+    /// The cells of the spatial lookup
+    pub grid: bool,
+    /// Tick marks at each world unit along the edges, and the cursor's coordinates
+    pub ruler: bool,
+    /// The tick count and a short checksum of the simulation, to compare with another instance
+    pub checksum: bool,
+    /// How long each phase of a tick and each render pass took over the last few seconds
+    pub profiler: bool,
 }
 
 pub struct EngineContext<'a> {
     pub surface_dimensions: PhysicalSize<u32>,
     pub scale_factor: f32,
     pub state: &'a State,
+    pub camera: Camera,
+    /// The cursor's position in world space, if it's within the window
+    pub cursor: Option<Vec2>,
+    /// Everything that's pushing the fluid around this frame
+    pub interactions: &'a [Interaction],
+    pub particle_style: ParticleStyle,
+    pub color_field: ColorField,
+    pub colormap: &'a Colormap,
+    pub overlays: Overlays,
+    /// The HUD, if it's shown
+    pub hud: Option<&'a Hud>,
+    /// Whether there's a gamepad connected
+    pub gamepad: bool,
+    /// Whether bright particles glow
+    pub bloom: bool,
+    /// Whether particles leave fading trails behind them
+    pub trails: bool,
+    /// Whether the window is see-through, with nothing drawn where there isn't any fluid
+    pub transparent: bool,
+    /// Whether only the fluid is drawn (no text, overlays or cursor), for transparent windows,
+    /// wallpapers and screensavers
+    pub fluid_only: bool,
+    /// A message to briefly show on screen
+    pub toast: Option<&'a str>,
     pub vsync: bool,
     pub fps: f32,
 }
 
+/// A finger on the screen
+struct TouchPoint {
+    id: u64,
+    position: PhysicalPosition<f64>,
+    // whether it's been lifted since the last frame, it's kept until then so even a quick tap acts
+    // on the fluid for a tick
+    lifted: bool,
+}
+
+/// What a frame that's read back is wanted for
+struct FrameUses {
+    screenshot: bool,
+    // the debug dump the frame is the screenshot for
+    debug_dump: Option<PathBuf>,
+    clip: bool,
+    recording: bool,
+}
+
+impl FrameUses {
+    fn any(&self) -> bool {
+        self.screenshot || self.debug_dump.is_some() || self.clip || self.recording
+    }
+}
+
 pub struct Engine;
 
 impl Engine {
-    pub fn run(args: Cli) -> ! {
-        let mut state = State::new();
+    pub fn run(args: Cli, settings: Settings, mut colormaps: Colormaps, config: Config) -> ! {
+        let mut state = State::new(settings, args.seed);
+        state.solver = args.solver;
+        state.base_behaviour = args.material;
+        config.apply(&mut state);
 
         // create window and setup gl context
         let (window, event_loop, gl_display, gl_surface, mut not_current_gl_context) =
-            create_window(LogicalSize::new(
-                (state.bounding_box.w * State::PIXELS_PER_UNIT) as u32,
-                (state.bounding_box.h * State::PIXELS_PER_UNIT) as u32,
-            ));
+            create_window(args.window_options());
 
         // engine state
         let mut time = Instant::now();
-        let mut paused = false;
+        let mut paused = args.start_paused;
+        // when starting paused, the window gaining focus shouldn't unpause it - only the user can
+        let mut held = args.start_paused;
         let mut fps_counter = FpsCounter::new();
         let mut surface_dimensions = window.inner_size();
         let mut cursor_pos = PhysicalPosition::default();
-        let mut cursor_button = MouseButton::Left;
-        let mut cursor_pressed = false;
+        // the mouse buttons that are held, in the order they were pressed
+        let mut cursor_buttons: Vec<MouseButton> = vec![];
+        let mut cursor_in_window = false;
+        let mut cursor_world_pos = None;
+        // the fingers on the screen, in the order they were put down
+        let mut touches: Vec<TouchPoint> = vec![];
+        let mut camera = Camera::new(State::VIEW);
+        let mut interactions = vec![];
+        let mut modifiers = ModifiersState::empty();
         let mut vsync = args.vsync;
+        let mut fps_cap = args.fps_cap;
+        // when the next frame should be drawn, if the frame rate's capped
+        let mut next_frame = Instant::now();
+        let mut window_motion = WindowMotion::new();
+        let mut particle_style = ParticleStyle::Dots;
+        let mut color_field = ColorField::Speed;
+        let mut overlays = Overlays::default();
+        let mut show_hud = true;
+        let mut bloom = false;
+        let mut trails = false;
+        // what left clicking does
+        let mut tool = Tool::Push;
+        let mut toast = args
+            .start_paused
+            .then(|| Toast::new("paused, press space to start"));
+        // the last few seconds of frames, to save when something interesting happens
+        let mut clip = Clip::new(args.clip_length);
+        // whether the next frame should be saved as a screenshot once it's drawn
+        let mut screenshot = false;
+        // the debug dump that's waiting on a screenshot of the next frame to be finished
+        let mut debug_dump: Option<PathBuf> = None;
+        // what the frame that's being read back is for
+        let mut reading_back: Option<FrameUses> = None;
+        // the video being recorded, and where the next one should be saved once a frame is drawn
+        // (since that's when the frame size is known)
+        let mut recording: Option<Recording> = None;
+        let mut start_recording = args.record.clone();
+        // the settings before each mutation, most recent last
+        let mut mutations = vec![];
+        let mut attract = args.attract_after().map(Attract::new);
+        // a wallpaper is only there to be looked at, and safe mode leaves gamepads out
+        let mut gamepad = (!args.wallpaper && !args.safe_mode)
+            .then(Gamepad::new)
+            .flatten();
+
+        // the monitor to go fullscreen on, otherwise it's whichever the window is on
+        let monitor = args.monitor.and_then(|index| {
+            let monitor = window.available_monitors().nth(index);
+            if monitor.is_none() {
+                tracing::warn!("there's no monitor {index}, using the current one");
+            }
+            monitor
+        });
+        if args.fullscreen || args.screensaver {
+            window.set_fullscreen(Some(Fullscreen::Borderless(monitor.clone())));
+        }
+        if args.screensaver {
+            window.set_cursor_visible(false);
+        }
+        // where the cursor was first seen, a screensaver exits once it's moved far enough from here
+        let mut screensaver_cursor: Option<PhysicalPosition<f64>> = None;
+
+        // reloads the renderer whenever its shaders change, if they're being read from a directory
+        let shader_watcher = args.shader_dir().cloned().and_then(|dir| {
+            // wake the event loop up so the change is seen, even while paused
+            let proxy = event_loop.create_proxy();
+            let on_change = move || {
+                // only fails once the event loop has exited, when it doesn't matter
+                let _ = proxy.send_event(());
+            };
+            match ShaderWatcher::new(dir, on_change) {
+                Ok(shader_watcher) => Some(shader_watcher),
+                Err(e) => {
+                    tracing::error!("failed to watch shaders: {e:#}");
+                    None
+                }
+            }
+        });
 
         // gl state
-        let mut gl_renderer = None;
+        let mut gl_renderer: Option<Renderer> = None;
         let mut gl_context = None;
 
         // surrender this thread to the window's event loop and run have it take over
         event_loop.run(move |event, _, control_flow| {
             // https://docs.rs/winit/latest/winit/index.html#event-handling
-            control_flow.set_poll();
+            if paused {
+                match &toast {
+                    // wake up again to hide the toast
+                    Some(toast) => control_flow.set_wait_until(toast.deadline()),
+                    None => control_flow.set_wait(),
+                }
+            } else if frame_interval(fps_cap, vsync, &state).is_some() {
+                // sleep until the next frame's due, rather than spinning
+                control_flow.set_wait_until(next_frame);
+            } else {
+                control_flow.set_poll();
+            }
 
             macro_rules! set_pause {
                 ($paused:expr) => {{
@@ -74,37 +342,612 @@ impl Engine {
                 }};
             }
 
+            // everything the renderer needs to draw a frame
+            macro_rules! engine_context {
+                () => {
+                    EngineContext {
+                        surface_dimensions,
+                        scale_factor: window.scale_factor() as f32,
+                        state: &state,
+                        camera,
+                        cursor: gamepad
+                            .as_ref()
+                            .and_then(Gamepad::cursor)
+                            .or(cursor_world_pos.filter(|_| cursor_in_window)),
+                        interactions: &interactions,
+                        particle_style,
+                        color_field,
+                        colormap: colormaps.current(),
+                        overlays,
+                        hud: show_hud.then_some(&config.hud),
+                        gamepad: gamepad.as_ref().is_some_and(Gamepad::connected),
+                        bloom,
+                        trails,
+                        transparent: args.transparent,
+                        fluid_only: args.transparent || args.wallpaper || args.screensaver,
+                        toast: toast.as_ref().map(Toast::message),
+                        vsync,
+                        fps: fps_counter.fps(),
+                    }
+                };
+            }
+
+            // any input stops the attract mode, putting the settings back
+            if let Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput { .. }
+                    | WindowEvent::MouseInput { .. }
+                    | WindowEvent::MouseWheel { .. }
+                    | WindowEvent::CursorMoved { .. }
+                    | WindowEvent::Touch(_)
+                    | WindowEvent::Focused(true),
+                ..
+            } = event
+            {
+                if let Some(attract) = attract.as_mut() {
+                    if attract.input(&mut state.settings) {
+                        toast = Some(Toast::new("welcome back"));
+                        window.request_redraw();
+                    }
+                }
+            }
+
             match event {
                 Event::LoopDestroyed => (),
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::CloseRequested => control_flow.set_exit(),
+                    // a screensaver exits on any input (except the cursor moving a tiny bit)
+                    WindowEvent::KeyboardInput { .. }
+                    | WindowEvent::MouseInput { .. }
+                    | WindowEvent::MouseWheel { .. }
+                    | WindowEvent::Touch(_)
+                        if args.screensaver =>
+                    {
+                        control_flow.set_exit()
+                    }
+                    WindowEvent::CursorMoved { position, .. } if args.screensaver => {
+                        let first = *screensaver_cursor.get_or_insert(position);
+                        let (dx, dy) = (position.x - first.x, position.y - first.y);
+                        if dx.hypot(dy) > SCREENSAVER_CURSOR_SLACK {
+                            control_flow.set_exit();
+                        }
+                    }
+                    // and a wallpaper is only there to be looked at
+                    WindowEvent::KeyboardInput { .. }
+                    | WindowEvent::MouseInput { .. }
+                    | WindowEvent::MouseWheel { .. }
+                    | WindowEvent::CursorMoved { .. }
+                    | WindowEvent::Touch(_)
+                        if args.wallpaper => {}
+                    // neither is ever focused, but they should still run
+                    WindowEvent::Focused(_) if args.wallpaper || args.screensaver => {}
                     WindowEvent::Focused(focused) => {
-                        set_pause!(!focused);
+                        // either pause, or carry on more slowly in the background
+                        state.background = !focused && state.settings.run_in_background;
+                        set_pause!((!focused && !state.background) || held);
                     }
                     WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
                         // close and exit when escape is pressed
                         Some(VirtualKeyCode::Escape) => control_flow.set_exit(),
                         // pause waveform render when space is pressed
                         Some(VirtualKeyCode::Space) if input.state == ElementState::Pressed => {
+                            held = false;
                             set_pause!(!paused);
                         }
+                        // toggle borderless fullscreen
+                        Some(key @ (VirtualKeyCode::F11 | VirtualKeyCode::Return))
+                            if input.state == ElementState::Pressed
+                                && (key == VirtualKeyCode::F11 || modifiers.alt()) =>
+                        {
+                            let fullscreen = match window.fullscreen() {
+                                Some(_) => None,
+                                None => Some(Fullscreen::Borderless(monitor.clone())),
+                            };
+                            window.set_fullscreen(fullscreen);
+                            // the window jumping to or from the corner of the screen isn't
+                            // something the particles should feel
+                            window_motion.reset();
+                        }
                         // toggle vsync
                         Some(VirtualKeyCode::V) if input.state == ElementState::Pressed => {
                             vsync = !vsync;
                             set_vsync(&gl_surface, gl_context.as_ref().unwrap(), vsync).unwrap();
                         }
+                        // cycle through frame rate caps
+                        Some(VirtualKeyCode::F) if input.state == ElementState::Pressed => {
+                            fps_cap = match fps_cap {
+                                None => Some(FPS_CAPS[0]),
+                                Some(cap) => FPS_CAPS.into_iter().find(|&next| next > cap),
+                            };
+                            let message = match fps_cap {
+                                Some(cap) if vsync => format!("fps cap: {cap} (once vsync is off)"),
+                                Some(cap) => format!("fps cap: {cap}"),
+                                None => "fps cap: off".to_string(),
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        // rotate, flip or toggle gravity
+                        Some(VirtualKeyCode::Left | VirtualKeyCode::J)
+                            if input.state == ElementState::Pressed =>
+                        {
+                            state.rotate_gravity(-GRAVITY_ROTATION_STEP);
+                        }
+                        Some(VirtualKeyCode::Right | VirtualKeyCode::L)
+                            if input.state == ElementState::Pressed =>
+                        {
+                            state.rotate_gravity(GRAVITY_ROTATION_STEP);
+                        }
+                        Some(VirtualKeyCode::Up | VirtualKeyCode::I)
+                            if input.state == ElementState::Pressed =>
+                        {
+                            state.flip_gravity();
+                        }
+                        Some(VirtualKeyCode::Down | VirtualKeyCode::K)
+                            if input.state == ElementState::Pressed =>
+                        {
+                            state.toggle_gravity();
+                        }
+                        // advance a single tick while paused
+                        Some(VirtualKeyCode::Period | VirtualKeyCode::N)
+                            if input.state == ElementState::Pressed && paused =>
+                        {
+                            state.step(&interactions);
+                            window.request_redraw();
+                        }
+                        // toggle trails behind the particles, or cycle how they're drawn
+                        Some(VirtualKeyCode::Tab)
+                            if input.state == ElementState::Pressed && modifiers.shift() =>
+                        {
+                            let supported = gl_renderer.as_ref().map(Renderer::supports_trails);
+                            let message = match supported {
+                                _ if args.safe_mode => "trails are off in safe mode",
+                                Some(false) => "trails aren't supported",
+                                _ => {
+                                    trails = !trails;
+                                    match trails {
+                                        true => "trails: on",
+                                        false => "trails: off",
+                                    }
+                                }
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        Some(VirtualKeyCode::Tab) if input.state == ElementState::Pressed => {
+                            particle_style = particle_style.next();
+                            window.request_redraw();
+                        }
+                        // cycle what particles are colored by, or the colormap while holding shift
+                        Some(VirtualKeyCode::C)
+                            if input.state == ElementState::Pressed && modifiers.shift() =>
+                        {
+                            let colormap = colormaps.next();
+                            toast = Some(Toast::new(format!("colormap: {}", colormap.name)));
+                            window.request_redraw();
+                        }
+                        Some(VirtualKeyCode::C) if input.state == ElementState::Pressed => {
+                            color_field = color_field.next();
+                            toast = Some(Toast::new(format!("coloring by {}", color_field.name())));
+                            window.request_redraw();
+                        }
+                        // toggle the velocity field overlay
+                        Some(VirtualKeyCode::F3) if input.state == ElementState::Pressed => {
+                            overlays.velocity = !overlays.velocity;
+                            window.request_redraw();
+                        }
+                        // toggle the ruler
+                        Some(VirtualKeyCode::F4) if input.state == ElementState::Pressed => {
+                            overlays.ruler = !overlays.ruler;
+                            window.request_redraw();
+                        }
+                        // toggle the profiler
+                        Some(VirtualKeyCode::F5) if input.state == ElementState::Pressed => {
+                            overlays.profiler = !overlays.profiler;
+                            profiler::set_enabled(overlays.profiler);
+                            window.request_redraw();
+                        }
+                        // toggle the checksum
+                        Some(VirtualKeyCode::F6) if input.state == ElementState::Pressed => {
+                            overlays.checksum = !overlays.checksum;
+                            window.request_redraw();
+                        }
+                        // toggle the spatial lookup's grid
+                        Some(VirtualKeyCode::G) if input.state == ElementState::Pressed => {
+                            overlays.grid = !overlays.grid;
+                            window.request_redraw();
+                        }
+                        // toggle the density heatmap
+                        Some(VirtualKeyCode::H) if input.state == ElementState::Pressed => {
+                            overlays.density = !overlays.density;
+                            window.request_redraw();
+                        }
+                        // toggle bloom
+                        Some(VirtualKeyCode::B) if input.state == ElementState::Pressed => {
+                            let message = match gl_renderer.as_ref().map(Renderer::supports_bloom) {
+                                _ if args.safe_mode => "bloom is off in safe mode",
+                                Some(false) => "bloom isn't supported",
+                                _ => {
+                                    bloom = !bloom;
+                                    match bloom {
+                                        true => "bloom: on",
+                                        false => "bloom: off",
+                                    }
+                                }
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        // toggle whether pushing and pulling preserves rotation, to compare them
+                        Some(VirtualKeyCode::T) if input.state == ElementState::Pressed => {
+                            state.settings.preserve_rotation = !state.settings.preserve_rotation;
+                            let preserved = match state.settings.preserve_rotation {
+                                true => "preserved",
+                                false => "damped",
+                            };
+                            toast = Some(Toast::new(format!("rotation: {preserved}")));
+                            window.request_redraw();
+                        }
+                        // toggle vorticity confinement
+                        Some(VirtualKeyCode::Q) if input.state == ElementState::Pressed => {
+                            let confinement = &mut state.settings.vorticity_confinement;
+                            *confinement = !*confinement;
+                            let on = if *confinement { "on" } else { "off" };
+                            toast = Some(Toast::new(format!("vorticity confinement: {on}")));
+                            window.request_redraw();
+                        }
+                        // toggle the force fields
+                        Some(VirtualKeyCode::E) if input.state == ElementState::Pressed => {
+                            let message = if state.force_fields.is_empty() {
+                                "there aren't any force fields in the config file".to_string()
+                            } else {
+                                state.settings.force_fields = !state.settings.force_fields;
+                                match state.settings.force_fields {
+                                    true => "force fields: on".to_string(),
+                                    false => "force fields: off".to_string(),
+                                }
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        // toggle rain
+                        Some(VirtualKeyCode::W) if input.state == ElementState::Pressed => {
+                            state.settings.rain = !state.settings.rain;
+                            let rain = if state.settings.rain { "on" } else { "off" };
+                            toast = Some(Toast::new(format!("rain: {rain}")));
+                            window.request_redraw();
+                        }
+                        // toggle goo, where neighbouring particles are joined by springs
+                        Some(VirtualKeyCode::Z) if input.state == ElementState::Pressed => {
+                            state.settings.goo = !state.settings.goo;
+                            let goo = if state.settings.goo { "on" } else { "off" };
+                            toast = Some(Toast::new(format!("goo: {goo}")));
+                            window.request_redraw();
+                        }
+                        // toggle heat, and color particles by their temperature while it's on
+                        Some(VirtualKeyCode::Y) if input.state == ElementState::Pressed => {
+                            state.settings.heat = !state.settings.heat;
+                            let message = if !state.settings.heat {
+                                "heat: off"
+                            } else if state.heaters.is_empty() {
+                                "heat: on, but there aren't any heaters in the config file"
+                            } else {
+                                "heat: on"
+                            };
+                            if state.settings.heat {
+                                color_field = ColorField::Temperature;
+                            }
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        // start an outbreak from a random particle, or stop it spreading with shift
+                        Some(VirtualKeyCode::O)
+                            if input.state == ElementState::Pressed && modifiers.shift() =>
+                        {
+                            state.settings.infection = false;
+                            toast = Some(Toast::new("infection stopped"));
+                            window.request_redraw();
+                        }
+                        Some(VirtualKeyCode::O) if input.state == ElementState::Pressed => {
+                            state.seed_infection();
+                            state.settings.infection = true;
+                            color_field = ColorField::Infection;
+                            toast = Some(Toast::new("outbreak!"));
+                            window.request_redraw();
+                        }
+                        // halve or double the vorticity confinement's strength with shift
+                        Some(key @ (VirtualKeyCode::LBracket | VirtualKeyCode::RBracket))
+                            if input.state == ElementState::Pressed && modifiers.shift() =>
+                        {
+                            let factor = match key {
+                                VirtualKeyCode::LBracket => 0.5,
+                                _ => 2.0,
+                            };
+                            state.settings.scale("vorticity_strength", factor).unwrap();
+                            toast = Some(Toast::new(format!(
+                                "vorticity strength: {}",
+                                state.settings.vorticity_strength
+                            )));
+                            window.request_redraw();
+                        }
+                        // halve or double the tick rate
+                        Some(key @ (VirtualKeyCode::LBracket | VirtualKeyCode::RBracket))
+                            if input.state == ElementState::Pressed =>
+                        {
+                            let factor = match key {
+                                VirtualKeyCode::LBracket => TICK_RATE_SCALE_FACTOR.recip(),
+                                _ => TICK_RATE_SCALE_FACTOR,
+                            };
+                            state.settings.scale("tick_rate", factor).unwrap();
+                            toast = Some(Toast::new(format!(
+                                "tick rate: {}Hz",
+                                state.settings.tick_rate
+                            )));
+                            window.request_redraw();
+                        }
+                        // cycle through what the left mouse button does: pushing particles away,
+                        // painting ink, swirling the fluid, or pouring more in
+                        Some(VirtualKeyCode::P) if input.state == ElementState::Pressed => {
+                            tool = tool.next();
+                            toast = Some(Toast::new(format!("tool: {}", tool.name())));
+                            window.request_redraw();
+                        }
+                        // cycle through the materials new particles are made of, from the base fluid
+                        // through those in the config file
+                        Some(VirtualKeyCode::D) if input.state == ElementState::Pressed => {
+                            let message = if state.materials.is_empty() {
+                                "there are no materials in the config file".to_string()
+                            } else {
+                                state.material = (state.material + 1) % (state.materials.len() + 1);
+                                format!("material: {}", state.material_name(state.material))
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        // pin whatever's pushing the fluid around in place, or with shift remove
+                        // all the pins
+                        Some(VirtualKeyCode::A) if input.state == ElementState::Pressed => {
+                            let message = if modifiers.shift() {
+                                state.pinned.clear();
+                                "removed all pins".to_string()
+                            } else if interactions.is_empty() {
+                                "hold a mouse button to pin what it's doing".to_string()
+                            } else {
+                                state.pinned.extend_from_slice(&interactions);
+                                format!("{} pinned, shift+A to remove them", state.pinned.len())
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        // drop a ball or a box into the fluid at the cursor (or in the middle of the
+                        // view, if it's not over the window), or remove all of them
+                        Some(key @ (VirtualKeyCode::Key1 | VirtualKeyCode::Key2))
+                            if input.state == ElementState::Pressed =>
+                        {
+                            let size = state.settings.body_size;
+                            let shape = match key {
+                                VirtualKeyCode::Key1 => Shape::Circle { radius: size },
+                                _ => Shape::Box {
+                                    half_size: Vec2::splat(size),
+                                },
+                            };
+                            let view = State::VIEW;
+                            let pos = cursor_world_pos
+                                .filter(|_| cursor_in_window)
+                                .unwrap_or(Vec2::new(view.x + view.w * 0.5, view.y + view.h * 0.5));
+                            state.add_body(shape, pos);
+                            window.request_redraw();
+                        }
+                        Some(VirtualKeyCode::Key0) if input.state == ElementState::Pressed => {
+                            state.bodies.clear();
+                            toast = Some(Toast::new("removed all bodies"));
+                            window.request_redraw();
+                        }
+                        // start again with the current settings
+                        Some(VirtualKeyCode::R) if input.state == ElementState::Pressed => {
+                            state.reset();
+                            window.request_redraw();
+                        }
+                        // randomly perturb the fluid's settings, or undo the last time that was done
+                        Some(VirtualKeyCode::M) if input.state == ElementState::Pressed => {
+                            let previous = state.settings.mutate(&mut thread_rng());
+                            let changes = previous
+                                .keys()
+                                .map(|name| {
+                                    let value = Settings::find(name).unwrap().get(&state.settings);
+                                    format!("{name}={value}")
+                                })
+                                .collect::<Vec<_>>();
+                            toast = Some(Toast::new(format!("mutated {}", changes.join(" "))));
+                            mutations.push(previous);
+                            window.request_redraw();
+                        }
+                        Some(VirtualKeyCode::U) if input.state == ElementState::Pressed => {
+                            toast = Some(Toast::new(match mutations.pop() {
+                                Some(previous) => {
+                                    state.settings.restore(&previous).unwrap();
+                                    "undid mutation"
+                                }
+                                None => "nothing to undo",
+                            }));
+                            window.request_redraw();
+                        }
+                        // show or hide the hud
+                        Some(VirtualKeyCode::F1) if input.state == ElementState::Pressed => {
+                            show_hud = !show_hud;
+                            toast = Some(Toast::new(format!("hud: {show_hud}")));
+                            window.request_redraw();
+                        }
+                        // save a screenshot of the next frame
+                        Some(VirtualKeyCode::F12) if input.state == ElementState::Pressed => {
+                            screenshot = true;
+                            window.request_redraw();
+                        }
+                        // save everything needed to report what's on screen right now
+                        Some(VirtualKeyCode::F9) if input.state == ElementState::Pressed => {
+                            match start_debug_dump(&state) {
+                                Ok(dir) => debug_dump = Some(dir),
+                                Err(e) => {
+                                    let message = format!("failed to save debug dump: {e:#}");
+                                    toast = Some(Toast::new(message));
+                                }
+                            }
+                            window.request_redraw();
+                        }
+                        // start or stop recording a video
+                        Some(VirtualKeyCode::F10) if input.state == ElementState::Pressed => {
+                            match recording.take() {
+                                Some(recording) => {
+                                    let path = recording.path().display();
+                                    toast = Some(Toast::new(format!("saving recording to {path}")));
+                                }
+                                None => {
+                                    let timestamp = SystemTime::now()
+                                        .duration_since(UNIX_EPOCH)
+                                        .unwrap()
+                                        .as_millis();
+                                    start_recording =
+                                        Some(PathBuf::from(format!("plasma-pong-{timestamp}.mp4")));
+                                }
+                            }
+                            window.request_redraw();
+                        }
+                        // save the last few seconds as a GIF
+                        Some(VirtualKeyCode::X) if input.state == ElementState::Pressed => {
+                            let message = match clip.save() {
+                                Ok(Some(path)) => format!(
+                                    "saving {:.1}s clip to {}",
+                                    clip.seconds(),
+                                    path.display()
+                                ),
+                                Ok(None) => "nothing to clip yet".to_string(),
+                                Err(e) => format!("failed to save clip: {e:#}"),
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        // save a snapshot of the current state, along with a thumbnail of it
+                        Some(VirtualKeyCode::S) if input.state == ElementState::Pressed => {
+                            let path = match Snapshot::from_state(&state).save_timestamped() {
+                                Ok(path) => path,
+                                Err(e) => {
+                                    tracing::error!("failed to save snapshot: {e:#}");
+                                    return;
+                                }
+                            };
+                            tracing::info!("saved snapshot to {}", path.display());
+
+                            if let Some(gl_renderer) = &mut gl_renderer {
+                                let ctx = engine_context!();
+                                let path = path.with_extension("png");
+                                match gl_renderer
+                                    .thumbnail(&ctx, THUMBNAIL_WIDTH)
+                                    .and_then(|image| image.save_png(&path))
+                                {
+                                    Ok(()) => {
+                                        tracing::info!("saved thumbnail to {}", path.display())
+                                    }
+                                    Err(e) => tracing::error!("failed to save thumbnail: {e:#}"),
+                                }
+                            }
+                        }
 
                         _ => {}
                     },
+                    WindowEvent::ModifiersChanged(state) => {
+                        modifiers = state;
+                    }
                     WindowEvent::CursorMoved { position, .. } => {
+                        // drag with the middle mouse button to pan the camera
+                        if cursor_buttons.contains(&MouseButton::Middle) {
+                            let delta = Vec2::new(
+                                (position.x - cursor_pos.x) as f32,
+                                (position.y - cursor_pos.y) as f32,
+                            );
+                            camera.pan(-camera.screen_len_to_world_len(surface_dimensions, delta));
+                        }
+
                         cursor_pos = position;
+                        // the mouse takes over from the gamepad's cursor
+                        if let Some(gamepad) = gamepad.as_mut() {
+                            gamepad.release();
+                        }
+                        // keep the cursor ring up to date
+                        if paused {
+                            window.request_redraw();
+                        }
+                    }
+                    WindowEvent::Touch(touch) => match touch.phase {
+                        TouchPhase::Started => touches.push(TouchPoint {
+                            id: touch.id,
+                            position: touch.location,
+                            lifted: false,
+                        }),
+                        phase => {
+                            if let Some(point) = touches.iter_mut().find(|p| p.id == touch.id) {
+                                point.position = touch.location;
+                                point.lifted = phase != TouchPhase::Moved;
+                            }
+                        }
+                    },
+                    WindowEvent::CursorEntered { .. } => {
+                        cursor_in_window = true;
+                    }
+                    WindowEvent::CursorLeft { .. } => {
+                        cursor_in_window = false;
+                    }
+                    // scroll to zoom the camera in or out around the cursor, or hold alt to change
+                    // the interaction radius or ctrl to change its strength
+                    WindowEvent::MouseWheel { delta, .. } => {
+                        let lines = match delta {
+                            MouseScrollDelta::LineDelta(_, y) => y,
+                            MouseScrollDelta::PixelDelta(pos) => {
+                                pos.y as f32
+                                    / (State::PIXELS_PER_UNIT * window.scale_factor() as f32)
+                            }
+                        };
+
+                        if modifiers.ctrl() || modifiers.alt() {
+                            let name = if modifiers.ctrl() {
+                                "interaction_strength"
+                            } else {
+                                "interaction_radius"
+                            };
+                            state
+                                .settings
+                                .scale(name, SCROLL_SCALE_FACTOR.powf(lines))
+                                .unwrap();
+                        } else {
+                            let pos = map_window_pos_to_world_pos(
+                                surface_dimensions,
+                                cursor_pos,
+                                &camera,
+                                state.bounding_box,
+                            );
+                            camera.zoom_at(pos, ZOOM_SCALE_FACTOR.powf(lines));
+                            window.request_redraw();
+                        }
                     }
                     WindowEvent::MouseInput { state, button, .. } => {
-                        cursor_pressed = matches!(state, ElementState::Pressed);
-                        cursor_button = button;
+                        cursor_buttons.retain(|held| *held != button);
+                        if state == ElementState::Pressed {
+                            cursor_buttons.push(button);
+                        }
+                    }
+                    // the surface is resized to match when it's next drawn
+                    WindowEvent::Resized(_) => window.request_redraw(),
+                    WindowEvent::Moved(position) => {
+                        let delta = window_motion.moved(position);
+                        state.shift_frame_velocity(map_window_len_to_world_len(
+                            surface_dimensions,
+                            delta,
+                            &camera,
+                        ));
                     }
                     _ => (),
                 },
+                // a shader has changed, see `shader_watcher`
+                Event::UserEvent(()) => window.request_redraw(),
                 Event::Resumed => {
                     gl_context = not_current_gl_context
                         .take()
@@ -115,32 +958,106 @@ impl Engine {
                     // configure the swap interval to not wait for vsync
                     set_vsync(&gl_surface, gl_context.as_ref().unwrap(), vsync).unwrap();
 
-                    gl_renderer = Some(Renderer::new(&gl_display, &window).unwrap());
+                    gl_renderer =
+                        Some(Renderer::new(&gl_display, &window, args.safe_mode).unwrap());
                 }
                 Event::MainEventsCleared => {
-                    if paused {
-                        return;
+                    // with the frame rate capped, nothing happens until the next frame's due (other
+                    // events can wake the loop up before then)
+                    let interval = frame_interval(fps_cap, vsync, &state);
+                    if let Some(interval) = interval.filter(|_| !paused) {
+                        let now = Instant::now();
+                        if now < next_frame {
+                            return;
+                        }
+                        // after falling behind, carry on from now rather than rushing to catch up
+                        next_frame = (next_frame + interval).max(now);
                     }
 
-                    // state update
                     let delta_time = time.elapsed().as_secs_f32();
                     time = Instant::now();
-                    state.update(
-                        delta_time,
-                        cursor_pressed.then(|| {
-                            let pos = map_window_pos_to_world_pos(
-                                surface_dimensions,
-                                cursor_pos,
-                                state.bounding_box,
-                            );
-                            match cursor_button {
-                                MouseButton::Right => Interaction::Suck(pos),
-                                _ => Interaction::Repel(pos),
-                            }
-                        }),
+
+                    // track the cursor's velocity in world space for dragging
+                    let pos = map_window_pos_to_world_pos(
+                        surface_dimensions,
+                        cursor_pos,
+                        &camera,
+                        state.bounding_box,
                     );
+                    let cursor_velocity = match cursor_world_pos.replace(pos) {
+                        Some(prev_pos) if delta_time > 0.0 => (pos - prev_pos) / delta_time,
+                        _ => Vec2::ZERO,
+                    };
+
+                    interactions.clear();
+                    for button in &cursor_buttons {
+                        interactions.push(match button {
+                            MouseButton::Left if modifiers.shift() => {
+                                Interaction::Drag(pos, cursor_velocity)
+                            }
+                            MouseButton::Right => Interaction::Suck(pos),
+                            // it pans the camera instead, see `WindowEvent::CursorMoved`
+                            MouseButton::Middle => continue,
+                            _ => tool.interaction(pos, state.material),
+                        });
+                    }
+
+                    // touches do whatever the left mouse button does
+                    for touch in &touches {
+                        let pos = map_window_pos_to_world_pos(
+                            surface_dimensions,
+                            touch.position,
+                            &camera,
+                            state.bounding_box,
+                        );
+                        interactions.push(tool.interaction(pos, state.material));
+                    }
+                    touches.retain(|touch| !touch.lifted);
+
+                    if let Some(gamepad) = gamepad.as_mut() {
+                        let (input, messages) = gamepad.update(delta_time, camera.view());
+                        if input && args.screensaver {
+                            control_flow.set_exit();
+                        }
+                        if let Some(attract) = attract.as_mut().filter(|_| input) {
+                            if attract.input(&mut state.settings) {
+                                toast = Some(Toast::new("welcome back"));
+                            }
+                        }
+                        if let Some(message) = messages.into_iter().last() {
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+                        interactions.extend(gamepad.interaction());
+                    }
+
+                    if toast.as_ref().is_some_and(Toast::expired) {
+                        toast = None;
+                        window.request_redraw();
+                    }
 
-                    // render
+                    if paused {
+                        return;
+                    }
+
+                    if let Some(attract) = attract.as_mut() {
+                        if attract.update(&mut state.settings) {
+                            toast = Some(Toast::new("attract mode, any input to stop"));
+                        }
+                    }
+
+                    // state update
+                    let delta = window_motion.settle();
+                    state.shift_frame_velocity(map_window_len_to_world_len(
+                        surface_dimensions,
+                        delta,
+                        &camera,
+                    ));
+                    state.update(delta_time, &interactions);
+
+                    window.request_redraw();
+                }
+                Event::RedrawRequested(_) => {
                     if let (Some(gl_context), Some(gl_renderer)) = (&gl_context, &mut gl_renderer) {
                         let window_size = window.inner_size();
                         if surface_dimensions != window_size {
@@ -156,13 +1073,91 @@ impl Engine {
                             }
                         }
 
-                        gl_renderer.draw(EngineContext {
-                            surface_dimensions,
-                            scale_factor: window.scale_factor() as f32,
-                            state: &state,
-                            vsync,
-                            fps: fps_counter.fps(),
-                        });
+                        // keep using the old shaders if the new ones don't compile (the old
+                        // renderer's gl objects aren't freed, but this is only for development)
+                        if shader_watcher.as_ref().is_some_and(ShaderWatcher::changed) {
+                            let message = match Renderer::new(&gl_display, &window, args.safe_mode)
+                            {
+                                Ok(reloaded) => {
+                                    *gl_renderer = reloaded;
+                                    "reloaded shaders".to_string()
+                                }
+                                Err(e) => format!("failed to reload shaders: {e:#}"),
+                            };
+                            toast = Some(Toast::new(message));
+                        }
+
+                        gl_renderer.draw(engine_context!());
+                        let has_size =
+                            surface_dimensions.width > 0 && surface_dimensions.height > 0;
+                        if let Some(path) = start_recording.take_if(|_| has_size) {
+                            let message = match Recording::start(path, surface_dimensions) {
+                                Ok(started) => {
+                                    let message =
+                                        format!("recording to {}", started.path().display());
+                                    recording = Some(started);
+                                    message
+                                }
+                                Err(e) => format!("failed to start recording: {e:#}"),
+                            };
+                            toast = Some(Toast::new(message));
+                            window.request_redraw();
+                        }
+
+                        // frames are read back a frame late, so drawing doesn't wait for them:
+                        // this one's started now, and the one before it is finished
+                        let in_flight = reading_back.as_ref();
+                        let uses = FrameUses {
+                            screenshot: std::mem::take(&mut screenshot),
+                            debug_dump: debug_dump.take(),
+                            // but not if there's already a frame on its way to them
+                            clip: has_size
+                                && clip.due()
+                                && !in_flight.is_some_and(|uses| uses.clip),
+                            recording: has_size
+                                && recording.as_ref().is_some_and(Recording::due)
+                                && !in_flight.is_some_and(|uses| uses.recording),
+                        };
+                        let image = if uses.any() {
+                            gl_renderer.read_frame(surface_dimensions)
+                        } else {
+                            gl_renderer.finish_read_frame()
+                        };
+                        let finished =
+                            std::mem::replace(&mut reading_back, uses.any().then_some(uses));
+                        if let (Some(image), Some(uses)) = (image, finished) {
+                            if uses.screenshot {
+                                let message = match save_screenshot(&image, &args.screenshot_dir) {
+                                    Ok(path) => format!("saved screenshot to {}", path.display()),
+                                    Err(e) => format!("failed to save screenshot: {e:#}"),
+                                };
+                                toast = Some(Toast::new(message));
+                            }
+                            if let Some(dir) = uses.debug_dump {
+                                let message = match image.save_png(dir.join("screenshot.png")) {
+                                    Ok(()) => format!("saved debug dump to {}", dir.display()),
+                                    Err(e) => {
+                                        format!("failed to save debug dump screenshot: {e:#}")
+                                    }
+                                };
+                                toast = Some(Toast::new(message));
+                            }
+                            if uses.clip {
+                                clip.push(&image);
+                            }
+                            if let Some(active) = recording.as_mut().filter(|_| uses.recording) {
+                                if let Err(e) = active.push(image) {
+                                    let message = format!("recording stopped: {e:#}");
+                                    toast = Some(Toast::new(message));
+                                    recording = None;
+                                }
+                            }
+                            window.request_redraw();
+                        }
+                        // even if nothing else would be drawn, so the frame's finished
+                        if reading_back.is_some() {
+                            window.request_redraw();
+                        }
                         gl_surface.swap_buffers(gl_context).unwrap();
                     }
 
@@ -196,15 +1191,71 @@ fn set_vsync(
     Ok(())
 }
 
+/// How long to wait between frames, if the frame rate's capped: by `fps_cap` (unless vsync already
+/// limits it), or while the simulation's running in the background
+fn frame_interval(fps_cap: Option<u32>, vsync: bool, state: &State) -> Option<Duration> {
+    let fps = match state.background {
+        true => state.settings.background_fps,
+        false => fps_cap.filter(|_| !vsync)? as f32,
+    };
+    Some(Duration::from_secs_f64(1.0 / fps as f64))
+}
+
+/// Saves the image as a timestamped PNG in the given directory, creating it if need be
+fn save_screenshot(image: &Image, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let path = dir.join(format!("plasma-pong-{timestamp}.png"));
+    image.save_png(&path)?;
+    Ok(path)
+}
+
+/// Saves the state, settings and recent logs into a new folder (named for when it was saved and the
+/// current tick) for a screenshot to be added to once the next frame's drawn
+fn start_debug_dump(state: &State) -> Result<PathBuf> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let dir = PathBuf::from(format!(
+        "plasma-pong-debug-{timestamp}-tick-{}",
+        state.ticks
+    ));
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    Snapshot::from_state(state).save(dir.join(format!("state.{SNAPSHOT_EXTENSION}")))?;
+
+    // in the same form as a config file, so it can be loaded with `--config`
+    let path = dir.join("settings.json");
+    let settings = serde_json::to_string_pretty(&state.settings.to_map())?;
+    fs::write(&path, settings).with_context(|| format!("failed to write {}", path.display()))?;
+
+    let path = dir.join("log.txt");
+    let mut logs = recent_logs::lines().join("\n");
+    logs.push('\n');
+    fs::write(&path, logs).with_context(|| format!("failed to write {}", path.display()))?;
+
+    tracing::info!("saving debug dump to {}", dir.display());
+    Ok(dir)
+}
+
+/// The world position under a point in the window, as seen through the camera, clamped to the
+/// bounding box
 fn map_window_pos_to_world_pos(
     window_size: PhysicalSize<u32>,
     window_position: PhysicalPosition<f64>,
+    camera: &Camera,
     bounding_box: Rect,
 ) -> Vec2 {
+    let window_position = Vec2::new(window_position.x as f32, window_position.y as f32);
+    let pos = camera.screen_to_world(window_size, window_position);
     Vec2::new(
-        (bounding_box.x + (window_position.x as f32 / window_size.width as f32) * bounding_box.w)
-            .clamp(bounding_box.left(), bounding_box.right()),
-        (bounding_box.y + (window_position.y as f32 / window_size.height as f32) * bounding_box.h)
-            .clamp(bounding_box.top(), bounding_box.bottom()),
+        pos.x.clamp(bounding_box.left(), bounding_box.right()),
+        pos.y.clamp(bounding_box.top(), bounding_box.bottom()),
     )
 }
+
+fn map_window_len_to_world_len(
+    window_size: PhysicalSize<u32>,
+    window_len: Vec2,
+    camera: &Camera,
+) -> Vec2 {
+    camera.screen_len_to_world_len(window_size, window_len)
+}
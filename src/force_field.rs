@@ -0,0 +1,25 @@
+//! Constant forces over regions of the world, like wind blowing across the top half of the window,
+//! set up by the `force_fields` section of the config file and toggled with E.
+//!
+//! ```json
+//! {
+//!   "force_fields": [
+//!     { "region": { "x": 0, "y": 0, "w": 16, "h": 4.5 }, "force": [6, 0] },
+//!     { "region": { "x": 0, "y": 4.5, "w": 16, "h": 4.5 }, "force": [-6, 0] }
+//!   ]
+//! }
+//! ```
+
+use glam::Vec2;
+use serde::Deserialize;
+
+use crate::rect::Rect;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ForceField {
+    /// Where it pushes particles, in world units
+    pub region: Rect,
+    /// How hard it pushes them, in world units per second squared (y points down)
+    pub force: Vec2,
+}
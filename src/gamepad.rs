@@ -0,0 +1,104 @@
+//! Playing with a gamepad: the left stick moves a cursor around the window, the right trigger pushes
+//! particles away from it and the left trigger sucks them in.
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+use glam::Vec2;
+
+use crate::engine::Interaction;
+use crate::rect::Rect;
+
+pub struct Gamepad {
+    gilrs: Gilrs,
+    // the cursor's position in world space, once the stick's been moved
+    cursor: Option<Vec2>,
+    // whether the cursor is shown, it's hidden again once the mouse is moved
+    in_use: bool,
+}
+
+impl Gamepad {
+    /// How far the cursor moves per second with the stick all the way over, as a fraction of the
+    /// width of the view (so it's just as easy to aim when zoomed in)
+    const CURSOR_SPEED: f32 = 0.75;
+
+    /// Starts listening for gamepads, or returns `None` if they can't be used on this system
+    pub fn new() -> Option<Gamepad> {
+        match Gilrs::new() {
+            Ok(gilrs) => Some(Gamepad {
+                gilrs,
+                cursor: None,
+                in_use: false,
+            }),
+            Err(e) => {
+                tracing::warn!("gamepads aren't available: {e}");
+                None
+            }
+        }
+    }
+
+    /// Whether there's a gamepad connected
+    pub fn connected(&self) -> bool {
+        self.gilrs.gamepads().next().is_some()
+    }
+
+    /// The cursor's position in world space, while it's the gamepad that's being used
+    pub fn cursor(&self) -> Option<Vec2> {
+        self.cursor.filter(|_| self.in_use)
+    }
+
+    /// Hides the cursor until the gamepad's used again, for when the mouse takes over
+    pub fn release(&mut self) {
+        self.in_use = false;
+    }
+
+    /// Handles the gamepads' events, and moves the cursor (staying within `view`) by however far
+    /// the stick's pushed. Returns whether there was any input, and the messages to show for any
+    /// gamepads that were connected or disconnected
+    pub fn update(&mut self, delta_time: f32, view: Rect) -> (bool, Vec<String>) {
+        let mut input = false;
+        let mut messages = vec![];
+        while let Some(event) = self.gilrs.next_event() {
+            let name = self.gilrs.gamepad(event.id).name().to_string();
+            match event.event {
+                EventType::Connected => messages.push(format!("{name} connected")),
+                EventType::Disconnected => messages.push(format!("{name} disconnected")),
+                EventType::Dropped | EventType::ForceFeedbackEffectCompleted => {}
+                _ => input = true,
+            }
+        }
+
+        let mut stick = Vec2::ZERO;
+        for (_, gamepad) in self.gilrs.gamepads() {
+            // the stick's y axis points up, and the world's points down
+            stick.x += gamepad.value(Axis::LeftStickX);
+            stick.y -= gamepad.value(Axis::LeftStickY);
+        }
+        let stick = stick.clamp_length_max(1.0);
+
+        // start in the middle of the view, rather than wherever the cursor was last left
+        let centre = Vec2::new(view.x + view.w * 0.5, view.y + view.h * 0.5);
+        let cursor = self.cursor.get_or_insert(centre);
+        *cursor += stick * view.w * Self::CURSOR_SPEED * delta_time;
+        *cursor = cursor
+            .max(Vec2::new(view.left(), view.top()))
+            .min(Vec2::new(view.right(), view.bottom()));
+
+        if input || stick != Vec2::ZERO {
+            self.in_use = true;
+        }
+
+        (input, messages)
+    }
+
+    /// What the triggers are doing at the cursor, if either is held
+    pub fn interaction(&self) -> Option<Interaction> {
+        let pos = self.cursor()?;
+        let held = |button| self.gilrs.gamepads().any(|(_, pad)| pad.is_pressed(button));
+        if held(Button::RightTrigger2) {
+            Some(Interaction::Repel(pos))
+        } else if held(Button::LeftTrigger2) {
+            Some(Interaction::Suck(pos))
+        } else {
+            None
+        }
+    }
+}
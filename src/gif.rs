@@ -0,0 +1,181 @@
+//! A small animated GIF encoder, good enough for short clips.
+//!
+//! Every frame shares a fixed palette with 3 bits each of red and green and 2 of blue, which is
+//! crude but fast, and doesn't need the frames to be analysed up front.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::renderer::Image;
+
+/// The clear code when each pixel is an 8 bit index, followed by the end of information code
+const CLEAR_CODE: u16 = 256;
+const END_CODE: u16 = 257;
+/// Codes are at most 12 bits, so the table can't grow past this
+const MAX_CODES: u16 = 4096;
+
+/// Saves the frames (which must all be the same size) as a looping GIF, showing each for `delay`
+/// hundredths of a second
+pub fn save(path: impl AsRef<Path>, frames: &[Image], delay: u16) -> Result<()> {
+    let path = path.as_ref();
+    let Some(first) = frames.first() else {
+        bail!("there are no frames to save");
+    };
+    let (Ok(width), Ok(height)) = (u16::try_from(first.width), u16::try_from(first.height)) else {
+        bail!("{}x{} is too large for a gif", first.width, first.height);
+    };
+
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+
+    // header and logical screen descriptor, with a global color table of 256 colors
+    out.write_all(b"GIF89a")?;
+    out.write_all(&width.to_le_bytes())?;
+    out.write_all(&height.to_le_bytes())?;
+    out.write_all(&[0xf7, 0, 0])?;
+    for idx in 0..=255u8 {
+        let [r, g, b] = palette_color(idx);
+        out.write_all(&[r, g, b])?;
+    }
+
+    // loop forever
+    out.write_all(&[0x21, 0xff, 0x0b])?;
+    out.write_all(b"NETSCAPE2.0")?;
+    out.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+    for frame in frames {
+        if (frame.width, frame.height) != (first.width, first.height) {
+            bail!("all frames must be {}x{}", first.width, first.height);
+        }
+
+        // graphic control extension, for the frame's delay
+        out.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        out.write_all(&delay.to_le_bytes())?;
+        out.write_all(&[0x00, 0x00])?;
+
+        // image descriptor covering the whole screen, using the global color table
+        out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+        out.write_all(&[0x00])?;
+
+        let indices = frame
+            .pixels
+            .chunks_exact(4)
+            .map(|pixel| palette_index([pixel[0], pixel[1], pixel[2]]))
+            .collect::<Vec<_>>();
+
+        // the image data, split into blocks of at most 255 bytes
+        out.write_all(&[8])?;
+        for block in lzw_encode(&indices).chunks(255) {
+            out.write_all(&[block.len() as u8])?;
+            out.write_all(block)?;
+        }
+        out.write_all(&[0])?;
+    }
+
+    out.write_all(&[0x3b])?;
+    out.flush()
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn palette_index([r, g, b]: [u8; 3]) -> u8 {
+    (r & 0xe0) | ((g & 0xe0) >> 3) | (b >> 6)
+}
+
+fn palette_color(idx: u8) -> [u8; 3] {
+    let r = (idx >> 5) & 0x7;
+    let g = (idx >> 2) & 0x7;
+    let b = idx & 0x3;
+    [
+        (r as u32 * 255 / 7) as u8,
+        (g as u32 * 255 / 7) as u8,
+        (b as u32 * 255 / 3) as u8,
+    ]
+}
+
+/// Packs variable width codes into bytes, least significant bit first
+struct CodeWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    bit_count: u32,
+    code_size: u32,
+}
+
+impl CodeWriter {
+    fn write(&mut self, code: u16) {
+        self.bits |= (code as u32) << self.bit_count;
+        self.bit_count += self.code_size;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.bytes.push(self.bits as u8);
+        }
+
+        self.bytes
+    }
+}
+
+/// Compresses 8 bit palette indices with GIF's flavour of LZW
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let mut writer = CodeWriter {
+        bytes: vec![],
+        bits: 0,
+        bit_count: 0,
+        code_size: 9,
+    };
+    // maps a code followed by an index to the code for both
+    let mut table = std::collections::HashMap::new();
+    let mut next_code = END_CODE + 1;
+
+    writer.write(CLEAR_CODE);
+
+    let Some((&first, rest)) = indices.split_first() else {
+        writer.write(END_CODE);
+        return writer.finish();
+    };
+
+    let mut prefix = first as u16;
+    for &idx in rest {
+        if let Some(&code) = table.get(&(prefix, idx)) {
+            prefix = code;
+            continue;
+        }
+
+        writer.write(prefix);
+        // the decoder widens its codes once the next one won't fit
+        if next_code >= 1 << writer.code_size && writer.code_size < 12 {
+            writer.code_size += 1;
+        }
+
+        if next_code < MAX_CODES {
+            table.insert((prefix, idx), next_code);
+            next_code += 1;
+        } else {
+            // the table is full, so start over
+            writer.write(CLEAR_CODE);
+            table.clear();
+            next_code = END_CODE + 1;
+            writer.code_size = 9;
+        }
+
+        prefix = idx as u16;
+    }
+
+    writer.write(prefix);
+    if next_code >= 1 << writer.code_size && writer.code_size < 12 {
+        writer.code_size += 1;
+    }
+    writer.write(END_CODE);
+    writer.finish()
+}
@@ -0,0 +1,185 @@
+//! A fluid simulated on a grid rather than with particles, after Jos Stam's "Stable Fluids": each
+//! tick its velocities are spread out by viscosity, carried along by themselves, and corrected so
+//! that as much fluid flows out of every cell as flows in.
+
+use glam::Vec2;
+
+use crate::ink::Ink;
+use crate::rect::Rect;
+
+/// A grid of velocities over the bounding box
+pub struct Grid {
+    bounding_box: Rect,
+    columns: usize,
+    rows: usize,
+    // the fluid's velocity at the middle of each cell, row by row starting from the top left
+    velocities: Vec<Vec2>,
+    // the pressure that was last solved for, which the next solve starts from so it needs fewer
+    // iterations to settle
+    pressures: Vec<f32>,
+}
+
+impl Grid {
+    /// The size of each cell, in world units: the same as the ink's, so the ink can be carried
+    /// along by the velocities as they are
+    pub const CELL_SIZE: f32 = Ink::CELL_SIZE;
+
+    /// How many Gauss-Seidel iterations viscosity and pressure are solved with: more is more
+    /// accurate, but slower
+    const SOLVER_ITERATIONS: usize = 20;
+
+    pub fn new(bounding_box: Rect) -> Grid {
+        let columns = (bounding_box.w / Self::CELL_SIZE).ceil() as usize;
+        let rows = (bounding_box.h / Self::CELL_SIZE).ceil() as usize;
+        Grid {
+            bounding_box,
+            columns,
+            rows,
+            velocities: vec![Vec2::ZERO; columns * rows],
+            pressures: vec![0.0; columns * rows],
+        }
+    }
+
+    /// The fluid's velocity in every cell, in the same order as the ink's cells
+    pub fn velocities(&self) -> &[Vec2] {
+        &self.velocities
+    }
+
+    pub fn velocities_mut(&mut self) -> &mut [Vec2] {
+        &mut self.velocities
+    }
+
+    pub fn clear(&mut self) {
+        self.velocities.fill(Vec2::ZERO);
+        self.pressures.fill(0.0);
+    }
+
+    /// The world position of the middle of each cell, in the same order as `velocities`
+    pub fn cell_centres(&self) -> impl Iterator<Item = Vec2> + '_ {
+        (0..self.rows).flat_map(move |row| {
+            (0..self.columns).map(move |column| {
+                Vec2::new(
+                    self.bounding_box.x + (column as f32 + 0.5) * Self::CELL_SIZE,
+                    self.bounding_box.y + (row as f32 + 0.5) * Self::CELL_SIZE,
+                )
+            })
+        })
+    }
+
+    /// The fluid's velocity at the given position, interpolated between the nearest cells
+    pub fn sample(&self, pos: Vec2) -> Vec2 {
+        let max = Vec2::new(self.columns as f32 - 1.0, self.rows as f32 - 1.0);
+        let origin = Vec2::new(self.bounding_box.x, self.bounding_box.y);
+        let grid_pos = ((pos - origin) / Self::CELL_SIZE - 0.5).clamp(Vec2::ZERO, max);
+
+        let (column, row) = (grid_pos.x as usize, grid_pos.y as usize);
+        let (next_column, next_row) = (
+            (column + 1).min(self.columns - 1),
+            (row + 1).min(self.rows - 1),
+        );
+        let t = grid_pos.fract();
+
+        let cell = |column: usize, row: usize| self.velocities[row * self.columns + column];
+        let top = cell(column, row).lerp(cell(next_column, row), t.x);
+        let bottom = cell(column, next_row).lerp(cell(next_column, next_row), t.x);
+        top.lerp(bottom, t.y)
+    }
+
+    /// Advances the fluid by `delta_time`, with the given viscosity (in world units squared per
+    /// second)
+    pub fn step(&mut self, delta_time: f32, viscosity: f32) {
+        if viscosity > 0.0 {
+            self.diffuse(viscosity * delta_time / (Self::CELL_SIZE * Self::CELL_SIZE));
+            self.project();
+        }
+
+        self.advect(delta_time);
+        self.project();
+    }
+
+    /// Spreads each cell's velocity out into its neighbours. This is solved implicitly (for the
+    /// velocities that would spread back out to the current ones), so it's stable however large
+    /// `rate` is
+    fn diffuse(&mut self, rate: f32) {
+        let initial = self.velocities.clone();
+        for _ in 0..Self::SOLVER_ITERATIONS {
+            for (idx, initial) in initial.iter().enumerate() {
+                let neighbours = self.neighbour_velocities(idx).into_iter().sum::<Vec2>();
+                self.velocities[idx] = (*initial + neighbours * rate) / (1.0 + 4.0 * rate);
+            }
+        }
+    }
+
+    /// Moves the velocities along with themselves: each cell takes whatever velocity was where its
+    /// contents have just come from
+    fn advect(&mut self, delta_time: f32) {
+        self.velocities = self
+            .velocities
+            .iter()
+            .zip(self.cell_centres())
+            .map(|(velocity, centre)| self.sample(centre - *velocity * delta_time))
+            .collect();
+    }
+
+    /// Removes whatever part of the velocities would pile fluid up in some cells and drain it from
+    /// others, by solving for the pressure that would push it back out and subtracting its gradient
+    fn project(&mut self) {
+        let h = Self::CELL_SIZE;
+        let divergences = (0..self.velocities.len())
+            .map(|idx| {
+                let [left, right, up, down] = self.neighbour_velocities(idx);
+                (right.x - left.x + down.y - up.y) / (2.0 * h)
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..Self::SOLVER_ITERATIONS {
+            for (idx, divergence) in divergences.iter().enumerate() {
+                // nothing flows through the walls, so cells past them are left out
+                let (sum, count) = self
+                    .neighbours(idx)
+                    .into_iter()
+                    .flatten()
+                    .fold((0.0, 0.0), |(sum, count), other_idx| {
+                        (sum + self.pressures[other_idx], count + 1.0)
+                    });
+                self.pressures[idx] = (sum - divergence * h * h) / count;
+            }
+        }
+
+        for idx in 0..self.velocities.len() {
+            let [left, right, up, down] = self
+                .neighbours(idx)
+                .map(|other_idx| self.pressures[other_idx.unwrap_or(idx)]);
+            self.velocities[idx] -= Vec2::new(right - left, down - up) / (2.0 * h);
+        }
+    }
+
+    /// The indices of the cells to the left, right, above and below the given one, or `None` for
+    /// any that would be past a wall
+    fn neighbours(&self, idx: usize) -> [Option<usize>; 4] {
+        let (column, row) = (idx % self.columns, idx / self.columns);
+        [
+            (column > 0).then(|| idx - 1),
+            (column + 1 < self.columns).then(|| idx + 1),
+            (row > 0).then(|| idx - self.columns),
+            (row + 1 < self.rows).then(|| idx + self.columns),
+        ]
+    }
+
+    /// The velocities to the left, right, above and below the given cell. Past a wall, it's the
+    /// cell's own velocity reflected off the wall, so the fluid slides along it but not through it
+    fn neighbour_velocities(&self, idx: usize) -> [Vec2; 4] {
+        let velocity = self.velocities[idx];
+        let flips = [
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(-1.0, 1.0),
+            Vec2::new(1.0, -1.0),
+            Vec2::new(1.0, -1.0),
+        ];
+        let neighbours = self.neighbours(idx);
+        std::array::from_fn(|i| match neighbours[i] {
+            Some(other_idx) => self.velocities[other_idx],
+            None => velocity * flips[i],
+        })
+    }
+}
@@ -0,0 +1,28 @@
+//! Regions of the world that warm up or cool down the particles in them, like a hot plate along the
+//! bottom of the window and a cold one along the top, set up by the `heaters` section of the config
+//! file. Warm fluid rises and cool fluid sinks, so between them they keep convection cells turning
+//! while heat is simulated (toggled with Y).
+//!
+//! ```json
+//! {
+//!   "gravity": [0, 9.8],
+//!   "heaters": [
+//!     { "region": { "x": 0, "y": 8, "w": 16, "h": 1 }, "temperature": 1 },
+//!     { "region": { "x": 0, "y": 0, "w": 16, "h": 1 }, "temperature": -1 }
+//!   ]
+//! }
+//! ```
+
+use serde::Deserialize;
+
+use crate::rect::Rect;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Heater {
+    /// Where it heats particles, in world units
+    pub region: Rect,
+    /// The temperature it brings them to: above 0 is warmer than the rest of the fluid starts at,
+    /// and below 0 is cooler
+    pub temperature: f32,
+}
@@ -0,0 +1,120 @@
+//! The HUD: a line of stats in a corner of the window, set up by the `hud` section of the config
+//! file.
+//!
+//! ```json
+//! {
+//!   "hud": { "stats": ["fps", "frame_time", "particles"], "corner": "bottom_right" }
+//! }
+//! ```
+
+use serde::Deserialize;
+
+use crate::engine::EngineContext;
+use crate::renderer::direction_glyph;
+
+/// Something the HUD can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    Fps,
+    /// How long each frame takes, on average
+    FrameTime,
+    Vsync,
+    /// How many particles there are
+    Particles,
+    TickRate,
+    /// Which way gravity points
+    Gravity,
+    /// The interaction's radius
+    Radius,
+    /// The interaction's strength
+    Strength,
+    /// How far the particles' densities are from the target density, on average
+    DensityError,
+    /// The pressure multiplier in effect, which is capped at low tick rates
+    Pressure,
+}
+
+impl Stat {
+    fn text(self, ctx: &EngineContext) -> String {
+        let settings = &ctx.state.settings;
+        match self {
+            Stat::Fps => format!("FPS: {:.2}", ctx.fps),
+            Stat::FrameTime => format!("FRAME: {:.2}ms", 1000.0 / ctx.fps),
+            Stat::Vsync => format!("VSYNC: {}", ctx.vsync),
+            Stat::Particles => format!("PARTICLES: {}", ctx.state.positions.len()),
+            Stat::TickRate => format!("TICK RATE: {}Hz", settings.tick_rate),
+            Stat::Gravity => format!("GRAVITY: {}", direction_glyph(settings.gravity)),
+            Stat::Radius => format!("RADIUS: {:.2}", settings.interaction_radius),
+            Stat::Strength => format!("STRENGTH: {:.2}", settings.interaction_strength),
+            Stat::DensityError => {
+                format!("DENSITY ERROR: {:.1}%", ctx.state.density_error() * 100.0)
+            }
+            Stat::Pressure => format!("PRESSURE: {:.1}", ctx.state.effective_pressure_multiplier()),
+        }
+    }
+}
+
+/// Which corner of the window the HUD is in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Corner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    pub fn is_right(self) -> bool {
+        matches!(self, Corner::TopRight | Corner::BottomRight)
+    }
+
+    pub fn is_bottom(self) -> bool {
+        matches!(self, Corner::BottomLeft | Corner::BottomRight)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hud {
+    /// What's shown, in order
+    pub stats: Vec<Stat>,
+    pub corner: Corner,
+}
+
+impl Default for Hud {
+    fn default() -> Hud {
+        Hud {
+            stats: vec![
+                Stat::Fps,
+                Stat::Vsync,
+                Stat::Gravity,
+                Stat::Radius,
+                Stat::Strength,
+            ],
+            corner: Corner::TopLeft,
+        }
+    }
+}
+
+impl Hud {
+    /// The stats as a single line, whether the pressure multiplier is being capped (so it's clear
+    /// why raising it doesn't do anything), and whether there's a gamepad connected
+    pub fn text(&self, ctx: &EngineContext) -> String {
+        let mut text = self
+            .stats
+            .iter()
+            .map(|stat| stat.text(ctx))
+            .collect::<Vec<_>>();
+        let pressure_multiplier = ctx.state.effective_pressure_multiplier();
+        if pressure_multiplier < ctx.state.settings.pressure_multiplier {
+            text.push(format!("PRESSURE CAPPED: {pressure_multiplier:.1}"));
+        }
+        if ctx.gamepad {
+            text.push("GAMEPAD".to_string());
+        }
+        text.join(" ")
+    }
+}
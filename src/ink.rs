@@ -0,0 +1,128 @@
+//! Dye painted into the fluid and carried along by it, so its motion can be seen even where the
+//! particles are evenly spread out.
+
+use glam::{Vec2, Vec3};
+
+use crate::rect::Rect;
+
+/// A coarse grid of RGB ink over the bounding box
+pub struct Ink {
+    bounding_box: Rect,
+    columns: usize,
+    rows: usize,
+    // row by row, starting from the top left
+    cells: Vec<Vec3>,
+    // whether any cell has enough ink in it to see, so an empty grid costs nothing to update
+    visible: bool,
+}
+
+impl Ink {
+    /// The size of each cell, in world units
+    pub const CELL_SIZE: f32 = 0.25;
+
+    /// Any less ink than this in every channel can't be seen once drawn
+    const VISIBLE_THRESHOLD: f32 = 1.0 / 255.0;
+
+    pub fn new(bounding_box: Rect) -> Ink {
+        let columns = (bounding_box.w / Self::CELL_SIZE).ceil() as usize;
+        let rows = (bounding_box.h / Self::CELL_SIZE).ceil() as usize;
+        Ink {
+            bounding_box,
+            columns,
+            rows,
+            cells: vec![Vec3::ZERO; columns * rows],
+            visible: false,
+        }
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The amount of each color in every cell, row by row from the top left
+    pub fn cells(&self) -> &[Vec3] {
+        &self.cells
+    }
+
+    /// Whether there's any ink to be seen
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(Vec3::ZERO);
+        self.visible = false;
+    }
+
+    /// The world position of the middle of each cell, in the same order as `cells`
+    pub fn cell_centres(&self) -> impl Iterator<Item = Vec2> + '_ {
+        (0..self.rows).flat_map(move |row| {
+            (0..self.columns).map(move |column| self.cell_centre(column as f32, row as f32))
+        })
+    }
+
+    fn cell_centre(&self, column: f32, row: f32) -> Vec2 {
+        Vec2::new(
+            self.bounding_box.x + (column + 0.5) * Self::CELL_SIZE,
+            self.bounding_box.y + (row + 0.5) * Self::CELL_SIZE,
+        )
+    }
+
+    /// Adds `amount` of the color to the cells within `radius` of the position, with less added
+    /// further out. Each channel is capped at 1
+    pub fn paint(&mut self, pos: Vec2, radius: f32, color: Vec3, amount: f32) {
+        for idx in 0..self.cells.len() {
+            let centre = self.cell_centre((idx % self.columns) as f32, (idx / self.columns) as f32);
+            let dist = (centre - pos).length();
+            if dist < radius {
+                let cell = &mut self.cells[idx];
+                *cell = (*cell + color * amount * (1.0 - dist / radius)).min(Vec3::ONE);
+            }
+        }
+
+        self.visible = true;
+    }
+
+    /// Moves the ink along with the given velocities (one for each cell, see `cell_centres`) and
+    /// fades it by `fade`, the fraction of it left afterwards
+    pub fn advect(&mut self, velocities: &[Vec2], delta_time: f32, fade: f32) {
+        if !self.visible {
+            return;
+        }
+
+        // each cell takes whatever ink was where its contents have just come from
+        let cells = velocities
+            .iter()
+            .zip(self.cell_centres())
+            .map(|(velocity, centre)| self.sample(centre - *velocity * delta_time) * fade)
+            .collect::<Vec<_>>();
+
+        self.visible = cells
+            .iter()
+            .any(|cell| cell.max_element() >= Self::VISIBLE_THRESHOLD);
+        self.cells = cells;
+    }
+
+    /// The ink at the given position, interpolated between the nearest cells
+    fn sample(&self, pos: Vec2) -> Vec3 {
+        let max = Vec2::new(self.columns as f32 - 1.0, self.rows as f32 - 1.0);
+        let origin = Vec2::new(self.bounding_box.x, self.bounding_box.y);
+        let grid_pos = ((pos - origin) / Self::CELL_SIZE - 0.5).clamp(Vec2::ZERO, max);
+
+        let (column, row) = (grid_pos.x as usize, grid_pos.y as usize);
+        let (next_column, next_row) = (
+            (column + 1).min(self.columns - 1),
+            (row + 1).min(self.rows - 1),
+        );
+        let t = grid_pos.fract();
+
+        let cell = |column: usize, row: usize| self.cells[row * self.columns + column];
+        let top = cell(column, row).lerp(cell(next_column, row), t.x);
+        let bottom = cell(column, next_row).lerp(cell(next_column, next_row), t.x);
+        top.lerp(bottom, t.y)
+    }
+}
@@ -0,0 +1,491 @@
+//! Smoothing kernels: how much each neighbour counts for at a given distance, out to the smoothing
+//! radius. Each is normalised in 2D, so it integrates to 1 over the circle it covers.
+//!
+//! Which one's used is picked with `kernel` in the config file, e.g. `{ "kernel": "cubic_spline" }`.
+//!
+//! With the `simd` feature, each kernel can also be evaluated at four distances at once, which is
+//! how the density and pressure loops go through a particle's neighbours (see `ValueSum` and
+//! `GradientSum`).
+
+use std::f32::consts::PI;
+
+#[cfg(feature = "simd")]
+use glam::{Vec2, Vec4};
+use serde::Deserialize;
+
+pub trait Kernel {
+    /// The kernel's value at `dist` from its centre, which is 0 from `radius` onwards
+    fn value(&self, dist: f32, radius: f32) -> f32;
+
+    /// How quickly the value changes with distance (so it's negative while it falls off)
+    fn derivative(&self, dist: f32, radius: f32) -> f32;
+
+    /// `value` at four distances at once
+    #[cfg(feature = "simd")]
+    fn values(&self, dists: Vec4, radius: f32) -> Vec4;
+
+    /// `derivative` at four distances at once
+    #[cfg(feature = "simd")]
+    fn derivatives(&self, dists: Vec4, radius: f32) -> Vec4;
+}
+
+/// `(r - d)^2`: soft, with a sharp peak at the centre
+pub struct Quadratic;
+
+impl Kernel for Quadratic {
+    fn value(&self, dist: f32, radius: f32) -> f32 {
+        if dist >= radius {
+            return 0.0;
+        }
+
+        let volume = (PI * radius.powi(4)) / 6.0;
+        (radius - dist) * (radius - dist) / volume
+    }
+
+    fn derivative(&self, dist: f32, radius: f32) -> f32 {
+        if dist >= radius {
+            return 0.0;
+        }
+
+        let scale = 12.0 / (radius.powi(4) * PI);
+        (dist - radius) * scale
+    }
+
+    // the wide versions clamp at the radius rather than branching, so they're 0 past it all the same
+
+    #[cfg(feature = "simd")]
+    fn values(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let volume = (PI * radius.powi(4)) / 6.0;
+        let t = (radius - dists).max(Vec4::ZERO);
+        t * t / volume
+    }
+
+    #[cfg(feature = "simd")]
+    fn derivatives(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let scale = 12.0 / (radius.powi(4) * PI);
+        (dists - radius).min(Vec4::ZERO) * scale
+    }
+}
+
+/// `(r^2 - d^2)^3`: smooth and flat at the centre, so close neighbours all count about the same
+pub struct Poly6;
+
+impl Kernel for Poly6 {
+    fn value(&self, dist: f32, radius: f32) -> f32 {
+        if dist >= radius {
+            return 0.0;
+        }
+
+        let volume = PI * radius.powi(8) / 4.0;
+        (radius * radius - dist * dist).powi(3) / volume
+    }
+
+    fn derivative(&self, dist: f32, radius: f32) -> f32 {
+        if dist >= radius {
+            return 0.0;
+        }
+
+        let scale = -24.0 / (PI * radius.powi(8));
+        scale * dist * (radius * radius - dist * dist).powi(2)
+    }
+
+    #[cfg(feature = "simd")]
+    fn values(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let volume = PI * radius.powi(8) / 4.0;
+        let t = (radius * radius - dists * dists).max(Vec4::ZERO);
+        t * t * t / volume
+    }
+
+    #[cfg(feature = "simd")]
+    fn derivatives(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let scale = -24.0 / (PI * radius.powi(8));
+        let t = (radius * radius - dists * dists).max(Vec4::ZERO);
+        scale * dists * t * t
+    }
+}
+
+/// `(r - d)^3`: its slope doesn't vanish at the centre, so particles that get too close are still
+/// pushed apart rather than clumping
+pub struct Spiky;
+
+impl Kernel for Spiky {
+    fn value(&self, dist: f32, radius: f32) -> f32 {
+        if dist >= radius {
+            return 0.0;
+        }
+
+        let volume = PI * radius.powi(5) / 10.0;
+        (radius - dist).powi(3) / volume
+    }
+
+    fn derivative(&self, dist: f32, radius: f32) -> f32 {
+        if dist >= radius {
+            return 0.0;
+        }
+
+        let scale = -30.0 / (PI * radius.powi(5));
+        scale * (radius - dist).powi(2)
+    }
+
+    #[cfg(feature = "simd")]
+    fn values(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let volume = PI * radius.powi(5) / 10.0;
+        let t = (radius - dists).max(Vec4::ZERO);
+        t * t * t / volume
+    }
+
+    #[cfg(feature = "simd")]
+    fn derivatives(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let scale = -30.0 / (PI * radius.powi(5));
+        let t = (radius - dists).max(Vec4::ZERO);
+        scale * t * t
+    }
+}
+
+/// The cubic B-spline (Monaghan's M4): bell shaped, and the usual choice in SPH literature
+pub struct CubicSpline;
+
+impl Kernel for CubicSpline {
+    fn value(&self, dist: f32, radius: f32) -> f32 {
+        // the spline's written in terms of half the radius
+        let h = radius * 0.5;
+        let q = dist / h;
+        let scale = 10.0 / (7.0 * PI * h * h);
+        match q {
+            q if q < 1.0 => scale * (1.0 - 1.5 * q * q + 0.75 * q * q * q),
+            q if q < 2.0 => scale * 0.25 * (2.0 - q).powi(3),
+            _ => 0.0,
+        }
+    }
+
+    fn derivative(&self, dist: f32, radius: f32) -> f32 {
+        let h = radius * 0.5;
+        let q = dist / h;
+        let scale = 10.0 / (7.0 * PI * h * h * h);
+        match q {
+            q if q < 1.0 => scale * (-3.0 * q + 2.25 * q * q),
+            q if q < 2.0 => scale * -0.75 * (2.0 - q).powi(2),
+            _ => 0.0,
+        }
+    }
+
+    // both pieces are worked out for every lane, and each lane picks its own
+
+    #[cfg(feature = "simd")]
+    fn values(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let h = radius * 0.5;
+        let q = dists / h;
+        let scale = 10.0 / (7.0 * PI * h * h);
+        let inner = scale * (1.0 - 1.5 * q * q + 0.75 * q * q * q);
+        let t = (2.0 - q).max(Vec4::ZERO);
+        let outer = scale * 0.25 * t * t * t;
+        Vec4::select(q.cmplt(Vec4::ONE), inner, outer)
+    }
+
+    #[cfg(feature = "simd")]
+    fn derivatives(&self, dists: Vec4, radius: f32) -> Vec4 {
+        let h = radius * 0.5;
+        let q = dists / h;
+        let scale = 10.0 / (7.0 * PI * h * h * h);
+        let inner = scale * (-3.0 * q + 2.25 * q * q);
+        let t = (2.0 - q).max(Vec4::ZERO);
+        let outer = scale * -0.75 * t * t;
+        Vec4::select(q.cmplt(Vec4::ONE), inner, outer)
+    }
+}
+
+/// Which kernel the simulation uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmoothingKernel {
+    #[default]
+    Quadratic,
+    Poly6,
+    Spiky,
+    CubicSpline,
+}
+
+impl Kernel for SmoothingKernel {
+    fn value(&self, dist: f32, radius: f32) -> f32 {
+        match self {
+            SmoothingKernel::Quadratic => Quadratic.value(dist, radius),
+            SmoothingKernel::Poly6 => Poly6.value(dist, radius),
+            SmoothingKernel::Spiky => Spiky.value(dist, radius),
+            SmoothingKernel::CubicSpline => CubicSpline.value(dist, radius),
+        }
+    }
+
+    fn derivative(&self, dist: f32, radius: f32) -> f32 {
+        match self {
+            SmoothingKernel::Quadratic => Quadratic.derivative(dist, radius),
+            SmoothingKernel::Poly6 => Poly6.derivative(dist, radius),
+            SmoothingKernel::Spiky => Spiky.derivative(dist, radius),
+            SmoothingKernel::CubicSpline => CubicSpline.derivative(dist, radius),
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    fn values(&self, dists: Vec4, radius: f32) -> Vec4 {
+        match self {
+            SmoothingKernel::Quadratic => Quadratic.values(dists, radius),
+            SmoothingKernel::Poly6 => Poly6.values(dists, radius),
+            SmoothingKernel::Spiky => Spiky.values(dists, radius),
+            SmoothingKernel::CubicSpline => CubicSpline.values(dists, radius),
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    fn derivatives(&self, dists: Vec4, radius: f32) -> Vec4 {
+        match self {
+            SmoothingKernel::Quadratic => Quadratic.derivatives(dists, radius),
+            SmoothingKernel::Poly6 => Poly6.derivatives(dists, radius),
+            SmoothingKernel::Spiky => Spiky.derivatives(dists, radius),
+            SmoothingKernel::CubicSpline => CubicSpline.derivatives(dists, radius),
+        }
+    }
+}
+
+/// Sums a kernel's value at each of a particle's neighbours, weighted by the neighbour's own weight
+/// (e.g. its mass, for a density), evaluating them four at a time
+#[cfg(feature = "simd")]
+pub struct ValueSum {
+    kernel: SmoothingKernel,
+    radius: f32,
+    // the neighbours waiting to be evaluated, the first `len` of each
+    dists: [f32; 4],
+    weights: [f32; 4],
+    len: usize,
+    sum: Vec4,
+}
+
+#[cfg(feature = "simd")]
+impl ValueSum {
+    pub fn new(kernel: SmoothingKernel, radius: f32) -> ValueSum {
+        ValueSum {
+            kernel,
+            radius,
+            dists: [0.0; 4],
+            weights: [0.0; 4],
+            len: 0,
+            sum: Vec4::ZERO,
+        }
+    }
+
+    pub fn add(&mut self, dist: f32, weight: f32) {
+        self.dists[self.len] = dist;
+        self.weights[self.len] = weight;
+        self.len += 1;
+        if self.len == 4 {
+            self.flush();
+        }
+    }
+
+    pub fn total(mut self) -> f32 {
+        if self.len > 0 {
+            self.flush();
+        }
+        self.sum.dot(Vec4::ONE)
+    }
+
+    fn flush(&mut self) {
+        // any lanes past `len` are left over from the last four, so they count for nothing
+        self.weights[self.len..].fill(0.0);
+        let values = self.kernel.values(Vec4::from(self.dists), self.radius);
+        self.sum += values * Vec4::from(self.weights);
+        self.len = 0;
+    }
+}
+
+/// Sums a kernel's derivative at each of a particle's neighbours along the direction to it,
+/// weighted by the neighbour's own weight (e.g. the pressure between them, for a pressure force),
+/// evaluating them four at a time
+#[cfg(feature = "simd")]
+pub struct GradientSum {
+    kernel: SmoothingKernel,
+    radius: f32,
+    // the neighbours waiting to be evaluated, the first `len` of each
+    dists: [f32; 4],
+    weights: [f32; 4],
+    dirs_x: [f32; 4],
+    dirs_y: [f32; 4],
+    len: usize,
+    sum_x: Vec4,
+    sum_y: Vec4,
+}
+
+#[cfg(feature = "simd")]
+impl GradientSum {
+    pub fn new(kernel: SmoothingKernel, radius: f32) -> GradientSum {
+        GradientSum {
+            kernel,
+            radius,
+            dists: [0.0; 4],
+            weights: [0.0; 4],
+            dirs_x: [0.0; 4],
+            dirs_y: [0.0; 4],
+            len: 0,
+            sum_x: Vec4::ZERO,
+            sum_y: Vec4::ZERO,
+        }
+    }
+
+    pub fn add(&mut self, dist: f32, dir: Vec2, weight: f32) {
+        self.dists[self.len] = dist;
+        self.weights[self.len] = weight;
+        self.dirs_x[self.len] = dir.x;
+        self.dirs_y[self.len] = dir.y;
+        self.len += 1;
+        if self.len == 4 {
+            self.flush();
+        }
+    }
+
+    pub fn total(mut self) -> Vec2 {
+        if self.len > 0 {
+            self.flush();
+        }
+        Vec2::new(self.sum_x.dot(Vec4::ONE), self.sum_y.dot(Vec4::ONE))
+    }
+
+    fn flush(&mut self) {
+        // any lanes past `len` are left over from the last four, so they count for nothing
+        self.weights[self.len..].fill(0.0);
+        let slopes = self.kernel.derivatives(Vec4::from(self.dists), self.radius);
+        let weighted = slopes * Vec4::from(self.weights);
+        self.sum_x += weighted * Vec4::from(self.dirs_x);
+        self.sum_y += weighted * Vec4::from(self.dirs_y);
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KERNELS: [SmoothingKernel; 4] = [
+        SmoothingKernel::Quadratic,
+        SmoothingKernel::Poly6,
+        SmoothingKernel::Spiky,
+        SmoothingKernel::CubicSpline,
+    ];
+
+    const RADII: [f32; 3] = [0.35, 1.0, 2.5];
+
+    #[test]
+    fn integrates_to_one() {
+        for kernel in KERNELS {
+            for radius in RADII {
+                // sum up rings of the circle it covers, with the midpoint rule
+                let steps = 10_000;
+                let width = radius / steps as f32;
+                let integral = (0..steps)
+                    .map(|i| {
+                        let dist = (i as f32 + 0.5) * width;
+                        let ring = 2.0 * PI * dist * width;
+                        (kernel.value(dist, radius) * ring) as f64
+                    })
+                    .sum::<f64>();
+
+                assert!(
+                    (integral - 1.0).abs() < 1e-3,
+                    "{kernel:?} with a radius of {radius} integrates to {integral}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn derivative_matches_finite_difference() {
+        for kernel in KERNELS {
+            for radius in RADII {
+                let step = radius * 1e-3;
+                for i in 1..100 {
+                    let dist = radius * i as f32 / 100.0;
+                    let expected = (kernel.value(dist + step, radius)
+                        - kernel.value(dist - step, radius))
+                        / (2.0 * step);
+                    let derivative = kernel.derivative(dist, radius);
+
+                    assert!(
+                        (derivative - expected).abs() <= 1e-2 * expected.abs().max(1.0),
+                        "{kernel:?} with a radius of {radius} at {dist}: derivative is \
+                         {derivative}, but the value changes by {expected}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn wide_matches_scalar() {
+        use glam::Vec4;
+
+        for kernel in KERNELS {
+            for radius in RADII {
+                // out past the radius too, where they're all 0
+                for i in 0..30 {
+                    let dists =
+                        Vec4::new(0.0, 1.0, 2.0, 3.0) * 0.01 * radius + i as f32 * 0.04 * radius;
+                    let values = kernel.values(dists, radius).to_array();
+                    let derivatives = kernel.derivatives(dists, radius).to_array();
+                    for (lane, dist) in dists.to_array().into_iter().enumerate() {
+                        let close = |a: f32, b: f32| (a - b).abs() <= 1e-4 * b.abs().max(1.0);
+                        assert!(
+                            close(values[lane], kernel.value(dist, radius)),
+                            "{kernel:?} with a radius of {radius} at {dist}: value is {}, but \
+                             {} one at a time",
+                            values[lane],
+                            kernel.value(dist, radius)
+                        );
+                        assert!(
+                            close(derivatives[lane], kernel.derivative(dist, radius)),
+                            "{kernel:?} with a radius of {radius} at {dist}: derivative is {}, \
+                             but {} one at a time",
+                            derivatives[lane],
+                            kernel.derivative(dist, radius)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn sums_match_scalar() {
+        use glam::Vec2;
+
+        let radius = 1.0;
+        // not a multiple of four, so there are some left over at the end
+        let neighbours = (0..23)
+            .map(|i| {
+                let dist = i as f32 * 0.05;
+                let dir = Vec2::from_angle(i as f32);
+                (dist, dir, 1.0 + i as f32 * 0.1)
+            })
+            .collect::<Vec<_>>();
+
+        for kernel in KERNELS {
+            let mut values = ValueSum::new(kernel, radius);
+            let mut gradient = GradientSum::new(kernel, radius);
+            let (mut expected_value, mut expected_gradient) = (0.0, Vec2::ZERO);
+            for &(dist, dir, weight) in &neighbours {
+                values.add(dist, weight);
+                gradient.add(dist, dir, weight);
+                expected_value += kernel.value(dist, radius) * weight;
+                expected_gradient += dir * kernel.derivative(dist, radius) * weight;
+            }
+
+            let (value, gradient) = (values.total(), gradient.total());
+            assert!(
+                (value - expected_value).abs() <= 1e-4 * expected_value.abs(),
+                "{kernel:?}: {value} != {expected_value}"
+            );
+            assert!(
+                gradient.abs_diff_eq(expected_gradient, 1e-4 * expected_gradient.length()),
+                "{kernel:?}: {gradient} != {expected_gradient}"
+            );
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! The grid half of the FLIP/PIC solver: the particles' velocities are spread onto a staggered
+//! (MAC) grid, made divergence free there, and read back. PIC takes the grid's new velocities as
+//! they are, which is stable but smears the motion out, while FLIP only adds on how much the grid
+//! changed them, which keeps the detail (and the splashes) but is noisier, so the two are blended.
+//!
+//! This follows Matthias Müller's "Ten Minute Physics" FLIP simulation.
+
+use glam::Vec2;
+
+use crate::rect::Rect;
+
+/// What fills a grid cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    /// The walls around the edge of the grid, which nothing flows through
+    Solid,
+    /// A cell with particles in it
+    Fluid,
+    /// An empty cell, which is at zero pressure, so fluid's free to flow into it (i.e. splash)
+    Air,
+}
+
+/// A staggered grid over the bounding box, with a ring of solid cells around it
+pub struct MacGrid {
+    // the world position of the top left corner of the first cell
+    origin: Vec2,
+    columns: usize,
+    rows: usize,
+    // row by row, starting from the top left
+    cells: Vec<Cell>,
+    // the horizontal velocity across the left edge of each cell, and the vertical velocity across
+    // its top edge
+    velocities: [Vec<f32>; 2],
+    // the velocities as they were before being made divergence free, which FLIP takes the change
+    // from
+    previous_velocities: [Vec<f32>; 2],
+    // how much the particles counted for at each edge, while their velocities are spread out
+    weights: [Vec<f32>; 2],
+    // how many particles are around the middle of each cell
+    densities: Vec<f32>,
+    // the average density of the fluid cells, as of the first solve: cells more crowded than
+    // this are pushed apart, so particles don't slowly drift together
+    rest_density: f32,
+}
+
+impl MacGrid {
+    /// The size of each cell, in world units: big enough for a few particles to be in each one
+    const CELL_SIZE: f32 = 0.5;
+
+    /// How many Gauss-Seidel iterations the pressure is solved with
+    const SOLVER_ITERATIONS: usize = 50;
+
+    /// How far past each Gauss-Seidel correction to go, which makes the solve settle much sooner
+    const OVER_RELAXATION: f32 = 1.9;
+
+    /// How strongly cells more crowded than the rest density are pushed apart
+    const DRIFT_CORRECTION: f32 = 0.1;
+
+    pub fn new(bounding_box: Rect) -> MacGrid {
+        let columns = (bounding_box.w / Self::CELL_SIZE).ceil() as usize + 2;
+        let rows = (bounding_box.h / Self::CELL_SIZE).ceil() as usize + 2;
+        let count = columns * rows;
+        MacGrid {
+            origin: Vec2::new(bounding_box.x, bounding_box.y) - Self::CELL_SIZE,
+            columns,
+            rows,
+            cells: vec![Cell::Air; count],
+            velocities: [vec![0.0; count], vec![0.0; count]],
+            previous_velocities: [vec![0.0; count], vec![0.0; count]],
+            weights: [vec![0.0; count], vec![0.0; count]],
+            densities: vec![0.0; count],
+            rest_density: 0.0,
+        }
+    }
+
+    /// Forgets the rest density, so it's measured again on the next solve
+    pub fn clear(&mut self) {
+        self.rest_density = 0.0;
+    }
+
+    /// Makes the particles' velocities divergence free, taking `flip_ratio` of the change from
+    /// FLIP and the rest from PIC
+    pub fn solve(&mut self, positions: &[Vec2], velocities: &mut [Vec2], flip_ratio: f32) {
+        self.transfer_to_grid(positions, velocities);
+        self.update_densities(positions);
+        self.make_incompressible();
+        self.transfer_to_particles(positions, velocities, flip_ratio);
+    }
+
+    /// Spreads the particles' velocities onto the edges around them, and marks which cells have
+    /// fluid in them
+    fn transfer_to_grid(&mut self, positions: &[Vec2], velocities: &[Vec2]) {
+        for idx in 0..self.cells.len() {
+            let (column, row) = (idx % self.columns, idx / self.columns);
+            let edge =
+                column == 0 || row == 0 || column + 1 == self.columns || row + 1 == self.rows;
+            self.cells[idx] = if edge { Cell::Solid } else { Cell::Air };
+        }
+        for pos in positions {
+            let idx = self.cell_index(*pos);
+            if self.cells[idx] == Cell::Air {
+                self.cells[idx] = Cell::Fluid;
+            }
+        }
+
+        for component in 0..2 {
+            self.velocities[component].fill(0.0);
+            self.weights[component].fill(0.0);
+            for (pos, velocity) in positions.iter().zip(velocities) {
+                for (idx, weight) in self.edge_weights(*pos, component) {
+                    self.velocities[component][idx] += velocity[component] * weight;
+                    self.weights[component][idx] += weight;
+                }
+            }
+
+            for idx in 0..self.cells.len() {
+                let weight = self.weights[component][idx];
+                let touches_solid = self.touches_solid(idx, component);
+                let velocity = &mut self.velocities[component][idx];
+                *velocity = if touches_solid || weight == 0.0 {
+                    0.0
+                } else {
+                    *velocity / weight
+                };
+            }
+        }
+
+        self.previous_velocities.clone_from(&self.velocities);
+    }
+
+    /// Counts the particles around the middle of each cell, and the rest density if it's not been
+    /// measured yet
+    fn update_densities(&mut self, positions: &[Vec2]) {
+        self.densities.fill(0.0);
+        let centre = Vec2::splat(Self::CELL_SIZE * 0.5);
+        for pos in positions {
+            for (idx, weight) in self.weights_around(*pos - centre) {
+                self.densities[idx] += weight;
+            }
+        }
+
+        if self.rest_density == 0.0 {
+            let (total, count) = self
+                .cells
+                .iter()
+                .zip(&self.densities)
+                .filter(|(cell, _)| **cell == Cell::Fluid)
+                .fold((0.0, 0.0), |(total, count), (_, density)| {
+                    (total + density, count + 1.0)
+                });
+            if count > 0.0 {
+                self.rest_density = total / count;
+            }
+        }
+    }
+
+    /// Adjusts the velocities around each fluid cell until as much flows out of it as flows in
+    /// (or a little more, if it's more crowded than the rest density)
+    fn make_incompressible(&mut self) {
+        for _ in 0..Self::SOLVER_ITERATIONS {
+            for idx in 0..self.cells.len() {
+                if self.cells[idx] != Cell::Fluid {
+                    continue;
+                }
+
+                // only the edges shared with cells that aren't solid can be changed
+                let open = |other_idx: usize| (self.cells[other_idx] != Cell::Solid) as u8 as f32;
+                let (left, right) = (open(idx - 1), open(idx + 1));
+                let (up, down) = (open(idx - self.columns), open(idx + self.columns));
+                let sides = left + right + up + down;
+                if sides == 0.0 {
+                    continue;
+                }
+
+                let [u, v] = &mut self.velocities;
+                let mut divergence = u[idx + 1] - u[idx] + v[idx + self.columns] - v[idx];
+                if self.rest_density > 0.0 {
+                    let compression = self.densities[idx] - self.rest_density;
+                    if compression > 0.0 {
+                        divergence -= Self::DRIFT_CORRECTION * compression;
+                    }
+                }
+
+                let pressure = -divergence / sides * Self::OVER_RELAXATION;
+                u[idx] -= left * pressure;
+                u[idx + 1] += right * pressure;
+                v[idx] -= up * pressure;
+                v[idx + self.columns] += down * pressure;
+            }
+        }
+    }
+
+    /// Reads the particles' velocities back from the edges around them, blending between PIC and
+    /// FLIP by `flip_ratio`
+    fn transfer_to_particles(&self, positions: &[Vec2], velocities: &mut [Vec2], flip_ratio: f32) {
+        for (pos, velocity) in positions.iter().zip(velocities) {
+            for component in 0..2 {
+                let mut total_weight = 0.0;
+                let mut pic = 0.0;
+                let mut change = 0.0;
+                for (idx, weight) in self.edge_weights(*pos, component) {
+                    // edges between two empty cells weren't given a velocity
+                    if !self.touches_fluid(idx, component) {
+                        continue;
+                    }
+
+                    let current = self.velocities[component][idx];
+                    pic += current * weight;
+                    change += (current - self.previous_velocities[component][idx]) * weight;
+                    total_weight += weight;
+                }
+
+                if total_weight > 0.0 {
+                    let pic = pic / total_weight;
+                    let flip = velocity[component] + change / total_weight;
+                    velocity[component] = pic + (flip - pic) * flip_ratio;
+                }
+            }
+        }
+    }
+
+    /// The index of the cell the position's in, kept off the solid edges
+    fn cell_index(&self, pos: Vec2) -> usize {
+        let grid_pos = (pos - self.origin) / Self::CELL_SIZE;
+        let column = (grid_pos.x.max(0.0) as usize).clamp(1, self.columns - 2);
+        let row = (grid_pos.y.max(0.0) as usize).clamp(1, self.rows - 2);
+        row * self.columns + column
+    }
+
+    /// The four edges around the position that hold the given component of the velocity (0 for
+    /// horizontal, 1 for vertical), and how much each counts for
+    fn edge_weights(&self, pos: Vec2, component: usize) -> [(usize, f32); 4] {
+        // horizontal velocities are halfway down the left edges, and vertical ones are halfway
+        // along the top edges
+        let offset = match component {
+            0 => Vec2::new(0.0, 0.5),
+            _ => Vec2::new(0.5, 0.0),
+        };
+        self.weights_around(pos - offset * Self::CELL_SIZE)
+    }
+
+    /// The four cell corners around the position, and how much each counts for (bilinearly)
+    fn weights_around(&self, pos: Vec2) -> [(usize, f32); 4] {
+        let grid_pos = (pos - self.origin) / Self::CELL_SIZE;
+        let column = (grid_pos.x.max(0.0) as usize).min(self.columns - 2);
+        let row = (grid_pos.y.max(0.0) as usize).min(self.rows - 2);
+        let t = (grid_pos - Vec2::new(column as f32, row as f32)).clamp(Vec2::ZERO, Vec2::ONE);
+
+        let idx = row * self.columns + column;
+        [
+            (idx, (1.0 - t.x) * (1.0 - t.y)),
+            (idx + 1, t.x * (1.0 - t.y)),
+            (idx + self.columns, (1.0 - t.x) * t.y),
+            (idx + self.columns + 1, t.x * t.y),
+        ]
+    }
+
+    /// The cell on the other side of the edge holding the given component in the cell, if there
+    /// is one
+    fn across_edge(&self, idx: usize, component: usize) -> Option<usize> {
+        match component {
+            0 => (!idx.is_multiple_of(self.columns)).then(|| idx - 1),
+            _ => (idx >= self.columns).then(|| idx - self.columns),
+        }
+    }
+
+    /// Whether the edge holding the given component in the cell is against a solid cell
+    fn touches_solid(&self, idx: usize, component: usize) -> bool {
+        self.cells[idx] == Cell::Solid
+            || self
+                .across_edge(idx, component)
+                .is_none_or(|other_idx| self.cells[other_idx] == Cell::Solid)
+    }
+
+    /// Whether the edge holding the given component in the cell is next to any fluid
+    fn touches_fluid(&self, idx: usize, component: usize) -> bool {
+        self.cells[idx] == Cell::Fluid
+            || self
+                .across_edge(idx, component)
+                .is_some_and(|other_idx| self.cells[other_idx] == Cell::Fluid)
+    }
+}
@@ -1,15 +1,137 @@
+mod attract;
+mod bench;
+mod body;
+mod boundary;
+mod camera;
+mod cell_list;
 mod cli;
+#[cfg(feature = "video")]
+mod clip;
+#[cfg(not(feature = "video"))]
+#[path = "disabled/clip.rs"]
+mod clip;
+mod colormap;
+mod config;
 mod engine;
+mod force_field;
 mod fps;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+#[cfg(not(feature = "gamepad"))]
+#[path = "disabled/gamepad.rs"]
+mod gamepad;
+#[cfg(feature = "video")]
+mod gif;
+mod grid;
+mod heater;
+mod hud;
+mod ink;
+mod kernel;
+mod mac_grid;
+mod material;
+mod noise;
+mod offline;
+mod profiler;
+mod recent_logs;
+#[cfg(feature = "video")]
+mod recording;
+#[cfg(not(feature = "video"))]
+#[path = "disabled/recording.rs"]
+mod recording;
 mod rect;
 mod renderer;
+mod settings;
+mod snapshot;
+mod springs;
+mod stability;
 mod state;
+mod toast;
+mod user_data;
 mod window;
+mod window_motion;
 
+use anyhow::Result;
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Command};
 use engine::Engine;
+use profiler::ProfilerLayer;
+use recent_logs::RecentLogs;
+use settings::Settings;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::EnvFilter;
 
-pub fn main() -> ! {
-    Engine::run(Cli::parse());
+pub fn main() -> Result<()> {
+    let env_filter = || {
+        EnvFilter::builder()
+            .with_default_directive(LevelFilter::INFO.into())
+            .from_env_lossy()
+    };
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(env_filter()),
+        )
+        // the same lines are kept in memory too, to be saved with debug dumps
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(|| RecentLogs)
+                .with_filter(env_filter()),
+        )
+        // the profiler only cares about spans, regardless of what's being logged
+        .with(ProfilerLayer.with_filter(filter_fn(|metadata| metadata.is_span())))
+        .init();
+
+    let args = Cli::parse();
+    match args.command {
+        Some(Command::Diff { ref a, ref b }) => snapshot::diff(a, b),
+        Some(Command::Settings) => {
+            Settings::print_registry();
+            Ok(())
+        }
+        Some(Command::Stability { ticks }) => {
+            let config = args.config()?;
+            let settings = args.settings(&config)?;
+            stability::run(
+                &settings,
+                &config,
+                args.seed,
+                args.solver,
+                args.material,
+                ticks,
+            )
+        }
+        Some(Command::Bench { ticks, particles }) => {
+            let config = args.config()?;
+            let settings = args.settings(&config)?;
+            bench::run(
+                &settings,
+                &config,
+                args.seed,
+                args.solver,
+                args.material,
+                particles,
+                ticks,
+            )
+        }
+        Some(Command::Render {
+            ticks,
+            every,
+            ref out,
+        }) => {
+            let config = args.config()?;
+            let settings = args.settings(&config)?;
+            let colormaps = args.colormaps(&config)?;
+            offline::render(&args, settings, &colormaps, &config, ticks, every, out)
+        }
+        None => {
+            let config = args.config()?;
+            let settings = args.settings(&config)?;
+            let colormaps = args.colormaps(&config)?;
+            Engine::run(args, settings, colormaps, config)
+        }
+    }
 }
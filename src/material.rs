@@ -0,0 +1,55 @@
+//! Fluids besides the base one (whose mass and target density are settings), so heavier and
+//! lighter fluids can be layered and mixed, like oil floating on water. They're listed in the
+//! `materials` section of the config file, and new particles are made of whichever one's picked
+//! with D (by rain, or poured in with the pour tool).
+//!
+//! ```json
+//! {
+//!   "materials": [
+//!     { "name": "oil", "mass": 0.5, "target_density": 2.5, "color": "#e0a030" },
+//!     { "name": "sand", "mass": 2, "target_density": 10, "color": "#c2b280", "behaviour": "sand" }
+//!   ]
+//! }
+//! ```
+
+use anyhow::Result;
+use clap::ValueEnum;
+use glam::Vec3;
+use serde::{Deserialize, Deserializer};
+
+use crate::colormap::parse_hex;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Material {
+    /// What it's called when it's picked
+    pub name: String,
+    /// The mass of each of its particles, in the same units as the `mass` setting
+    pub mass: f32,
+    /// The density it tries to maintain, in the same units as the `target_density` setting
+    pub target_density: f32,
+    /// The color its particles are drawn in (rather than by the colormap), in the form `#rrggbb`
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: Vec3,
+    /// Whether it flows or piles up
+    #[serde(default)]
+    pub behaviour: Behaviour,
+}
+
+/// How a material's particles move with each other
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Behaviour {
+    /// Flows, and holds itself together at its surface
+    #[default]
+    Liquid,
+    /// Granular: grains only ever push each other apart, bounce off each other when they overlap,
+    /// and rub against each other, so they pile up into slopes rather than levelling out
+    Sand,
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec3, D::Error> {
+    let color = String::deserialize(deserializer)?;
+    let [r, g, b] = parse_hex(&color).map_err(serde::de::Error::custom)?;
+    Ok(Vec3::new(r as f32, g as f32, b as f32) / 255.0)
+}
@@ -0,0 +1,104 @@
+//! Seeded gradient noise (Ken Perlin's improved noise), and its curl: a flow that swirls around
+//! without bunching anything up, since it has no divergence.
+
+use glam::{Vec2, Vec3};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+pub struct Noise {
+    // a shuffled permutation of 0..=255, repeated so lookups don't need to wrap
+    perm: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(rng: &mut impl Rng) -> Noise {
+        let mut shuffled = [0u8; 256];
+        for (i, value) in shuffled.iter_mut().enumerate() {
+            *value = i as u8;
+        }
+        shuffled.shuffle(rng);
+
+        let mut perm = [0; 512];
+        for (i, value) in perm.iter_mut().enumerate() {
+            *value = shuffled[i % 256];
+        }
+
+        Noise { perm }
+    }
+
+    /// The noise at the given point, which is smooth and roughly within -1..=1
+    pub fn sample(&self, p: Vec3) -> f32 {
+        let cell = p.floor();
+        let x = (cell.x as i32 & 255) as usize;
+        let y = (cell.y as i32 & 255) as usize;
+        let z = (cell.z as i32 & 255) as usize;
+
+        let f = p - cell;
+        let (u, v, w) = (fade(f.x), fade(f.y), fade(f.z));
+
+        // hash each corner of the cell
+        let perm = &self.perm;
+        let a = perm[x] as usize + y;
+        let aa = perm[a] as usize + z;
+        let ab = perm[a + 1] as usize + z;
+        let b = perm[x + 1] as usize + y;
+        let ba = perm[b] as usize + z;
+        let bb = perm[b + 1] as usize + z;
+
+        let corner = |hash: usize, offset: Vec3| grad(perm[hash], f - offset);
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, corner(aa, Vec3::ZERO), corner(ba, Vec3::X)),
+                lerp(u, corner(ab, Vec3::Y), corner(bb, Vec3::X + Vec3::Y)),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    corner(aa + 1, Vec3::Z),
+                    corner(ba + 1, Vec3::X + Vec3::Z),
+                ),
+                lerp(
+                    u,
+                    corner(ab + 1, Vec3::Y + Vec3::Z),
+                    corner(bb + 1, Vec3::ONE),
+                ),
+            ),
+        )
+    }
+
+    /// The curl of the noise at the given position, with time as the third dimension so the flow
+    /// changes smoothly as it passes
+    pub fn curl(&self, pos: Vec2, time: f32) -> Vec2 {
+        const EPSILON: f32 = 1e-3;
+
+        let p = pos.extend(time);
+        let dx = self.sample(p + Vec3::X * EPSILON) - self.sample(p - Vec3::X * EPSILON);
+        let dy = self.sample(p + Vec3::Y * EPSILON) - self.sample(p - Vec3::Y * EPSILON);
+        Vec2::new(dy, -dx) / (2.0 * EPSILON)
+    }
+}
+
+/// Eases `t` so the noise's derivatives are continuous across cells
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f32, a: f32, b: f32) -> f32 {
+    a + t * (b - a)
+}
+
+/// Dots `p` with one of twelve gradients, picked by the hash
+fn grad(hash: u8, p: Vec3) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { p.x } else { p.y };
+    let v = match h {
+        0..=3 => p.y,
+        12 | 14 => p.x,
+        _ => p.z,
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
@@ -0,0 +1,89 @@
+//! Running the simulation without showing it, and drawing each tick to an image.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use glutin::prelude::*;
+use winit::dpi::{LogicalSize, PhysicalSize};
+
+use crate::camera::Camera;
+use crate::cli::Cli;
+use crate::colormap::Colormaps;
+use crate::config::Config;
+use crate::engine::{EngineContext, Overlays};
+use crate::renderer::{ColorField, ParticleStyle, Renderer};
+use crate::settings::Settings;
+use crate::state::State;
+use crate::window::create_hidden_window;
+
+/// Runs `ticks` ticks (rounded down to a multiple of `every`), and writes the particles after every
+/// `every`th one to a numbered PNG in `out`
+pub fn render(
+    args: &Cli,
+    settings: Settings,
+    colormaps: &Colormaps,
+    config: &Config,
+    ticks: u64,
+    every: u64,
+    out: &Path,
+) -> Result<()> {
+    fs::create_dir_all(out).with_context(|| format!("failed to create {}", out.display()))?;
+
+    let mut state = State::new(settings, args.seed);
+    state.solver = args.solver;
+    state.base_behaviour = args.material;
+    config.apply(&mut state);
+    // frames are the size the window would be on a display without any scaling
+    let dimensions = PhysicalSize::new(
+        (State::VIEW.w * State::PIXELS_PER_UNIT) as u32,
+        (State::VIEW.h * State::PIXELS_PER_UNIT) as u32,
+    );
+
+    let (window, _event_loop, gl_display, gl_surface, not_current_gl_context) =
+        create_hidden_window(
+            LogicalSize::new(dimensions.width, dimensions.height),
+            args.safe_mode,
+        );
+    let _gl_context = not_current_gl_context
+        .unwrap()
+        .make_current(&gl_surface)
+        .context("failed to make the context current")?;
+    let mut gl_renderer = Renderer::new(&gl_display, &window, args.safe_mode)?;
+
+    // frames are numbered without gaps (regardless of `every`), and padded so they sort in order
+    let frames = ticks / every;
+    let digits = frames.to_string().len();
+    for frame in 1..=frames {
+        for _ in 0..every {
+            state.step(&[]);
+        }
+
+        let ctx = EngineContext {
+            surface_dimensions: dimensions,
+            scale_factor: 1.0,
+            state: &state,
+            camera: Camera::new(State::VIEW),
+            cursor: None,
+            interactions: &[],
+            particle_style: ParticleStyle::Dots,
+            color_field: ColorField::Speed,
+            colormap: colormaps.current(),
+            overlays: Overlays::default(),
+            hud: None,
+            gamepad: false,
+            bloom: false,
+            trails: false,
+            transparent: false,
+            fluid_only: false,
+            toast: None,
+            vsync: false,
+            fps: 0.0,
+        };
+        let path = out.join(format!("{frame:0digits$}.png"));
+        gl_renderer.render_particles(&ctx)?.save_png(&path)?;
+    }
+
+    tracing::info!("rendered {frames} frames to {}", out.display());
+    Ok(())
+}
@@ -0,0 +1,191 @@
+//! A quick on-screen profiler: how long each span (the phases of a tick, the render passes, etc)
+//! took over the last few seconds, collected by a `tracing` layer.
+//!
+//! Spans are shown by their name, or their `pass` field if they have one, and nothing is recorded
+//! unless the profiler is enabled.
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// How far back timings are shown for
+const WINDOW: Duration = Duration::from_secs(3);
+
+/// How many characters wide a bar is when its span takes up all of the time
+const BAR_WIDTH: usize = 40;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMINGS: Mutex<Timings> = Mutex::new(Timings::new());
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Starts or stops recording, any timings recorded so far are thrown away
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+    TIMINGS.lock().unwrap().samples.clear();
+}
+
+/// One line for each span seen in the last few seconds, indented by how deeply it's nested: how
+/// long it took on average, and a bar for how much of the time was spent in it
+pub fn summary() -> Vec<String> {
+    let mut timings = TIMINGS.lock().unwrap();
+    timings.prune(Instant::now());
+
+    let mut totals = vec![(Duration::ZERO, 0u32); timings.names.len()];
+    for (name, _, duration) in &timings.samples {
+        totals[*name].0 += *duration;
+        totals[*name].1 += 1;
+    }
+
+    let width = timings
+        .names
+        .iter()
+        .map(|(name, depth)| name.len() + depth * 2)
+        .max()
+        .unwrap_or(0);
+
+    timings
+        .names
+        .iter()
+        .zip(totals)
+        .filter(|(_, (_, count))| *count > 0)
+        .map(|((name, depth), (total, count))| {
+            let mean = total.as_secs_f32() * 1000.0 / count as f32;
+            let share = (total.as_secs_f32() / WINDOW.as_secs_f32()).min(1.0);
+            let bar = "#".repeat((share * BAR_WIDTH as f32).ceil() as usize);
+            let name = format!("{}{name}", "  ".repeat(*depth));
+            format!("{name:<width$} {mean:>7.3}ms {bar}")
+        })
+        .collect()
+}
+
+struct Timings {
+    // every span seen along with how deeply it's nested, in the order they were first created so
+    // that parents come before their children
+    names: Vec<(String, usize)>,
+    // when each span ended and how long it took, oldest first
+    samples: VecDeque<(usize, Instant, Duration)>,
+}
+
+impl Timings {
+    const fn new() -> Timings {
+        Timings {
+            names: Vec::new(),
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn name_index(&mut self, name: String, depth: usize) -> usize {
+        let key = (name, depth);
+        match self.names.iter().position(|existing| *existing == key) {
+            Some(idx) => idx,
+            None => {
+                self.names.push(key);
+                self.names.len() - 1
+            }
+        }
+    }
+
+    fn record(&mut self, name: usize, duration: Duration) {
+        let now = Instant::now();
+        self.samples.push_back((name, now, duration));
+        self.prune(now);
+    }
+
+    /// Drops any samples that are too old to be shown
+    fn prune(&mut self, now: Instant) {
+        while let Some((_, ended, _)) = self.samples.front() {
+            if now.duration_since(*ended) <= WINDOW {
+                break;
+            }
+
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// Records how long spans are entered for while the profiler is enabled
+pub struct ProfilerLayer;
+
+/// Kept in each span's extensions: its index in `Timings::names`, and when it was entered
+struct SpanTiming {
+    name: usize,
+    entered: Option<Instant>,
+}
+
+impl<S> Layer<S> for ProfilerLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if !enabled() {
+            return;
+        }
+
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut pass = PassVisitor(None);
+        attrs.record(&mut pass);
+        let name = pass
+            .0
+            .unwrap_or_else(|| attrs.metadata().name().to_string());
+        let depth = span.scope().skip(1).count();
+
+        let name = TIMINGS.lock().unwrap().name_index(name, depth);
+        span.extensions_mut().insert(SpanTiming {
+            name,
+            entered: None,
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                timing.entered = Some(Instant::now());
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        if !enabled() {
+            return;
+        }
+
+        if let Some(span) = ctx.span(id) {
+            if let Some(timing) = span.extensions_mut().get_mut::<SpanTiming>() {
+                if let Some(entered) = timing.entered.take() {
+                    TIMINGS
+                        .lock()
+                        .unwrap()
+                        .record(timing.name, entered.elapsed());
+                }
+            }
+        }
+    }
+}
+
+/// Finds a span's `pass` field
+struct PassVisitor(Option<String>);
+
+impl Visit for PassVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "pass" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+}
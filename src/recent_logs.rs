@@ -0,0 +1,40 @@
+//! The last few lines that were logged, kept around so they can be saved with a debug dump.
+//!
+//! This is a writer for a `tracing_subscriber::fmt` layer, so the lines are formatted just like the
+//! ones on stderr (minus the colors).
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// How many lines are kept
+const CAPACITY: usize = 200;
+
+static LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// The lines kept so far, oldest first
+pub fn lines() -> Vec<String> {
+    LINES.lock().unwrap().iter().cloned().collect()
+}
+
+/// Keeps whatever's written to it as lines, dropping the oldest ones once there are too many
+pub struct RecentLogs;
+
+impl Write for RecentLogs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // each event is written all at once, so there aren't any partial lines to hold on to
+        let mut lines = LINES.lock().unwrap();
+        for line in String::from_utf8_lossy(buf).lines() {
+            if lines.len() == CAPACITY {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+//! Records the window to a video by streaming its frames to an `ffmpeg` child process, which does
+//! all the encoding.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread;
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use winit::dpi::PhysicalSize;
+
+use crate::renderer::Image;
+
+pub struct Recording {
+    path: PathBuf,
+    dimensions: PhysicalSize<u32>,
+    started: Instant,
+    // how many frames have been sent to ffmpeg so far, counting repeats
+    frames: u64,
+    // frames to write, and how many times to write each of them
+    sender: SyncSender<(Image, u64)>,
+}
+
+impl Recording {
+    /// The video's frame rate, frames are repeated if they're drawn less often than this
+    const FRAME_RATE: u32 = 30;
+    /// How many frames can be waiting for ffmpeg before new ones are dropped
+    const QUEUE_LEN: usize = 4;
+
+    /// Starts ffmpeg, which writes frames of the given size to `path` (its extension decides the
+    /// container, e.g. `.mp4`)
+    pub fn start(path: PathBuf, dimensions: PhysicalSize<u32>) -> Result<Recording> {
+        let mut child = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-y"])
+            .args(["-f", "rawvideo", "-pixel_format", "rgba"])
+            .arg("-video_size")
+            .arg(format!("{}x{}", dimensions.width, dimensions.height))
+            .arg("-framerate")
+            .arg(Self::FRAME_RATE.to_string())
+            .args(["-i", "-"])
+            // most encoders need even dimensions, and players expect 4:2:0
+            .args([
+                "-vf",
+                "crop=trunc(iw/2)*2:trunc(ih/2)*2",
+                "-pix_fmt",
+                "yuv420p",
+            ])
+            .arg(&path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .context("failed to run ffmpeg, is it installed?")?;
+        let mut stdin = child.stdin.take().unwrap();
+
+        // write on another thread, so encoding doesn't hold up drawing
+        let (sender, receiver) = mpsc::sync_channel::<(Image, u64)>(Self::QUEUE_LEN);
+        thread::spawn({
+            let path = path.clone();
+            move || {
+                let written = receiver.iter().try_for_each(|(frame, count)| {
+                    (0..count).try_for_each(|_| stdin.write_all(&frame.pixels))
+                });
+
+                // closing stdin tells ffmpeg there are no more frames
+                drop(receiver);
+                drop(stdin);
+                match (child.wait(), written) {
+                    (Ok(status), _) if !status.success() => {
+                        tracing::error!("ffmpeg failed to record {}: {status}", path.display())
+                    }
+                    (Err(e), _) => tracing::error!("failed to wait for ffmpeg: {e}"),
+                    (_, Err(e)) => tracing::error!("failed to write frames to ffmpeg: {e}"),
+                    (Ok(_), Ok(())) => tracing::info!("saved recording to {}", path.display()),
+                }
+            }
+        });
+
+        Ok(Recording {
+            path,
+            dimensions,
+            started: Instant::now(),
+            frames: 0,
+            sender,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How many frames of video are due since the last one was sent
+    fn frames_due(&self) -> u64 {
+        let elapsed = self.started.elapsed().as_secs_f64() * Self::FRAME_RATE as f64;
+        (elapsed as u64 + 1).saturating_sub(self.frames)
+    }
+
+    /// Whether it's time to send another frame
+    pub fn due(&self) -> bool {
+        self.frames_due() > 0
+    }
+
+    /// Sends a frame to ffmpeg, repeated to fill any time since the last one. Fails if the frame is
+    /// a different size to the video, or ffmpeg has stopped
+    pub fn push(&mut self, frame: Image) -> Result<()> {
+        if (frame.width, frame.height) != (self.dimensions.width, self.dimensions.height) {
+            bail!("the window was resized");
+        }
+
+        let count = self.frames_due();
+        match self.sender.try_send((frame, count)) {
+            Ok(()) => self.frames += count,
+            // ffmpeg is falling behind, the next frame will be repeated to make up for this one
+            Err(TrySendError::Full(_)) => {}
+            Err(TrySendError::Disconnected(_)) => bail!("ffmpeg stopped"),
+        }
+
+        Ok(())
+    }
+}
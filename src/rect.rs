@@ -1,6 +1,9 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
-#[derive(Debug, Clone, Copy)]
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -28,6 +31,12 @@ impl Rect {
     pub fn bottom(&self) -> f32 {
         self.y + self.h
     }
+
+    /// Whether the point is inside, or on the top or left edge
+    pub fn contains(&self, point: Vec2) -> bool {
+        (self.left()..self.right()).contains(&point.x)
+            && (self.top()..self.bottom()).contains(&point.y)
+    }
 }
 
 // TODO: macros for these, since there's a lot of repeated code
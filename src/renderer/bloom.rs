@@ -0,0 +1,211 @@
+use anyhow::{bail, Result};
+use winit::dpi::PhysicalSize;
+
+use super::debug::{self, DebugGroup};
+use super::pass::{quad_buffer, Pass, Target, SOURCE_TEXTURE_UNIT};
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{select_shader, GlslVersion};
+use crate::gl_assert_ok;
+
+/// The texture unit the blurred highlights are read from in the composite
+const BLOOM_TEXTURE_UNIT: u32 = 4;
+
+/// Makes bright things glow: the scene is drawn into a framebuffer, its highlights are picked out
+/// and blurred at half resolution, and then both are added on top of what was drawn before
+pub struct GlBloom {
+    vbo: u32,
+    bright: Pass,
+    blur: Pass,
+    composite: Pass,
+    texel_step_uniform: i32,
+    // the scene is drawn into this, with `depth` as its depth buffer
+    scene: Target,
+    depth: u32,
+    // the highlights are blurred back and forth between these, at half the size of the scene
+    blurred: [Target; 2],
+    // the size the targets were last allocated at
+    dimensions: PhysicalSize<u32>,
+    // what to go back to once the scene's been drawn
+    previous_fbo: i32,
+    previous_viewport: [i32; 4],
+}
+
+impl GlBloom {
+    /// How many times the highlights are blurred (horizontally then vertically), the more times
+    /// the further the glow spreads
+    const BLUR_PASSES: usize = 2;
+
+    pub fn new(version: GlslVersion) -> Result<GlBloom> {
+        if !gl::GenFramebuffers::is_loaded() || !gl::GenRenderbuffers::is_loaded() {
+            bail!("framebuffer objects are not supported");
+        }
+
+        let vbo = quad_buffer("bloom");
+        let mut depth = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut depth);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::RENDERBUFFER, depth, "bloom scene depth");
+
+        let bright = Pass::new(
+            version,
+            &select_shader(
+                version,
+                shader!("bloom_bright.frag"),
+                shader!("bloom_bright.legacy.frag"),
+            ),
+            vbo,
+            "bloom bright pass",
+        )?;
+        let blur = Pass::new(
+            version,
+            &select_shader(
+                version,
+                shader!("bloom_blur.frag"),
+                shader!("bloom_blur.legacy.frag"),
+            ),
+            vbo,
+            "bloom blur",
+        )?;
+        let composite = Pass::new(
+            version,
+            &select_shader(
+                version,
+                shader!("bloom_composite.frag"),
+                shader!("bloom_composite.legacy.frag"),
+            ),
+            vbo,
+            "bloom composite",
+        )?;
+
+        let texel_step_uniform;
+        unsafe {
+            gl::UseProgram(bright.program);
+            Uniform::Int(SOURCE_TEXTURE_UNIT as _).create(bright.program, "source")?;
+            gl::UseProgram(blur.program);
+            Uniform::Int(SOURCE_TEXTURE_UNIT as _).create(blur.program, "source")?;
+            texel_step_uniform = Uniform::Vec2(0.0, 0.0).create(blur.program, "texelStep")?;
+            gl::UseProgram(composite.program);
+            Uniform::Int(SOURCE_TEXTURE_UNIT as _).create(composite.program, "scene")?;
+            Uniform::Int(BLOOM_TEXTURE_UNIT as _).create(composite.program, "bloom")?;
+        }
+
+        Ok(GlBloom {
+            vbo,
+            bright,
+            blur,
+            composite,
+            texel_step_uniform,
+            scene: Target::new("bloom scene"),
+            depth,
+            blurred: [Target::new("bloom blur 0"), Target::new("bloom blur 1")],
+            dimensions: PhysicalSize::new(0, 0),
+            previous_fbo: 0,
+            previous_viewport: [0; 4],
+        })
+    }
+
+    /// Starts drawing the scene into the bloom's framebuffer (resizing it to match the surface if
+    /// need be), until `end` is called
+    pub fn begin(&mut self, dimensions: PhysicalSize<u32>) -> Result<()> {
+        unsafe {
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut self.previous_fbo);
+            gl::GetIntegerv(gl::VIEWPORT, self.previous_viewport.as_mut_ptr());
+
+            if self.dimensions != dimensions {
+                let (width, height) = (dimensions.width as i32, dimensions.height as i32);
+
+                gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth);
+                gl::RenderbufferStorage(
+                    gl::RENDERBUFFER,
+                    gl::DEPTH_COMPONENT16,
+                    width.max(1),
+                    height.max(1),
+                );
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.scene.fbo);
+                gl::FramebufferRenderbuffer(
+                    gl::FRAMEBUFFER,
+                    gl::DEPTH_ATTACHMENT,
+                    gl::RENDERBUFFER,
+                    self.depth,
+                );
+
+                let resized = self.scene.resize(width, height).and_then(|()| {
+                    self.blurred
+                        .iter()
+                        .try_for_each(|target| target.resize(width / 2, height / 2))
+                });
+                if let Err(e) = resized {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, self.previous_fbo as _);
+                    return Err(e);
+                }
+
+                self.dimensions = dimensions;
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.scene.fbo);
+            gl::Viewport(0, 0, dimensions.width as _, dimensions.height as _);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            gl_assert_ok!();
+        }
+
+        Ok(())
+    }
+
+    /// Blurs the highlights of everything drawn since `begin`, and adds the scene and its glow on
+    /// top of the framebuffer that was bound before
+    pub fn end(&self) {
+        let _bloom_group = DebugGroup::push("bloom");
+        let width = (self.dimensions.width as i32 / 2).max(1);
+        let height = (self.dimensions.height as i32 / 2).max(1);
+        unsafe {
+            // blending is additive, so with each target cleared first a pass just writes its output
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Viewport(0, 0, width, height);
+
+            // pick out the highlights
+            self.pass(&self.bright, self.scene.texture, &self.blurred[0]);
+
+            // then blur them back and forth
+            gl::UseProgram(self.blur.program);
+            for _ in 0..Self::BLUR_PASSES {
+                gl::Uniform2f(self.texel_step_uniform, 1.0 / width as f32, 0.0);
+                self.pass(&self.blur, self.blurred[0].texture, &self.blurred[1]);
+                gl::Uniform2f(self.texel_step_uniform, 0.0, 1.0 / height as f32);
+                self.pass(&self.blur, self.blurred[1].texture, &self.blurred[0]);
+            }
+
+            // and add them with the scene to whatever was there before
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.previous_fbo as _);
+            let [x, y, w, h] = self.previous_viewport;
+            gl::Viewport(x, y, w, h);
+            gl::ActiveTexture(gl::TEXTURE0 + BLOOM_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.blurred[0].texture);
+            gl::ActiveTexture(gl::TEXTURE0 + SOURCE_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.scene.texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            // added as is, the alpha it writes is only for transparent windows
+            gl::BlendFunc(gl::ONE, gl::ONE);
+            self.composite.draw();
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+
+            gl::Enable(gl::DEPTH_TEST);
+            gl_assert_ok!();
+        }
+    }
+
+    /// Draws `source` through the pass into `target`
+    unsafe fn pass(&self, pass: &Pass, source: u32, target: &Target) {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::ActiveTexture(gl::TEXTURE0 + SOURCE_TEXTURE_UNIT);
+        gl::BindTexture(gl::TEXTURE_2D, source);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+        pass.draw();
+    }
+}
@@ -0,0 +1,21 @@
+uniform sampler2D source;
+// one texel along the direction being blurred in
+uniform vec2 texelStep;
+in vec2 texCoord;
+out vec4 FragColor;
+
+// the samples either side of the center, `offset` texels away
+vec3 taps(float offset) {
+    return texture(source, texCoord + texelStep * offset).rgb
+        + texture(source, texCoord - texelStep * offset).rgb;
+}
+
+void main() {
+    // a 9 tap gaussian, which is separable so it's done horizontally then vertically
+    vec3 color = texture(source, texCoord).rgb * 0.227027
+        + taps(1.0) * 0.1945946
+        + taps(2.0) * 0.1216216
+        + taps(3.0) * 0.054054
+        + taps(4.0) * 0.016216;
+    FragColor = vec4(color, 1.0);
+}
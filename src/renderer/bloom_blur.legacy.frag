@@ -0,0 +1,18 @@
+uniform sampler2D source;
+uniform vec2 texelStep;
+varying vec2 texCoord;
+
+// see bloom_blur.frag
+vec3 taps(float offset) {
+    return texture2D(source, texCoord + texelStep * offset).rgb
+        + texture2D(source, texCoord - texelStep * offset).rgb;
+}
+
+void main() {
+    vec3 color = texture2D(source, texCoord).rgb * 0.227027
+        + taps(1.0) * 0.1945946
+        + taps(2.0) * 0.1216216
+        + taps(3.0) * 0.054054
+        + taps(4.0) * 0.016216;
+    gl_FragColor = vec4(color, 1.0);
+}
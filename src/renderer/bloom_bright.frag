@@ -0,0 +1,13 @@
+uniform sampler2D source;
+in vec2 texCoord;
+out vec4 FragColor;
+
+// how bright a pixel's brightest channel has to be before it starts to glow
+const float THRESHOLD = 0.5;
+
+void main() {
+    vec3 color = texture(source, texCoord).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    // fade in above the threshold, rather than cutting off sharply
+    FragColor = vec4(color * smoothstep(THRESHOLD, 1.0, brightness), 1.0);
+}
@@ -0,0 +1,11 @@
+uniform sampler2D source;
+varying vec2 texCoord;
+
+// see bloom_bright.frag
+const float THRESHOLD = 0.5;
+
+void main() {
+    vec3 color = texture2D(source, texCoord).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    gl_FragColor = vec4(color * smoothstep(THRESHOLD, 1.0, brightness), 1.0);
+}
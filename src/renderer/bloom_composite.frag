@@ -0,0 +1,14 @@
+uniform sampler2D scene;
+uniform sampler2D bloom;
+in vec2 texCoord;
+out vec4 FragColor;
+
+// how strongly the blurred highlights are added back on top of the scene
+const float INTENSITY = 1.5;
+
+void main() {
+    vec3 color = texture(scene, texCoord).rgb + texture(bloom, texCoord).rgb * INTENSITY;
+    // the composite isn't blended by its alpha, which is only there for transparent windows: it's
+    // how much of whatever's behind the window the glow covers
+    FragColor = vec4(color, min(max(color.r, max(color.g, color.b)), 1.0));
+}
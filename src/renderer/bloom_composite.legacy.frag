@@ -0,0 +1,11 @@
+uniform sampler2D scene;
+uniform sampler2D bloom;
+varying vec2 texCoord;
+
+// see bloom_composite.frag
+const float INTENSITY = 1.5;
+
+void main() {
+    vec3 color = texture2D(scene, texCoord).rgb + texture2D(bloom, texCoord).rgb * INTENSITY;
+    gl_FragColor = vec4(color, min(max(color.r, max(color.g, color.b)), 1.0));
+}
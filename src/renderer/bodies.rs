@@ -0,0 +1,149 @@
+use std::ffi::CString;
+use std::mem::size_of;
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use gl::types::*;
+use glam::{Mat4, Vec2};
+
+use super::debug;
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{compile_shader, link_program, select_shader, GlslVersion};
+use crate::body::Shape;
+use crate::engine::EngineContext;
+use crate::gl_assert_ok;
+
+/// Draws the rigid bodies in the fluid: each one's filled in faintly, with an anti-aliased outline
+/// (and a spoke for balls, so they can be seen rolling)
+pub struct GlBodies {
+    vao: u32,
+    vbo: u32,
+    program: u32,
+    center_uniform: i32,
+    half_size_uniform: i32,
+    angle_uniform: i32,
+    circle_uniform: i32,
+    pixel_size_uniform: i32,
+    color_uniform: i32,
+    camera_uniform: i32,
+}
+
+impl GlBodies {
+    const COLOR: [f32; 4] = [0.9, 0.9, 0.9, 0.9];
+
+    pub fn new(version: GlslVersion) -> Result<GlBodies> {
+        let vs = compile_shader(
+            version,
+            select_shader(version, shader!("body.vert"), shader!("body.legacy.vert")),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(
+            version,
+            select_shader(version, shader!("body.frag"), shader!("body.legacy.frag")),
+            gl::FRAGMENT_SHADER,
+        )?;
+        let program = link_program(vs, fs)?;
+
+        // a quad around the shape, which is scaled, turned and moved in the vertex shader
+        let points: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        let (center_uniform, half_size_uniform, angle_uniform, circle_uniform);
+        let (pixel_size_uniform, color_uniform, camera_uniform);
+        unsafe {
+            gl::UseProgram(program);
+            center_uniform = Uniform::Vec2(0.0, 0.0).create(program, "center")?;
+            half_size_uniform = Uniform::Vec2(1.0, 1.0).create(program, "halfSize")?;
+            angle_uniform = Uniform::F32(0.0).create(program, "angle")?;
+            circle_uniform = Uniform::F32(0.0).create(program, "circle")?;
+            pixel_size_uniform = Uniform::F32(0.0).create(program, "pixelSize")?;
+            let [r, g, b, a] = Self::COLOR;
+            color_uniform = Uniform::Vec4(r, g, b, a).create(program, "color")?;
+            camera_uniform = Uniform::Mat4(Mat4::IDENTITY).create(program, "camera")?;
+
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (points.len() * size_of::<f32>()) as GLsizeiptr,
+                points.as_ptr() as _,
+                gl::STATIC_DRAW,
+            );
+
+            let attr = gl::GetAttribLocation(program, CString::new("quadPosition")?.as_ptr());
+            if attr < 0 {
+                return Err(anyhow!("quadPosition GetAttribLocation -> {attr}"));
+            }
+            let n_values = 2;
+            gl::VertexAttribPointer(
+                attr as _,
+                n_values,
+                gl::FLOAT,
+                gl::FALSE,
+                n_values * size_of::<GLfloat>() as GLsizei,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(attr as _);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::PROGRAM, program, "bodies");
+        debug::label(gl::VERTEX_ARRAY, vao, "bodies");
+        debug::label(gl::BUFFER, vbo, "bodies");
+
+        Ok(GlBodies {
+            vao,
+            vbo,
+            program,
+            center_uniform,
+            half_size_uniform,
+            angle_uniform,
+            circle_uniform,
+            pixel_size_uniform,
+            color_uniform,
+            camera_uniform,
+        })
+    }
+
+    pub fn draw(&self, ctx: &EngineContext) {
+        if ctx.state.bodies.is_empty() {
+            return;
+        }
+
+        let pixel_size = ctx
+            .camera
+            .screen_len_to_world_len(ctx.surface_dimensions, Vec2::ONE)
+            .max_element();
+        let t = ctx.state.interpolation();
+
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.pixel_size_uniform, pixel_size);
+            let [r, g, b, a] = Self::COLOR;
+            gl::Uniform4f(self.color_uniform, r, g, b, a);
+            Uniform::Mat4(ctx.camera.world_to_clip()).set(self.camera_uniform);
+
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            for body in &ctx.state.bodies {
+                let (center, angle) = body.interpolated(t);
+                let (half_size, circle) = match body.shape {
+                    Shape::Circle { radius } => (Vec2::splat(radius), 1.0),
+                    Shape::Box { half_size } => (half_size, 0.0),
+                };
+                gl::Uniform2f(self.center_uniform, center.x, center.y);
+                gl::Uniform2f(self.half_size_uniform, half_size.x, half_size.y);
+                gl::Uniform1f(self.angle_uniform, angle);
+                gl::Uniform1f(self.circle_uniform, circle);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            }
+
+            gl_assert_ok!();
+        }
+    }
+}
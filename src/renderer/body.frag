@@ -0,0 +1,32 @@
+uniform vec2 halfSize;
+// 1 for a circle (with a radius of halfSize.x), or 0 for a box
+uniform float circle;
+uniform float pixelSize;
+uniform vec4 color;
+in vec2 localPosition;
+out vec4 FragColor;
+
+// how many pixels wide the outline is
+const float THICKNESS = 2.0;
+// how much of the color the inside's filled with
+const float FILL = 0.3;
+
+void main() {
+    // how far this is from the shape's edge, in world units (negative inside it)
+    vec2 excess = abs(localPosition) - halfSize;
+    float boxDist = length(max(excess, 0.0)) + min(max(excess.x, excess.y), 0.0);
+    float circleDist = length(localPosition) - halfSize.x;
+    float dist = mix(boxDist, circleDist, circle);
+
+    // a line from the centre of a circle out to its edge, so it can be seen rolling
+    float spoke = circle * step(0.0, localPosition.x) * step(abs(localPosition.y), pixelSize);
+
+    float inside = 1.0 - smoothstep(-pixelSize, 0.0, dist);
+    float outline = inside * smoothstep(-pixelSize * (THICKNESS + 1.0), -pixelSize * THICKNESS, dist);
+    float alpha = max(inside * FILL, max(outline, spoke * inside));
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    FragColor = vec4(color.rgb, color.a * alpha);
+}
@@ -0,0 +1,27 @@
+uniform vec2 halfSize;
+uniform float circle;
+uniform float pixelSize;
+uniform vec4 color;
+varying vec2 localPosition;
+
+// see body.frag
+const float THICKNESS = 2.0;
+const float FILL = 0.3;
+
+void main() {
+    vec2 excess = abs(localPosition) - halfSize;
+    float boxDist = length(max(excess, 0.0)) + min(max(excess.x, excess.y), 0.0);
+    float circleDist = length(localPosition) - halfSize.x;
+    float dist = mix(boxDist, circleDist, circle);
+
+    float spoke = circle * step(0.0, localPosition.x) * step(abs(localPosition.y), pixelSize);
+
+    float inside = 1.0 - smoothstep(-pixelSize, 0.0, dist);
+    float outline = inside * smoothstep(-pixelSize * (THICKNESS + 1.0), -pixelSize * THICKNESS, dist);
+    float alpha = max(inside * FILL, max(outline, spoke * inside));
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    gl_FragColor = vec4(color.rgb, color.a * alpha);
+}
@@ -0,0 +1,14 @@
+attribute vec2 quadPosition;
+uniform vec2 center;
+uniform vec2 halfSize;
+uniform float angle;
+uniform float pixelSize;
+uniform mat4 camera;
+varying vec2 localPosition;
+
+void main() {
+    // see body.vert
+    localPosition = quadPosition * (halfSize + pixelSize);
+    mat2 rotation = mat2(cos(angle), sin(angle), -sin(angle), cos(angle));
+    gl_Position = camera * vec4(center + rotation * localPosition, -0.1, 1.0);
+}
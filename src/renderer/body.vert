@@ -0,0 +1,19 @@
+layout(location = 0) in vec2 quadPosition;
+// the body's centre in world space, how far its shape reaches along each of its own axes, and how
+// far it's turned
+uniform vec2 center;
+uniform vec2 halfSize;
+uniform float angle;
+// how far one pixel is in world space, so there's room around the shape for its anti-aliased edge
+uniform float pixelSize;
+// takes world space to clip space
+uniform mat4 camera;
+// in world units, relative to the body's centre before it's turned
+out vec2 localPosition;
+
+void main() {
+    localPosition = quadPosition * (halfSize + pixelSize);
+    mat2 rotation = mat2(cos(angle), sin(angle), -sin(angle), cos(angle));
+    // drawn in front of the particles (which it pushes out of the way), but behind the overlays
+    gl_Position = camera * vec4(center + rotation * localPosition, -0.1, 1.0);
+}
@@ -0,0 +1,65 @@
+use super::debug;
+use crate::colormap::Colormap;
+use crate::gl_assert_ok;
+
+/// The texture unit the colormap is bound to (0 is used by the glyph texture)
+pub const COLORMAP_TEXTURE_UNIT: u32 = 1;
+
+/// Holds the current colormap in a texture that's a single row of pixels (1D textures aren't
+/// available in GLES), so shaders can sample it with a `sampler2D` on `COLORMAP_TEXTURE_UNIT`
+pub struct GlColormap {
+    texture: u32,
+    // the name of the colormap in `texture`
+    name: Option<String>,
+}
+
+impl GlColormap {
+    pub fn new() -> GlColormap {
+        let mut texture = 0;
+        unsafe {
+            gl::GenTextures(1, &mut texture);
+            gl::ActiveTexture(gl::TEXTURE0 + COLORMAP_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::TEXTURE, texture, "colormap");
+
+        GlColormap {
+            texture,
+            name: None,
+        }
+    }
+
+    /// Binds the colormap's texture, uploading it first if it's changed
+    pub fn bind(&mut self, colormap: &Colormap) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + COLORMAP_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+
+            if self.name.as_ref() != Some(&colormap.name) {
+                let pixels = colormap.to_rgba();
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA as _,
+                    Colormap::RESOLUTION as _,
+                    1,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as _,
+                );
+                self.name = Some(colormap.name.clone());
+            }
+
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl_assert_ok!();
+        }
+    }
+}
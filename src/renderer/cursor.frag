@@ -0,0 +1,22 @@
+uniform vec4 color;
+// the radius of the ring in pixels, used to keep the edges one pixel wide
+uniform float pixelRadius;
+in vec2 circlePosition;
+out vec4 FragColor;
+
+const float THICKNESS = 2.0;
+
+void main() {
+    float pixel = 1.0 / pixelRadius;
+    float dist = length(circlePosition);
+
+    // coverage of a ring just inside the edge of the circle, with anti-aliased edges
+    float outer = 1.0 - smoothstep(1.0 - pixel, 1.0, dist);
+    float inner = smoothstep(1.0 - pixel * (THICKNESS + 1.0), 1.0 - pixel * THICKNESS, dist);
+    float alpha = outer * inner;
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    FragColor = vec4(color.rgb, color.a * alpha);
+}
@@ -0,0 +1,20 @@
+uniform vec4 color;
+// see cursor.frag
+uniform float pixelRadius;
+varying vec2 circlePosition;
+
+const float THICKNESS = 2.0;
+
+void main() {
+    float pixel = 1.0 / pixelRadius;
+    float dist = length(circlePosition);
+
+    float outer = 1.0 - smoothstep(1.0 - pixel, 1.0, dist);
+    float inner = smoothstep(1.0 - pixel * (THICKNESS + 1.0), 1.0 - pixel * THICKNESS, dist);
+    float alpha = outer * inner;
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    gl_FragColor = vec4(color.rgb, color.a * alpha);
+}
@@ -0,0 +1,11 @@
+attribute vec2 quadPosition;
+uniform vec2 center;
+uniform float radius;
+uniform mat4 camera;
+varying vec2 circlePosition;
+
+void main() {
+    // drawn in front of everything else
+    gl_Position = camera * vec4(center + quadPosition * radius, -0.5, 1.0);
+    circlePosition = quadPosition;
+}
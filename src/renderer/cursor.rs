@@ -0,0 +1,166 @@
+use std::ffi::CString;
+use std::mem::size_of;
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use gl::types::*;
+use glam::Mat4;
+
+use super::debug;
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{compile_shader, link_program, select_shader, GlslVersion};
+use crate::engine::{EngineContext, Interaction};
+use crate::gl_assert_ok;
+
+/// Draws an anti-aliased ring around the cursor and each interaction showing the radius of the
+/// interaction, colored by its type
+pub struct GlCursor {
+    vao: u32,
+    vbo: u32,
+    program: u32,
+    center_uniform: i32,
+    radius_uniform: i32,
+    pixel_radius_uniform: i32,
+    color_uniform: i32,
+    camera_uniform: i32,
+}
+
+impl GlCursor {
+    const IDLE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.5];
+    const REPEL_COLOR: [f32; 4] = [1.0, 0.4, 0.3, 0.9];
+    const SUCK_COLOR: [f32; 4] = [0.3, 0.6, 1.0, 0.9];
+    const DRAG_COLOR: [f32; 4] = [0.4, 1.0, 0.4, 0.9];
+    const PAINT_COLOR: [f32; 4] = [1.0, 0.9, 0.3, 0.9];
+    const SWIRL_COLOR: [f32; 4] = [0.8, 0.4, 1.0, 0.9];
+    const POUR_COLOR: [f32; 4] = [0.3, 0.9, 0.9, 0.9];
+
+    pub fn new(version: GlslVersion) -> Result<GlCursor> {
+        let vs = compile_shader(
+            version,
+            select_shader(
+                version,
+                shader!("cursor.vert"),
+                shader!("cursor.legacy.vert"),
+            ),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(
+            version,
+            select_shader(
+                version,
+                shader!("cursor.frag"),
+                shader!("cursor.legacy.frag"),
+            ),
+            gl::FRAGMENT_SHADER,
+        )?;
+        let program = link_program(vs, fs)?;
+
+        // a quad around the unit circle, which is scaled and moved in the vertex shader
+        let points: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        let (center_uniform, radius_uniform, pixel_radius_uniform, color_uniform, camera_uniform);
+        unsafe {
+            gl::UseProgram(program);
+            center_uniform = Uniform::Vec2(0.0, 0.0).create(program, "center")?;
+            radius_uniform = Uniform::F32(0.0).create(program, "radius")?;
+            pixel_radius_uniform = Uniform::F32(1.0).create(program, "pixelRadius")?;
+            let [r, g, b, a] = Self::IDLE_COLOR;
+            color_uniform = Uniform::Vec4(r, g, b, a).create(program, "color")?;
+            camera_uniform = Uniform::Mat4(Mat4::IDENTITY).create(program, "camera")?;
+
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (points.len() * size_of::<f32>()) as GLsizeiptr,
+                points.as_ptr() as _,
+                gl::STATIC_DRAW,
+            );
+
+            let attr = gl::GetAttribLocation(program, CString::new("quadPosition")?.as_ptr());
+            if attr < 0 {
+                return Err(anyhow!("quadPosition GetAttribLocation -> {attr}"));
+            }
+            let n_values = 2;
+            gl::VertexAttribPointer(
+                attr as _,
+                n_values,
+                gl::FLOAT,
+                gl::FALSE,
+                n_values * size_of::<GLfloat>() as GLsizei,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(attr as _);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::PROGRAM, program, "cursor");
+        debug::label(gl::VERTEX_ARRAY, vao, "cursor");
+        debug::label(gl::BUFFER, vbo, "cursor");
+
+        Ok(GlCursor {
+            vao,
+            vbo,
+            program,
+            center_uniform,
+            radius_uniform,
+            pixel_radius_uniform,
+            color_uniform,
+            camera_uniform,
+        })
+    }
+
+    /// The color each type of interaction is drawn in
+    pub(super) fn color(interaction: &Interaction) -> [f32; 4] {
+        match interaction {
+            Interaction::Repel(_) => Self::REPEL_COLOR,
+            Interaction::Suck(_) => Self::SUCK_COLOR,
+            Interaction::Drag(..) => Self::DRAG_COLOR,
+            Interaction::Paint(_) => Self::PAINT_COLOR,
+            Interaction::Swirl(_) => Self::SWIRL_COLOR,
+            Interaction::Pour(..) => Self::POUR_COLOR,
+        }
+    }
+
+    /// Draws a ring around each interaction, and around the cursor if there isn't one there already
+    pub fn draw(&self, ctx: &EngineContext) {
+        let idle = ctx
+            .cursor
+            .filter(|cursor| !ctx.interactions.iter().any(|i| i.pos() == *cursor))
+            .map(|cursor| (cursor, Self::IDLE_COLOR));
+        let rings = ctx
+            .interactions
+            .iter()
+            .map(|interaction| (interaction.pos(), Self::color(interaction)));
+
+        let view = ctx.camera.view();
+        let radius = ctx.state.settings.interaction_radius;
+        let pixel_radius = f32::min(
+            radius / view.w * ctx.surface_dimensions.width as f32,
+            radius / view.h * ctx.surface_dimensions.height as f32,
+        );
+
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::Uniform1f(self.radius_uniform, radius);
+            Uniform::Mat4(ctx.camera.world_to_clip()).set(self.camera_uniform);
+            gl::Uniform1f(self.pixel_radius_uniform, pixel_radius.max(1.0));
+
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            for (center, [r, g, b, a]) in idle.into_iter().chain(rings) {
+                gl::Uniform2f(self.center_uniform, center.x, center.y);
+                gl::Uniform4f(self.color_uniform, r, g, b, a);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            }
+
+            gl_assert_ok!();
+        }
+    }
+}
@@ -0,0 +1,13 @@
+layout(location = 0) in vec2 quadPosition;
+// the circle's center and radius in world space
+uniform vec2 center;
+uniform float radius;
+// takes world space to clip space
+uniform mat4 camera;
+out vec2 circlePosition;
+
+void main() {
+    // drawn in front of everything else
+    gl_Position = camera * vec4(center + quadPosition * radius, -0.5, 1.0);
+    circlePosition = quadPosition;
+}
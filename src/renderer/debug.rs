@@ -0,0 +1,153 @@
+//! Annotations for graphics debuggers (RenderDoc, apitrace, etc) and driver message logging via
+//! `KHR_debug`.
+//!
+//! These are all no-ops if the extension isn't available.
+
+use std::ffi::{c_void, CStr, CString};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+use tracing::span::EnteredSpan;
+use tracing::Level;
+
+use super::utils::{has_extension, GlslVersion};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Checks if `KHR_debug` is available - gl must already be loaded. In `safe_mode` it's treated as
+/// unavailable, since a driver's debug output is one more thing for it to trip over
+pub fn init(version: GlslVersion, safe_mode: bool) {
+    let enabled = !safe_mode
+        && gl::PushDebugGroup::is_loaded()
+        && gl::PopDebugGroup::is_loaded()
+        && gl::ObjectLabel::is_loaded()
+        && has_extension(version, "GL_KHR_debug");
+
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    if enabled && gl::DebugMessageCallback::is_loaded() && gl::DebugMessageControl::is_loaded() {
+        unsafe {
+            gl::Enable(gl::DEBUG_OUTPUT);
+            // makes sure messages are emitted from the call that caused them
+            if cfg!(debug_assertions) {
+                gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            }
+
+            gl::DebugMessageCallback(Some(debug_message_callback), ptr::null());
+
+            // notifications are very chatty, so don't even ask for them unless they'd be logged
+            let notifications = tracing::enabled!(target: "gl", Level::DEBUG);
+            gl::DebugMessageControl(
+                gl::DONT_CARE,
+                gl::DONT_CARE,
+                gl::DEBUG_SEVERITY_NOTIFICATION,
+                0,
+                ptr::null(),
+                notifications as _,
+            );
+        }
+    }
+}
+
+/// Routes driver messages through `tracing` under the `gl` target, so they can be filtered with
+/// `RUST_LOG`, e.g. `RUST_LOG=gl=debug`
+extern "system" fn debug_message_callback(
+    source: GLenum,
+    ty: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    _length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    let source = match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    };
+    let ty = match ty {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behaviour",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behaviour",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    };
+
+    match severity {
+        gl::DEBUG_SEVERITY_HIGH => {
+            tracing::error!(target: "gl", source, ty, id, "{message}")
+        }
+        gl::DEBUG_SEVERITY_MEDIUM => {
+            tracing::warn!(target: "gl", source, ty, id, "{message}")
+        }
+        gl::DEBUG_SEVERITY_LOW => {
+            tracing::info!(target: "gl", source, ty, id, "{message}")
+        }
+        _ => tracing::debug!(target: "gl", source, ty, id, "{message}"),
+    }
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Names a GL object, e.g. `label(gl::BUFFER, vbo, "particles")`
+pub fn label(identifier: GLenum, name: GLuint, label: &str) {
+    if !enabled() {
+        return;
+    }
+
+    let label = CString::new(label).unwrap();
+    unsafe {
+        gl::ObjectLabel(identifier, name, -1, label.as_ptr());
+    }
+}
+
+/// Groups all GL calls made until this is dropped, which are also timed by the profiler as a
+/// render pass
+#[must_use]
+pub struct DebugGroup {
+    pushed: bool,
+    _span: EnteredSpan,
+}
+
+impl DebugGroup {
+    pub fn push(name: &str) -> DebugGroup {
+        let span = tracing::debug_span!("render pass", pass = name).entered();
+        if !enabled() {
+            return DebugGroup {
+                pushed: false,
+                _span: span,
+            };
+        }
+
+        let name = CString::new(name).unwrap();
+        unsafe {
+            gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, -1, name.as_ptr());
+        }
+
+        DebugGroup {
+            pushed: true,
+            _span: span,
+        }
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if self.pushed {
+            unsafe {
+                gl::PopDebugGroup();
+            }
+        }
+    }
+}
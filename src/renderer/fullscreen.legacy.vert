@@ -0,0 +1,8 @@
+attribute vec2 quadPosition;
+varying vec2 texCoord;
+
+void main() {
+    // see fullscreen.vert
+    gl_Position = vec4(quadPosition, 0.0, 1.0);
+    texCoord = (quadPosition + 1.0) * 0.5;
+}
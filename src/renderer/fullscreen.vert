@@ -0,0 +1,8 @@
+layout(location = 0) in vec2 quadPosition;
+out vec2 texCoord;
+
+void main() {
+    gl_Position = vec4(quadPosition, 0.0, 1.0);
+    // the framebuffers are drawn to and read from the same way up, so there's nothing to flip
+    texCoord = (quadPosition + 1.0) * 0.5;
+}
@@ -1,5 +1,3 @@
-#version 150
-
 uniform sampler2D font_tex;
 
 in vec2 f_tex_pos;
@@ -0,0 +1,23 @@
+// Unlike glyph.vert, the quads are expanded on the CPU since there's no instancing or gl_VertexID
+
+const mat4 INVERT_Y_AXIS = mat4(
+    vec4(1.0, 0.0, 0.0, 0.0),
+    vec4(0.0, -1.0, 0.0, 0.0),
+    vec4(0.0, 0.0, 1.0, 0.0),
+    vec4(0.0, 0.0, 0.0, 1.0)
+);
+
+uniform mat4 transform;
+
+attribute vec3 pos;
+attribute vec2 tex_pos;
+attribute vec4 color;
+
+varying vec2 f_tex_pos;
+varying vec4 f_color;
+
+void main() {
+    f_tex_pos = tex_pos;
+    f_color = color;
+    gl_Position = INVERT_Y_AXIS * transform * vec4(pos, 1.0);
+}
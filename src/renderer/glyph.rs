@@ -5,23 +5,38 @@ use std::ffi::CString;
 use std::{mem, ptr};
 
 use anyhow::{anyhow, Result};
-use gl::types::{GLfloat, GLint, GLsizeiptr, GLuint};
+use gl::types::{GLenum, GLfloat, GLint, GLsizeiptr, GLuint};
 use glyph_brush::ab_glyph::{point, Rect};
 use winit::dpi::PhysicalSize;
 
-use super::{compile_shader, link_program};
+use super::shaders::shader;
+use super::utils::{select_shader, GlslVersion};
+use super::{compile_shader, debug, link_program};
 use crate::gl_assert_ok;
 
 /// `[left_top * 3, right_bottom * 2, tex_left_top * 2, tex_right_bottom * 2, color * 4]`
 pub type GlGlyphVertex = [GLfloat; 13];
 
+/// `[pos * 3, tex_pos * 2, color * 4]`, used on legacy versions without instancing
+type GlLegacyGlyphVertex = [GLfloat; 9];
+
+/// Not present in the core profile bindings, but needed in place of `RED` for GLES 2.0
+const LUMINANCE: GLenum = 0x1909;
+
 /// The texture used to cache drawn glyphs
 pub struct GlGlyphTexture {
     pub gl_texture: GLuint,
 }
 
 impl GlGlyphTexture {
-    pub fn new((width, height): (u32, u32)) -> Self {
+    pub fn new(version: GlslVersion, (width, height): (u32, u32)) -> Self {
+        // single channel textures are only available as `LUMINANCE` in legacy versions
+        let (internal_format, format) = if version.is_legacy() {
+            (LUMINANCE, LUMINANCE)
+        } else {
+            (gl::R8, gl::RED)
+        };
+
         let mut gl_texture = 0;
         unsafe {
             // Create a texture for the glyphs
@@ -36,15 +51,16 @@ impl GlGlyphTexture {
             gl::TexImage2D(
                 gl::TEXTURE_2D,
                 0,
-                gl::RED as _,
+                internal_format as _,
                 width as _,
                 height as _,
                 0,
-                gl::RED,
+                format,
                 gl::UNSIGNED_BYTE,
                 ptr::null(),
             );
             gl_assert_ok!();
+            debug::label(gl::TEXTURE, gl_texture, "glyph cache");
 
             Self { gl_texture }
         }
@@ -69,14 +85,27 @@ pub struct GlTextPipe {
     vertex_buffer_len: usize,
 
     window_size: PhysicalSize<u32>,
+
+    // on legacy versions each glyph is expanded into two triangles before upload
+    legacy: bool,
+    legacy_vertices: Vec<GlLegacyGlyphVertex>,
 }
 
 impl GlTextPipe {
-    pub fn new(window_size: PhysicalSize<u32>) -> Result<Self> {
+    pub fn new(version: GlslVersion, window_size: PhysicalSize<u32>) -> Result<Self> {
         let (w, h) = (window_size.width as f32, window_size.height as f32);
-
-        let vs = compile_shader(include_str!("glyph.vert"), gl::VERTEX_SHADER)?;
-        let fs = compile_shader(include_str!("glyph.frag"), gl::FRAGMENT_SHADER)?;
+        let legacy = version.is_legacy();
+
+        let vs = compile_shader(
+            version,
+            select_shader(version, shader!("glyph.vert"), shader!("glyph.legacy.vert")),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(
+            version,
+            select_shader(version, shader!("glyph.frag"), shader!("glyph.legacy.frag")),
+            gl::FRAGMENT_SHADER,
+        )?;
         let program = link_program(vs, fs)?;
 
         let mut vao = 0;
@@ -93,7 +122,9 @@ impl GlTextPipe {
 
             // Use shader program
             gl::UseProgram(program);
-            gl::BindFragDataLocation(program, 0, CString::new("out_color")?.as_ptr());
+            if gl::BindFragDataLocation::is_loaded() {
+                gl::BindFragDataLocation(program, 0, CString::new("out_color")?.as_ptr());
+            }
 
             // Specify the layout of the vertex data
             let uniform = gl::GetUniformLocation(program, CString::new("transform")?.as_ptr());
@@ -103,14 +134,26 @@ impl GlTextPipe {
             let transform = ortho(0.0, w, 0.0, h, 1.0, -1.0);
             gl::UniformMatrix4fv(uniform, 1, 0, transform.as_ptr());
 
+            let (attributes, stride): (&[(&str, i32)], _) = if legacy {
+                (
+                    &[("pos", 3), ("tex_pos", 2), ("color", 4)],
+                    mem::size_of::<GlLegacyGlyphVertex>(),
+                )
+            } else {
+                (
+                    &[
+                        ("left_top", 3),
+                        ("right_bottom", 2),
+                        ("tex_left_top", 2),
+                        ("tex_right_bottom", 2),
+                        ("color", 4),
+                    ],
+                    mem::size_of::<GlGlyphVertex>(),
+                )
+            };
+
             let mut offset = 0;
-            for (v_field, float_count) in &[
-                ("left_top", 3),
-                ("right_bottom", 2),
-                ("tex_left_top", 2),
-                ("tex_right_bottom", 2),
-                ("color", 4),
-            ] {
+            for (v_field, float_count) in attributes {
                 let attr = gl::GetAttribLocation(program, CString::new(*v_field)?.as_ptr());
                 if attr < 0 {
                     return Err(anyhow!("{v_field} GetAttribLocation -> {attr}"));
@@ -120,11 +163,13 @@ impl GlTextPipe {
                     *float_count,
                     gl::FLOAT,
                     gl::FALSE as _,
-                    mem::size_of::<GlGlyphVertex>() as _,
+                    stride as _,
                     offset as _,
                 );
                 gl::EnableVertexAttribArray(attr as _);
-                gl::VertexAttribDivisor(attr as _, 1); // Important for use with DrawArraysInstanced
+                if !legacy {
+                    gl::VertexAttribDivisor(attr as _, 1); // Important for use with DrawArraysInstanced
+                }
 
                 offset += float_count * 4;
             }
@@ -132,11 +177,17 @@ impl GlTextPipe {
             // Enabled alpha blending
             gl::Enable(gl::BLEND);
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
-            // Use srgb for consistency with other examples
-            gl::Enable(gl::FRAMEBUFFER_SRGB);
+            // Use srgb for consistency with other examples (GLES has no way to toggle this)
+            if !version.is_es() {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            }
             gl::ClearColor(0.02, 0.02, 0.02, 1.0);
             gl_assert_ok!();
 
+            debug::label(gl::PROGRAM, program, "text");
+            debug::label(gl::VERTEX_ARRAY, vao, "text");
+            debug::label(gl::BUFFER, vbo, "text");
+
             uniform
         };
 
@@ -149,10 +200,25 @@ impl GlTextPipe {
             vertex_count: 0,
             vertex_buffer_len: 0,
             window_size,
+            legacy,
+            legacy_vertices: vec![],
         })
     }
 
     pub fn upload_vertices(&mut self, vertices: &[GlGlyphVertex]) {
+        if self.legacy {
+            self.legacy_vertices.clear();
+            self.legacy_vertices
+                .extend(vertices.iter().flat_map(expand_legacy_vertex));
+            let legacy_vertices = std::mem::take(&mut self.legacy_vertices);
+            self.upload(&legacy_vertices);
+            self.legacy_vertices = legacy_vertices;
+        } else {
+            self.upload(vertices);
+        }
+    }
+
+    fn upload<T>(&mut self, vertices: &[T]) {
         // Draw new vertices
         self.vertex_count = vertices.len();
 
@@ -162,7 +228,7 @@ impl GlTextPipe {
             if self.vertex_buffer_len < self.vertex_count {
                 gl::BufferData(
                     gl::ARRAY_BUFFER,
-                    (self.vertex_count * mem::size_of::<GlGlyphVertex>()) as GLsizeiptr,
+                    (self.vertex_count * mem::size_of::<T>()) as GLsizeiptr,
                     vertices.as_ptr() as _,
                     gl::DYNAMIC_DRAW,
                 );
@@ -171,7 +237,7 @@ impl GlTextPipe {
                 gl::BufferSubData(
                     gl::ARRAY_BUFFER,
                     0,
-                    (self.vertex_count * mem::size_of::<GlGlyphVertex>()) as GLsizeiptr,
+                    (self.vertex_count * mem::size_of::<T>()) as GLsizeiptr,
                     vertices.as_ptr() as _,
                 );
             }
@@ -204,8 +270,12 @@ impl GlTextPipe {
         unsafe {
             gl::UseProgram(self.program);
             gl::BindVertexArray(self.vao);
-            // If implementing this yourself, make sure to set VertexAttribDivisor as well
-            gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, self.vertex_count as _);
+            if self.legacy {
+                gl::DrawArrays(gl::TRIANGLES, 0, self.vertex_count as _);
+            } else {
+                // If implementing this yourself, make sure to set VertexAttribDivisor as well
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4, self.vertex_count as _);
+            }
             gl_assert_ok!();
         }
     }
@@ -235,6 +305,26 @@ pub fn ortho(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32)
     ]
 }
 
+/// Expands a glyph into the two triangles that `glyph.vert` would generate from it
+fn expand_legacy_vertex(v: &GlGlyphVertex) -> [GlLegacyGlyphVertex; 6] {
+    let [left, top, z, right, bottom, tex_left, tex_top, tex_right, tex_bottom, r, g, b, a] = *v;
+    let corner = |x, y, u, v| [x, y, z, u, v, r, g, b, a];
+
+    let left_top = corner(left, top, tex_left, tex_top);
+    let right_top = corner(right, top, tex_right, tex_top);
+    let left_bottom = corner(left, bottom, tex_left, tex_bottom);
+    let right_bottom = corner(right, bottom, tex_right, tex_bottom);
+
+    [
+        left_top,
+        right_top,
+        left_bottom,
+        left_bottom,
+        right_top,
+        right_bottom,
+    ]
+}
+
 #[inline]
 pub fn to_vertex(
     glyph_brush::GlyphVertex {
@@ -1,5 +1,3 @@
-#version 150
-
 const mat4 INVERT_Y_AXIS = mat4(
     vec4(1.0, 0.0, 0.0, 0.0),
     vec4(0.0, -1.0, 0.0, 0.0),
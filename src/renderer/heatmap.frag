@@ -0,0 +1,12 @@
+uniform sampler2D density;
+uniform sampler2D colormap;
+in vec2 texCoord;
+out vec4 FragColor;
+
+// dimmed so the particles stand out against it
+const float BRIGHTNESS = 0.35;
+
+void main() {
+    float value = texture(density, texCoord).r;
+    FragColor = vec4(texture(colormap, vec2(value, 0.5)).rgb * BRIGHTNESS, 1.0);
+}
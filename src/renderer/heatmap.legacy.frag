@@ -0,0 +1,11 @@
+uniform sampler2D density;
+uniform sampler2D colormap;
+varying vec2 texCoord;
+
+// see heatmap.frag
+const float BRIGHTNESS = 0.35;
+
+void main() {
+    float value = texture2D(density, texCoord).r;
+    gl_FragColor = vec4(texture2D(colormap, vec2(value, 0.5)).rgb * BRIGHTNESS, 1.0);
+}
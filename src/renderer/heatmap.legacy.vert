@@ -0,0 +1,10 @@
+attribute vec2 quadPosition;
+uniform vec4 bounds;
+uniform mat4 camera;
+varying vec2 texCoord;
+
+void main() {
+    // see heatmap.vert
+    gl_Position = camera * vec4(bounds.xy + quadPosition * bounds.zw, 0.5, 1.0);
+    texCoord = quadPosition;
+}
@@ -0,0 +1,172 @@
+use std::ffi::CString;
+use std::mem::size_of;
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use gl::types::*;
+use glam::{Mat4, Vec2};
+
+use super::colormap::COLORMAP_TEXTURE_UNIT;
+use super::debug;
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{compile_shader, link_program, select_shader, GlslVersion};
+use crate::engine::EngineContext;
+use crate::gl_assert_ok;
+use crate::rect::Rect;
+
+/// The texture unit the sampled densities are bound to
+const DENSITY_TEXTURE_UNIT: u32 = 2;
+
+/// Draws the fluid's density as a background behind the particles, colored with the colormap
+pub struct GlHeatmap {
+    vao: u32,
+    vbo: u32,
+    program: u32,
+    texture: u32,
+    bounds_uniform: i32,
+    camera_uniform: i32,
+}
+
+impl GlHeatmap {
+    /// The size of the cells the density is sampled on, in world units
+    const CELL_SIZE: f32 = 0.25;
+
+    pub fn new(version: GlslVersion) -> Result<GlHeatmap> {
+        let vs = compile_shader(
+            version,
+            select_shader(
+                version,
+                shader!("heatmap.vert"),
+                shader!("heatmap.legacy.vert"),
+            ),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(
+            version,
+            select_shader(
+                version,
+                shader!("heatmap.frag"),
+                shader!("heatmap.legacy.frag"),
+            ),
+            gl::FRAGMENT_SHADER,
+        )?;
+        let program = link_program(vs, fs)?;
+
+        // a quad that's scaled to cover the world in the vertex shader
+        let points: [f32; 8] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        let mut texture = 0;
+        let (bounds_uniform, camera_uniform);
+        unsafe {
+            gl::UseProgram(program);
+            Uniform::Int(DENSITY_TEXTURE_UNIT as _).create(program, "density")?;
+            Uniform::Int(COLORMAP_TEXTURE_UNIT as _).create(program, "colormap")?;
+            bounds_uniform = Uniform::Vec4(0.0, 0.0, 1.0, 1.0).create(program, "bounds")?;
+            camera_uniform = Uniform::Mat4(Mat4::IDENTITY).create(program, "camera")?;
+
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (points.len() * size_of::<f32>()) as GLsizeiptr,
+                points.as_ptr() as _,
+                gl::STATIC_DRAW,
+            );
+
+            let attr = gl::GetAttribLocation(program, CString::new("quadPosition")?.as_ptr());
+            if attr < 0 {
+                return Err(anyhow!("quadPosition GetAttribLocation -> {attr}"));
+            }
+            let n_values = 2;
+            gl::VertexAttribPointer(
+                attr as _,
+                n_values,
+                gl::FLOAT,
+                gl::FALSE,
+                n_values * size_of::<GLfloat>() as GLsizei,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(attr as _);
+
+            gl::GenTextures(1, &mut texture);
+            gl::ActiveTexture(gl::TEXTURE0 + DENSITY_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::PROGRAM, program, "heatmap");
+        debug::label(gl::VERTEX_ARRAY, vao, "heatmap");
+        debug::label(gl::BUFFER, vbo, "heatmap");
+        debug::label(gl::TEXTURE, texture, "heatmap density");
+
+        Ok(GlHeatmap {
+            vao,
+            vbo,
+            program,
+            texture,
+            bounds_uniform,
+            camera_uniform,
+        })
+    }
+
+    /// Samples the density on a grid and draws it, expects the colormap to be bound already
+    pub fn draw(&self, ctx: &EngineContext) {
+        let bounding_box = &ctx.state.bounding_box;
+        let columns = (bounding_box.w / Self::CELL_SIZE).ceil() as usize;
+        let rows = (bounding_box.h / Self::CELL_SIZE).ceil() as usize;
+
+        // the target density is in the middle, like `ColorField::Density`
+        let target_density = ctx.state.settings.target_density;
+        let mut pixels = Vec::with_capacity(columns * rows * 4);
+        for row in 0..rows {
+            for column in 0..columns {
+                let pos = Vec2::new(
+                    bounding_box.x + (column as f32 + 0.5) * Self::CELL_SIZE,
+                    bounding_box.y + (row as f32 + 0.5) * Self::CELL_SIZE,
+                );
+                let density = ctx.state.sample_density(pos);
+                let value = 0.5 + (density - target_density) / target_density;
+                let value = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                pixels.extend([value, value, value, 255]);
+            }
+        }
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + DENSITY_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as _,
+                columns as _,
+                rows as _,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as _,
+            );
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            gl::UseProgram(self.program);
+            let Rect { x, y, w, h } = ctx.state.bounding_box;
+            gl::Uniform4f(self.bounds_uniform, x, y, w, h);
+            Uniform::Mat4(ctx.camera.world_to_clip()).set(self.camera_uniform);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+
+            gl_assert_ok!();
+        }
+    }
+}
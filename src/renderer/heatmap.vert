@@ -0,0 +1,14 @@
+// a corner of the world, from 0,0 at the top left to 1,1 at the bottom right
+layout(location = 0) in vec2 quadPosition;
+// the world's bounding box: x, y, width, height
+uniform vec4 bounds;
+// takes world space to clip space
+uniform mat4 camera;
+out vec2 texCoord;
+
+void main() {
+    // drawn behind everything else
+    gl_Position = camera * vec4(bounds.xy + quadPosition * bounds.zw, 0.5, 1.0);
+    // the texture's first row is the top of the world
+    texCoord = quadPosition;
+}
@@ -0,0 +1,161 @@
+//! Offscreen rendering, and reading the results back so they can be saved as images.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use winit::dpi::PhysicalSize;
+
+use super::debug;
+
+/// An 8-bit RGBA image, with the top row first
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// An image from pixels read back from gl, whose rows start from the bottom
+    pub(super) fn from_gl_rows(dimensions: PhysicalSize<u32>, pixels: &[u8]) -> Image {
+        let row_len = (dimensions.width * 4) as usize;
+        let pixels = pixels
+            .chunks_exact(row_len.max(1))
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+
+        Image {
+            width: dimensions.width,
+            height: dimensions.height,
+            pixels,
+        }
+    }
+
+    /// Averages blocks of pixels so the image is at most `max_width` pixels wide
+    pub fn downscale(&self, max_width: u32) -> Image {
+        let factor = self.width.div_ceil(max_width.max(1)).max(1);
+        let width = self.width / factor;
+        let height = self.height / factor;
+
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0u32; 4];
+                for sy in 0..factor {
+                    for sx in 0..factor {
+                        let idx = (((y * factor + sy) * self.width + x * factor + sx) * 4) as usize;
+                        for (c, sum) in sum.iter_mut().enumerate() {
+                            *sum += self.pixels[idx + c] as u32;
+                        }
+                    }
+                }
+                pixels.extend(sum.map(|sum| (sum / (factor * factor)) as u8));
+            }
+        }
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()?
+            .write_image_data(&self.pixels)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Calls `draw` with an offscreen framebuffer of the given size bound, and reads back what it drew
+pub fn render_offscreen(dimensions: PhysicalSize<u32>, draw: impl FnOnce()) -> Result<Image> {
+    if !gl::GenFramebuffers::is_loaded() || !gl::GenRenderbuffers::is_loaded() {
+        bail!("framebuffer objects are not supported");
+    }
+
+    let (width, height) = (dimensions.width as i32, dimensions.height as i32);
+    let mut image = None;
+    let (mut fbo, mut renderbuffers, mut viewport) = (0, [0; 2], [0; 4]);
+    unsafe {
+        gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+
+        gl::GenFramebuffers(1, &mut fbo);
+        gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+        debug::label(gl::FRAMEBUFFER, fbo, "offscreen");
+
+        gl::GenRenderbuffers(2, renderbuffers.as_mut_ptr());
+        let [color, depth] = renderbuffers;
+        gl::BindRenderbuffer(gl::RENDERBUFFER, color);
+        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, width, height);
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::RENDERBUFFER,
+            color,
+        );
+        gl::BindRenderbuffer(gl::RENDERBUFFER, depth);
+        gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT16, width, height);
+        gl::FramebufferRenderbuffer(
+            gl::FRAMEBUFFER,
+            gl::DEPTH_ATTACHMENT,
+            gl::RENDERBUFFER,
+            depth,
+        );
+
+        let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+        if status == gl::FRAMEBUFFER_COMPLETE {
+            gl::Viewport(0, 0, width, height);
+            draw();
+
+            image = Some(read_pixels(dimensions));
+        }
+
+        // go back to drawing to the window
+        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        gl::DeleteRenderbuffers(2, renderbuffers.as_ptr());
+        gl::DeleteFramebuffers(1, &fbo);
+        let [x, y, w, h] = viewport;
+        gl::Viewport(x, y, w, h);
+
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            bail!("offscreen framebuffer is incomplete: {status:#x}");
+        }
+    }
+
+    Ok(image.unwrap())
+}
+
+/// Reads back what's been drawn to the bound framebuffer so far (for the window, that's the frame
+/// that's about to be presented)
+pub fn read_pixels(dimensions: PhysicalSize<u32>) -> Image {
+    let (width, height) = (dimensions.width as i32, dimensions.height as i32);
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(
+            0,
+            0,
+            width,
+            height,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            pixels.as_mut_ptr() as _,
+        );
+    }
+
+    Image::from_gl_rows(dimensions, &pixels)
+}
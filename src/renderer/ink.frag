@@ -0,0 +1,13 @@
+uniform sampler2D ink;
+in vec2 texCoord;
+out vec4 FragColor;
+
+// how opaque the thickest ink is, so what's behind it still shows through
+const float OPACITY = 0.6;
+
+void main() {
+    // the ink isn't blended by its alpha, which is only there for transparent windows: it's how
+    // much of whatever's behind the window the ink covers
+    vec3 color = texture(ink, texCoord).rgb * OPACITY;
+    FragColor = vec4(color, max(color.r, max(color.g, color.b)));
+}
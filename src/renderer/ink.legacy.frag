@@ -0,0 +1,10 @@
+uniform sampler2D ink;
+varying vec2 texCoord;
+
+// see ink.frag
+const float OPACITY = 0.6;
+
+void main() {
+    vec3 color = texture2D(ink, texCoord).rgb * OPACITY;
+    gl_FragColor = vec4(color, max(color.r, max(color.g, color.b)));
+}
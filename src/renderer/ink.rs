@@ -0,0 +1,156 @@
+use std::ffi::CString;
+use std::mem::size_of;
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use gl::types::*;
+use glam::Mat4;
+
+use super::debug;
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{compile_shader, link_program, select_shader, GlslVersion};
+use crate::engine::EngineContext;
+use crate::gl_assert_ok;
+use crate::rect::Rect;
+
+/// The texture unit the ink is bound to
+const INK_TEXTURE_UNIT: u32 = 5;
+
+/// Draws the ink in the fluid as a translucent layer behind the particles
+pub struct GlInk {
+    vao: u32,
+    vbo: u32,
+    program: u32,
+    texture: u32,
+    bounds_uniform: i32,
+    camera_uniform: i32,
+}
+
+impl GlInk {
+    pub fn new(version: GlslVersion) -> Result<GlInk> {
+        let vs = compile_shader(
+            version,
+            select_shader(version, shader!("ink.vert"), shader!("ink.legacy.vert")),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(
+            version,
+            select_shader(version, shader!("ink.frag"), shader!("ink.legacy.frag")),
+            gl::FRAGMENT_SHADER,
+        )?;
+        let program = link_program(vs, fs)?;
+
+        // a quad that's scaled to cover the world in the vertex shader
+        let points: [f32; 8] = [0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 1.0];
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        let mut texture = 0;
+        let (bounds_uniform, camera_uniform);
+        unsafe {
+            gl::UseProgram(program);
+            Uniform::Int(INK_TEXTURE_UNIT as _).create(program, "ink")?;
+            bounds_uniform = Uniform::Vec4(0.0, 0.0, 1.0, 1.0).create(program, "bounds")?;
+            camera_uniform = Uniform::Mat4(Mat4::IDENTITY).create(program, "camera")?;
+
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                (points.len() * size_of::<f32>()) as GLsizeiptr,
+                points.as_ptr() as _,
+                gl::STATIC_DRAW,
+            );
+
+            let attr = gl::GetAttribLocation(program, CString::new("quadPosition")?.as_ptr());
+            if attr < 0 {
+                return Err(anyhow!("quadPosition GetAttribLocation -> {attr}"));
+            }
+            let n_values = 2;
+            gl::VertexAttribPointer(
+                attr as _,
+                n_values,
+                gl::FLOAT,
+                gl::FALSE,
+                n_values * size_of::<GLfloat>() as GLsizei,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(attr as _);
+
+            gl::GenTextures(1, &mut texture);
+            gl::ActiveTexture(gl::TEXTURE0 + INK_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::PROGRAM, program, "ink");
+        debug::label(gl::VERTEX_ARRAY, vao, "ink");
+        debug::label(gl::BUFFER, vbo, "ink");
+        debug::label(gl::TEXTURE, texture, "ink");
+
+        Ok(GlInk {
+            vao,
+            vbo,
+            program,
+            texture,
+            bounds_uniform,
+            camera_uniform,
+        })
+    }
+
+    /// Uploads the ink and draws it, if there's any to see
+    pub fn draw(&self, ctx: &EngineContext) {
+        let ink = &ctx.state.ink;
+        if !ink.is_visible() {
+            return;
+        }
+
+        let pixels = ink
+            .cells()
+            .iter()
+            .flat_map(|cell| {
+                let [r, g, b] = (*cell * 255.0).round().to_array().map(|c| c as u8);
+                [r, g, b, 255]
+            })
+            .collect::<Vec<_>>();
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + INK_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as _,
+                ink.columns() as _,
+                ink.rows() as _,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as _,
+            );
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            gl::UseProgram(self.program);
+            let Rect { x, y, w, h } = ctx.state.bounding_box;
+            gl::Uniform4f(self.bounds_uniform, x, y, w, h);
+            Uniform::Mat4(ctx.camera.world_to_clip()).set(self.camera_uniform);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            // added as is, the alpha it writes is only for transparent windows
+            gl::BlendFunc(gl::ONE, gl::ONE);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+
+            gl_assert_ok!();
+        }
+    }
+}
@@ -0,0 +1,6 @@
+uniform vec4 color;
+out vec4 FragColor;
+
+void main() {
+    FragColor = color;
+}
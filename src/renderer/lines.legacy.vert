@@ -0,0 +1,7 @@
+attribute vec2 position;
+uniform mat4 camera;
+
+void main() {
+    // see lines.vert
+    gl_Position = camera * vec4(position, -0.25, 1.0);
+}
@@ -0,0 +1,108 @@
+use std::ffi::CString;
+use std::mem::{size_of, size_of_val};
+use std::ptr;
+
+use anyhow::{anyhow, Result};
+use gl::types::*;
+use glam::{Mat4, Vec2};
+
+use super::debug;
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{compile_shader, link_program, select_shader, GlslVersion};
+use crate::camera::Camera;
+use crate::gl_assert_ok;
+
+/// Draws plain line segments, for debug overlays
+pub struct GlLines {
+    vao: u32,
+    vbo: u32,
+    program: u32,
+    color_uniform: i32,
+    camera_uniform: i32,
+}
+
+impl GlLines {
+    pub fn new(version: GlslVersion) -> Result<GlLines> {
+        let vs = compile_shader(
+            version,
+            select_shader(version, shader!("lines.vert"), shader!("lines.legacy.vert")),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(
+            version,
+            select_shader(version, shader!("lines.frag"), shader!("lines.legacy.frag")),
+            gl::FRAGMENT_SHADER,
+        )?;
+        let program = link_program(vs, fs)?;
+
+        let mut vao = 0;
+        let mut vbo = 0;
+        let (color_uniform, camera_uniform);
+        unsafe {
+            gl::UseProgram(program);
+            color_uniform = Uniform::Vec4(1.0, 1.0, 1.0, 1.0).create(program, "color")?;
+            camera_uniform = Uniform::Mat4(Mat4::IDENTITY).create(program, "camera")?;
+
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let attr = gl::GetAttribLocation(program, CString::new("position")?.as_ptr());
+            if attr < 0 {
+                return Err(anyhow!("position GetAttribLocation -> {attr}"));
+            }
+            let n_values = 2;
+            gl::VertexAttribPointer(
+                attr as _,
+                n_values,
+                gl::FLOAT,
+                gl::FALSE,
+                n_values * size_of::<GLfloat>() as GLsizei,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(attr as _);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::PROGRAM, program, "lines");
+        debug::label(gl::VERTEX_ARRAY, vao, "lines");
+        debug::label(gl::BUFFER, vbo, "lines");
+
+        Ok(GlLines {
+            vao,
+            vbo,
+            program,
+            color_uniform,
+            camera_uniform,
+        })
+    }
+
+    /// Draws a line between each pair of points, which are in world space
+    pub fn draw(&self, camera: &Camera, points: &[Vec2], color: [f32; 4]) {
+        if points.is_empty() {
+            return;
+        }
+
+        let [r, g, b, a] = color;
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::Uniform4f(self.color_uniform, r, g, b, a);
+            Uniform::Mat4(camera.world_to_clip()).set(self.camera_uniform);
+
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size_of_val(points) as GLsizeiptr,
+                points.as_ptr() as _,
+                gl::STREAM_DRAW,
+            );
+            gl::DrawArrays(gl::LINES, 0, points.len() as GLsizei);
+
+            gl_assert_ok!();
+        }
+    }
+}
@@ -0,0 +1,9 @@
+// in world space
+layout(location = 0) in vec2 position;
+// takes world space to clip space
+uniform mat4 camera;
+
+void main() {
+    // drawn in front of the particles, but behind the cursor
+    gl_Position = camera * vec4(position, -0.25, 1.0);
+}
@@ -1,6 +1,26 @@
+mod bloom;
+mod bodies;
+mod colormap;
+mod cursor;
+mod debug;
 mod glyph;
+mod heatmap;
+mod image;
+mod ink;
+mod lines;
+mod overlay;
 mod particles;
+mod pass;
+mod pins;
+mod readback;
+#[cfg(feature = "shader-reload")]
+mod shader_watcher;
+#[cfg(not(feature = "shader-reload"))]
+#[path = "../disabled/shader_watcher.rs"]
+mod shader_watcher;
+mod shaders;
 mod text;
+mod trails;
 mod uniform;
 mod utils;
 
@@ -10,24 +30,60 @@ use anyhow::Result;
 use glam::Vec2;
 use glutin::display::Display;
 use glutin::prelude::*;
-use glyph_brush::{Section, Text};
+use glyph_brush::{BuiltInLineBreaker, HorizontalAlign, Layout, Section, Text, VerticalAlign};
+use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
+use self::bloom::GlBloom;
+use self::bodies::GlBodies;
+use self::colormap::GlColormap;
+use self::cursor::GlCursor;
+use self::debug::DebugGroup;
+use self::heatmap::GlHeatmap;
+pub use self::image::Image;
+use self::ink::GlInk;
+use self::overlay::GlOverlay;
 use self::particles::GlParticles;
+pub use self::particles::{ColorField, ParticleStyle};
+use self::pins::GlPins;
+use self::readback::GlReadback;
+pub use self::shader_watcher::ShaderWatcher;
 use self::text::GlText;
-use self::utils::{compile_shader, link_program};
+use self::trails::GlTrails;
+use self::utils::{compile_shader, link_program, GlslVersion};
 use crate::engine::EngineContext;
-use crate::rect::Rect;
+use crate::profiler;
 
 pub struct Renderer {
+    // holds the colormap used by the particles and heatmap
+    colormap: GlColormap,
+    // renders the density heatmap behind the particles
+    heatmap: GlHeatmap,
+    // renders the ink in the fluid, between the heatmap and the particles
+    ink: GlInk,
+    // renders the interaction radius around the cursor
+    cursor: GlCursor,
+    // renders an icon for each pinned interaction
+    pins: GlPins,
     // renders the particles
     particles: GlParticles,
+    // renders the rigid bodies in the fluid, over the particles
+    bodies: GlBodies,
+    // renders any debug overlays
+    overlay: GlOverlay,
     // renders any text on the screen
     text: GlText,
+    // makes the heatmap and particles glow, if framebuffers are supported
+    bloom: Option<GlBloom>,
+    // leaves fading trails behind the particles, if framebuffers are supported
+    trails: Option<GlTrails>,
+    // reads frames back for screenshots, clips and recordings
+    readback: GlReadback,
 }
 
 impl Renderer {
-    pub fn new(gl_display: &Display, window: &Window) -> Result<Renderer> {
+    /// In `safe_mode`, the driver's debug output isn't used (see `debug::init`)
+    pub fn new(gl_display: &Display, window: &Window, safe_mode: bool) -> Result<Renderer> {
         let dimensions = window.inner_size();
 
         // provide loader to link gl function pointers to the display
@@ -41,50 +97,405 @@ impl Renderer {
             gl::DepthFunc(gl::LESS);
         }
 
+        let version = GlslVersion::detect();
+        tracing::info!("using shaders for {version:?}");
+        debug::init(version, safe_mode);
+
         Ok(Renderer {
-            particles: GlParticles::new()?,
-            text: GlText::new(dimensions)?,
+            colormap: GlColormap::new(),
+            heatmap: GlHeatmap::new(version)?,
+            ink: GlInk::new(version)?,
+            cursor: GlCursor::new(version)?,
+            pins: GlPins::new(version)?,
+            particles: GlParticles::new(version)?,
+            bodies: GlBodies::new(version)?,
+            overlay: GlOverlay::new(version)?,
+            text: GlText::new(version, dimensions)?,
+            bloom: GlBloom::new(version)
+                .inspect_err(|e| tracing::warn!("bloom isn't available: {e:#}"))
+                .ok(),
+            trails: GlTrails::new(version)
+                .inspect_err(|e| tracing::warn!("trails aren't available: {e:#}"))
+                .ok(),
+            readback: GlReadback::new(),
         })
     }
 
+    /// Draws just the particles offscreen (without any text, overlays or the cursor), and returns
+    /// them as an image the size of the surface
+    pub fn render_particles(&mut self, ctx: &EngineContext) -> Result<Image> {
+        image::render_offscreen(ctx.surface_dimensions, || unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            self.colormap.bind(ctx.colormap);
+            self.particles.draw(ctx);
+        })
+    }
+
+    /// Draws just the particles offscreen, and returns them as an image at most `max_width` pixels
+    /// wide
+    pub fn thumbnail(&mut self, ctx: &EngineContext, max_width: u32) -> Result<Image> {
+        let _thumbnail_group = DebugGroup::push("thumbnail");
+        Ok(self.render_particles(ctx)?.downscale(max_width))
+    }
+
+    /// Whether bloom can be drawn, see `EngineContext::bloom`
+    pub fn supports_bloom(&self) -> bool {
+        self.bloom.is_some()
+    }
+
+    /// Whether trails can be drawn, see `EngineContext::trails`
+    pub fn supports_trails(&self) -> bool {
+        self.trails.is_some()
+    }
+
+    /// Starts reading back the frame that's just been drawn (before it's presented) without waiting
+    /// for it, and returns the one started before it, if there was one
+    pub fn read_frame(&mut self, surface_dimensions: PhysicalSize<u32>) -> Option<Image> {
+        self.readback.read_frame(surface_dimensions)
+    }
+
+    /// Returns the last frame started with `read_frame`, if it hasn't been returned already
+    pub fn finish_read_frame(&mut self) -> Option<Image> {
+        self.readback.finish()
+    }
+
     pub fn draw(&mut self, ctx: EngineContext) {
+        let _draw_span = tracing::debug_span!("draw").entered();
         unsafe {
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            // a transparent window only shows the fluid, over whatever's behind it
+            let alpha = if ctx.transparent { 0.0 } else { 1.0 };
+            gl::ClearColor(0.0, 0.0, 0.0, alpha);
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
 
             // draw text on screen
+            let text_group = DebugGroup::push("text");
             self.text.update_geometry(ctx.surface_dimensions);
-            self.text.draw(&[
-                // draw fps
-                Section::default()
-                    .add_text(
-                        Text::new(&format!("FPS: {:.2} VSYNC: {}", ctx.fps, ctx.vsync))
-                            .with_scale((18.0 * ctx.scale_factor).round())
-                            .with_color([1.0, 1.0, 1.0, 1.0]),
-                    )
-                    .with_bounds((
-                        ctx.surface_dimensions.width as f32,
-                        ctx.surface_dimensions.height as f32,
-                    )),
-            ]);
+            let text_scale = (18.0 * ctx.scale_factor).round();
+            let mut sections = vec![];
+
+            // draw the hud in whichever corner it's in
+            let status = ctx.hud.map(|hud| hud.text(&ctx)).unwrap_or_default();
+            if let Some(hud) = ctx.hud.filter(|_| !status.is_empty()) {
+                let (width, height) = (
+                    ctx.surface_dimensions.width as f32,
+                    ctx.surface_dimensions.height as f32,
+                );
+                let (right, bottom) = (hud.corner.is_right(), hud.corner.is_bottom());
+                let layout = Layout::default()
+                    .h_align(match right {
+                        true => HorizontalAlign::Right,
+                        false => HorizontalAlign::Left,
+                    })
+                    .v_align(match bottom {
+                        true => VerticalAlign::Bottom,
+                        false => VerticalAlign::Top,
+                    });
+                let position = (
+                    if right { width } else { 0.0 },
+                    if bottom { height } else { 0.0 },
+                );
+                sections.push(
+                    Section::default()
+                        .add_text(
+                            Text::new(&status)
+                                .with_scale(text_scale)
+                                .with_color([1.0, 1.0, 1.0, 1.0]),
+                        )
+                        .with_screen_position(position)
+                        .with_bounds((width, height))
+                        .with_layout(layout),
+                );
+            }
+
+            // draw any toast in the bottom left corner
+            if let Some(toast) = ctx.toast {
+                sections.push(
+                    Section::default()
+                        .add_text(
+                            Text::new(toast)
+                                .with_scale(text_scale)
+                                .with_color([1.0, 1.0, 0.6, 1.0]),
+                        )
+                        .with_screen_position((0.0, ctx.surface_dimensions.height as f32))
+                        .with_bounds((
+                            ctx.surface_dimensions.width as f32,
+                            ctx.surface_dimensions.height as f32,
+                        ))
+                        .with_layout(Layout::default().v_align(VerticalAlign::Bottom)),
+                );
+            }
+
+            // draw the checksum in the top right corner, so it's easy to compare between windows
+            let checksum = match ctx.overlays.checksum {
+                // the top half of the checksum is plenty to see when they differ
+                true => format!(
+                    "TICK: {} CHECKSUM: {:08x}",
+                    ctx.state.ticks,
+                    ctx.state.checksum() >> 32
+                ),
+                false => String::new(),
+            };
+            if !checksum.is_empty() {
+                sections.push(
+                    Section::default()
+                        .add_text(
+                            Text::new(&checksum)
+                                .with_scale(text_scale)
+                                .with_color([1.0, 1.0, 1.0, 1.0]),
+                        )
+                        .with_screen_position((ctx.surface_dimensions.width as f32, 0.0))
+                        .with_layout(Layout::default().h_align(HorizontalAlign::Right)),
+                );
+            }
+
+            // draw the profiler's timings below the status line
+            let profile = match ctx.overlays.profiler {
+                true => profiler::summary().join("\n"),
+                false => String::new(),
+            };
+            if !profile.is_empty() {
+                sections.push(
+                    Section::default()
+                        .add_text(
+                            Text::new(&profile)
+                                .with_scale((14.0 * ctx.scale_factor).round())
+                                .with_color([0.6, 1.0, 0.6, 1.0]),
+                        )
+                        .with_screen_position((0.0, text_scale))
+                        .with_bounds((
+                            ctx.surface_dimensions.width as f32,
+                            ctx.surface_dimensions.height as f32,
+                        )),
+                );
+            }
+
+            // draw ruler labels and the cursor's coordinates, and how many particles are off-screen
+            // while zoomed in
+            let mut labels = match ctx.overlays.ruler {
+                true => ruler_labels(&ctx),
+                false => vec![],
+            };
+            if ctx.camera.zoom() > 1.0 {
+                labels.extend(offscreen_labels(&ctx));
+            }
+            let label_scale = (12.0 * ctx.scale_factor).round();
+            for (label, position, layout) in &labels {
+                sections.push(
+                    Section::default()
+                        .add_text(
+                            Text::new(label)
+                                .with_scale(label_scale)
+                                .with_color([0.8, 0.8, 0.8, 1.0]),
+                        )
+                        .with_screen_position(*position)
+                        .with_layout(*layout),
+                );
+            }
+
+            if !ctx.fluid_only {
+                self.text.draw(&sections);
+            }
+
+            drop(text_group);
+
+            self.colormap.bind(ctx.colormap);
+
+            // the heatmap and particles are drawn offscreen for the bloom, and added on top after
+            let bloom = match (ctx.bloom, &mut self.bloom) {
+                (true, Some(bloom)) => match bloom.begin(ctx.surface_dimensions) {
+                    Ok(()) => Some(bloom),
+                    Err(e) => {
+                        tracing::error!("disabling bloom: {e:#}");
+                        self.bloom = None;
+                        None
+                    }
+                },
+                _ => None,
+            };
+
+            // draw the density heatmap, which counts as an overlay
+            if ctx.overlays.density && !ctx.fluid_only {
+                let _heatmap_group = DebugGroup::push("heatmap");
+                self.heatmap.draw(&ctx);
+            }
+
+            // draw any ink
+            let ink_group = DebugGroup::push("ink");
+            self.ink.draw(&ctx);
+            drop(ink_group);
+
+            // the particles are drawn over what's left of their trails, which are added on top after
+            let trail_length = ctx.state.settings.trail_length;
+            let trails = match (ctx.trails, &mut self.trails) {
+                (true, Some(trails)) => match trails.begin(ctx.surface_dimensions, trail_length) {
+                    Ok(()) => Some(trails),
+                    Err(e) => {
+                        tracing::error!("disabling trails: {e:#}");
+                        self.trails = None;
+                        None
+                    }
+                },
+                _ => None,
+            };
 
             // draw particles
+            let particles_group = DebugGroup::push("particles");
             self.particles.draw(&ctx);
+            drop(particles_group);
+
+            if let Some(trails) = trails {
+                trails.end();
+            }
+
+            if let Some(bloom) = bloom {
+                bloom.end();
+            }
+
+            // draw the bodies, which are part of the scene so they're still drawn with only the
+            // fluid showing
+            let bodies_group = DebugGroup::push("bodies");
+            self.bodies.draw(&ctx);
+            drop(bodies_group);
+
+            if ctx.fluid_only {
+                return;
+            }
+
+            // draw any debug overlays
+            let overlay_group = DebugGroup::push("overlay");
+            self.overlay.draw(&ctx);
+            drop(overlay_group);
+
+            let pins_group = DebugGroup::push("pins");
+            self.pins.draw(&ctx);
+            drop(pins_group);
+
+            // draw the interaction radius
+            let _cursor_group = DebugGroup::push("cursor");
+            self.cursor.draw(&ctx);
         }
     }
 }
 
-#[inline]
-pub fn world_pos_to_gl_pos(bounding_box: &Rect, world_pos: &Vec2) -> Vec2 {
-    let x = (world_pos.x - bounding_box.x) / (bounding_box.w * 0.5) - 1.0;
-    let y = (world_pos.y - bounding_box.y) / (bounding_box.h * 0.5) - 1.0;
-    Vec2::new(x, -y)
+/// Labels for each world unit along the top and left edges of the window, and the cursor's world
+/// and cell coordinates in the bottom right corner
+fn ruler_labels(ctx: &EngineContext) -> Vec<(String, (f32, f32), Layout<BuiltInLineBreaker>)> {
+    let view = ctx.camera.view();
+    let to_screen = |world_pos: Vec2| {
+        let screen_pos = ctx
+            .camera
+            .world_to_screen(ctx.surface_dimensions, world_pos);
+        (screen_pos.x, screen_pos.y)
+    };
+    // keep the labels clear of the tick marks
+    let padding = 8.0 * ctx.scale_factor;
+
+    let mut labels = vec![];
+    for x in (view.left().ceil() as i32 + 1)..(view.right().ceil() as i32) {
+        let (screen_x, _) = to_screen(Vec2::new(x as f32, view.top()));
+        labels.push((
+            x.to_string(),
+            // below the status line
+            (screen_x, 18.0 * ctx.scale_factor + padding),
+            Layout::default().h_align(HorizontalAlign::Center),
+        ));
+    }
+    for y in (view.top().ceil() as i32 + 1)..(view.bottom().ceil() as i32) {
+        let (_, screen_y) = to_screen(Vec2::new(view.left(), y as f32));
+        labels.push((
+            y.to_string(),
+            (padding, screen_y),
+            Layout::default().v_align(VerticalAlign::Center),
+        ));
+    }
+
+    if let Some(cursor) = ctx.cursor {
+        let cell = ctx.state.cell_pos(cursor);
+        labels.push((
+            format!(
+                "x: {:.2} y: {:.2} cell: {},{}",
+                cursor.x, cursor.y, cell.x, cell.y
+            ),
+            (
+                ctx.surface_dimensions.width as f32 - padding,
+                ctx.surface_dimensions.height as f32 - padding,
+            ),
+            Layout::default()
+                .h_align(HorizontalAlign::Right)
+                .v_align(VerticalAlign::Bottom),
+        ));
+    }
+
+    labels
+}
+
+/// How many particles are past each edge of the window, in the middle of that edge (and only for
+/// the edges that have any). Particles past a corner are counted for both of its edges
+fn offscreen_labels(ctx: &EngineContext) -> Vec<(String, (f32, f32), Layout<BuiltInLineBreaker>)> {
+    let counts = ctx.state.count_outside(ctx.camera.view());
+
+    let (width, height) = (
+        ctx.surface_dimensions.width as f32,
+        ctx.surface_dimensions.height as f32,
+    );
+    let padding = 8.0 * ctx.scale_factor;
+    // keep clear of the ruler's labels along the top and left edges
+    let ruler = match ctx.overlays.ruler {
+        true => 24.0 * ctx.scale_factor,
+        false => 0.0,
+    };
+    let edges = [
+        (
+            Vec2::NEG_X,
+            (padding + ruler, height * 0.5),
+            Layout::default().v_align(VerticalAlign::Center),
+        ),
+        (
+            Vec2::X,
+            (width - padding, height * 0.5),
+            Layout::default()
+                .h_align(HorizontalAlign::Right)
+                .v_align(VerticalAlign::Center),
+        ),
+        (
+            Vec2::NEG_Y,
+            // below the status line
+            (width * 0.5, 18.0 * ctx.scale_factor + padding + ruler),
+            Layout::default().h_align(HorizontalAlign::Center),
+        ),
+        (
+            Vec2::Y,
+            (width * 0.5, height - padding),
+            Layout::default()
+                .h_align(HorizontalAlign::Center)
+                .v_align(VerticalAlign::Bottom),
+        ),
+    ];
+
+    counts
+        .into_iter()
+        .zip(edges)
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, (dir, position, layout))| {
+            (
+                format!("{} {count}", direction_glyph(dir)),
+                position,
+                layout,
+            )
+        })
+        .collect()
 }
 
-#[allow(unused)]
-#[inline]
-pub fn world_len_to_gl_len(bounding_box: &Rect, world_len: f32) -> f32 {
-    let world_min = f32::min(bounding_box.x, bounding_box.y);
-    let world_max = f32::max(bounding_box.w, bounding_box.h);
-    (world_len - world_min) / (world_max - world_min)
+/// Returns an arrow glyph pointing in the direction of the given world space vector
+pub fn direction_glyph(dir: Vec2) -> char {
+    if dir == Vec2::ZERO {
+        return '·';
+    }
+
+    // world space has y pointing down, just like the screen
+    const ARROWS: [char; 8] = ['→', '↘', '↓', '↙', '←', '↖', '↑', '↗'];
+    let angle = dir.y.atan2(dir.x).rem_euclid(std::f32::consts::TAU);
+    let idx = (angle / std::f32::consts::FRAC_PI_4).round() as usize % ARROWS.len();
+    ARROWS[idx]
 }
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use glam::{IVec2, Vec2};
+
+use super::lines::GlLines;
+use super::utils::GlslVersion;
+use crate::engine::EngineContext;
+
+/// Debug overlays drawn on top of the particles
+pub struct GlOverlay {
+    lines: GlLines,
+}
+
+impl GlOverlay {
+    /// The size of the cells the velocity field is sampled on, in world units
+    const VELOCITY_CELL_SIZE: f32 = 0.5;
+    /// How many seconds of movement each velocity arrow covers
+    const VELOCITY_ARROW_TIME: f32 = 0.05;
+    const VELOCITY_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 0.6];
+
+    const GRID_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.5];
+    const OCCUPIED_CELL_COLOR: [f32; 4] = [0.2, 0.4, 0.8, 0.6];
+    const QUERIED_CELL_COLOR: [f32; 4] = [1.0, 1.0, 0.4, 0.9];
+
+    /// The length of the ruler's tick marks at each world unit, in world units (half units get
+    /// ticks half as long)
+    const RULER_TICK_LENGTH: f32 = 0.25;
+    const RULER_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 0.8];
+
+    pub fn new(version: GlslVersion) -> Result<GlOverlay> {
+        Ok(GlOverlay {
+            lines: GlLines::new(version)?,
+        })
+    }
+
+    pub fn draw(&self, ctx: &EngineContext) {
+        if ctx.overlays.grid {
+            self.draw_spatial_grid(ctx);
+        }
+        if ctx.overlays.velocity {
+            self.draw_velocity_field(ctx);
+        }
+        if ctx.overlays.ruler {
+            self.draw_ruler(ctx);
+        }
+    }
+
+    /// Draws tick marks along each edge of the window at every half world unit
+    fn draw_ruler(&self, ctx: &EngineContext) {
+        let view = ctx.camera.view();
+        // the ticks are the same size on screen however far the camera is zoomed in
+        let tick_length = Self::RULER_TICK_LENGTH / ctx.camera.zoom();
+
+        let mut points = vec![];
+        let half_units = |from: f32, to: f32| {
+            ((from * 2.0).ceil() as i32..=(to * 2.0).floor() as i32).map(|i| {
+                let length = match i % 2 {
+                    0 => tick_length,
+                    _ => tick_length * 0.5,
+                };
+                (i as f32 * 0.5, length)
+            })
+        };
+        for (x, length) in half_units(view.left(), view.right()) {
+            for (edge, dir) in [(view.top(), 1.0), (view.bottom(), -1.0)] {
+                points.push(Vec2::new(x, edge));
+                points.push(Vec2::new(x, edge + length * dir));
+            }
+        }
+        for (y, length) in half_units(view.top(), view.bottom()) {
+            for (edge, dir) in [(view.left(), 1.0), (view.right(), -1.0)] {
+                points.push(Vec2::new(edge, y));
+                points.push(Vec2::new(edge + length * dir, y));
+            }
+        }
+
+        self.lines.draw(&ctx.camera, &points, Self::RULER_COLOR);
+    }
+
+    /// Draws the spatial lookup's cells, outlining those with particles in them and highlighting the
+    /// cells queried for the neighbours of the particle nearest the cursor
+    fn draw_spatial_grid(&self, ctx: &EngineContext) {
+        let state = ctx.state;
+        let bounding_box = &state.bounding_box;
+        let cell_size = state.cell_size();
+
+        // grid lines
+        let mut points = vec![];
+        let first = state.cell_pos(Vec2::new(bounding_box.left(), bounding_box.top()));
+        let last = state.cell_pos(Vec2::new(bounding_box.right(), bounding_box.bottom()));
+        for x in first.x..=last.x + 1 {
+            let x = (x as f32 * cell_size).clamp(bounding_box.left(), bounding_box.right());
+            points.push(Vec2::new(x, bounding_box.top()));
+            points.push(Vec2::new(x, bounding_box.bottom()));
+        }
+        for y in first.y..=last.y + 1 {
+            let y = (y as f32 * cell_size).clamp(bounding_box.top(), bounding_box.bottom());
+            points.push(Vec2::new(bounding_box.left(), y));
+            points.push(Vec2::new(bounding_box.right(), y));
+        }
+        self.lines.draw(&ctx.camera, &points, Self::GRID_COLOR);
+
+        let occupied = state
+            .positions
+            .iter()
+            .map(|pos| state.cell_pos(*pos))
+            .collect::<HashSet<_>>();
+
+        let queried = ctx
+            .cursor
+            .and_then(|cursor| state.nearest_particle(cursor))
+            .map(|idx| state.neighbour_cells(state.positions[idx]).to_vec())
+            .unwrap_or_default();
+        let occupied = occupied
+            .into_iter()
+            .filter(|cell| !queried.contains(cell))
+            .collect::<Vec<_>>();
+
+        for (cells, color) in [
+            (&occupied[..], Self::OCCUPIED_CELL_COLOR),
+            (&queried[..], Self::QUERIED_CELL_COLOR),
+        ] {
+            let points = cells
+                .iter()
+                .flat_map(|cell| cell_outline(*cell, cell_size))
+                .collect::<Vec<_>>();
+            self.lines.draw(&ctx.camera, &points, color);
+        }
+    }
+
+    /// Draws an arrow in each cell of a coarse grid, showing the fluid's velocity there
+    fn draw_velocity_field(&self, ctx: &EngineContext) {
+        let bounding_box = &ctx.state.bounding_box;
+        let columns = (bounding_box.w / Self::VELOCITY_CELL_SIZE).ceil() as usize;
+        let rows = (bounding_box.h / Self::VELOCITY_CELL_SIZE).ceil() as usize;
+
+        let mut points = vec![];
+        for row in 0..rows {
+            for column in 0..columns {
+                let center = Vec2::new(
+                    bounding_box.x + (column as f32 + 0.5) * Self::VELOCITY_CELL_SIZE,
+                    bounding_box.y + (row as f32 + 0.5) * Self::VELOCITY_CELL_SIZE,
+                );
+                let velocity = ctx.state.sample_velocity(center);
+                if velocity == Vec2::ZERO {
+                    continue;
+                }
+
+                // keep the arrows within their cell
+                let arrow = (velocity * Self::VELOCITY_ARROW_TIME)
+                    .clamp_length_max(Self::VELOCITY_CELL_SIZE * 0.5);
+                let tip = center + arrow;
+                let head = -arrow * 0.3;
+
+                for (a, b) in [
+                    (center, tip),
+                    (tip, tip + Vec2::from_angle(0.5).rotate(head)),
+                    (tip, tip + Vec2::from_angle(-0.5).rotate(head)),
+                ] {
+                    points.push(a);
+                    points.push(b);
+                }
+            }
+        }
+
+        self.lines.draw(&ctx.camera, &points, Self::VELOCITY_COLOR);
+    }
+}
+
+/// Line segments around the inside of a cell, inset so they don't overlap the grid lines
+fn cell_outline(cell: IVec2, cell_size: f32) -> [Vec2; 8] {
+    let inset = cell_size * 0.08;
+    let min = cell.as_vec2() * cell_size + inset;
+    let max = (cell + 1).as_vec2() * cell_size - inset;
+    let (top_right, bottom_left) = (Vec2::new(max.x, min.y), Vec2::new(min.x, max.y));
+    [
+        min,
+        top_right,
+        top_right,
+        max,
+        max,
+        bottom_left,
+        bottom_left,
+        min,
+    ]
+}
@@ -1,18 +1,20 @@
-#version 330 core
-
+uniform sampler2D colormap;
 out vec4 FragColor;
-in float particleVelocity;
+in float particleValue;
+in vec4 particleTint;
+in float pointPixel;
 
 void main() {
     // gl_PointCoord provides the coordinate within the point sprite, ranging from (0,0) to (1,1).
     // We calculate the distance from the center of the point.
     float dist = length(gl_PointCoord - vec2(0.5));
 
-    // If the distance is greater than 0.5, discard the fragment.
-    if (dist > 0.5) {
+    // fade out over the last pixel or so, and discard anything outside the circle
+    float alpha = 1.0 - smoothstep(0.5 - pointPixel * 1.5, 0.5, dist);
+    if (alpha <= 0.0) {
         discard;
     }
 
-    // Otherwise, set the fragment color as desired.
-    FragColor = vec4(particleVelocity, 1 - particleVelocity, 0.0, 1.0);
-}
\ No newline at end of file
+    vec3 color = texture(colormap, vec2(particleValue, 0.5)).rgb;
+    FragColor = vec4(mix(color, particleTint.rgb, particleTint.a), alpha);
+}
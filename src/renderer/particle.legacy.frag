@@ -0,0 +1,16 @@
+uniform sampler2D colormap;
+varying float particleValue;
+varying vec4 particleTint;
+varying float pointPixel;
+
+void main() {
+    // see particle.frag
+    float dist = length(gl_PointCoord - vec2(0.5));
+    float alpha = 1.0 - smoothstep(0.5 - pointPixel * 1.5, 0.5, dist);
+    if (alpha <= 0.0) {
+        discard;
+    }
+
+    vec3 color = texture2D(colormap, vec2(particleValue, 0.5)).rgb;
+    gl_FragColor = vec4(mix(color, particleTint.rgb, particleTint.a), alpha);
+}
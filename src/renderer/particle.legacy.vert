@@ -0,0 +1,21 @@
+// xy: position, zw: velocity (both in world space)
+attribute vec4 particle;
+// the field particles are colored by, roughly from 0 to 1
+attribute float field;
+// rgb: the color of the particle's material, a: how much of it is used instead of the colormap
+attribute vec4 tint;
+uniform float pointSize;
+// takes world space to clip space
+uniform mat4 camera;
+varying float particleValue;
+varying vec4 particleTint;
+// the size of a pixel within the point sprite, so its edge can be softened by about a pixel
+varying float pointPixel;
+
+void main() {
+    gl_Position = camera * vec4(particle.xy, 0.0, 1.0);
+    gl_PointSize = pointSize;
+    particleValue = field;
+    particleTint = tint;
+    pointPixel = 1.0 / pointSize;
+}
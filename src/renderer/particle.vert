@@ -1,9 +1,21 @@
-#version 330 core
-
-layout(location = 0) in vec3 particlePosition;
-out float particleVelocity;
+// xy: position, zw: velocity (both in world space)
+layout(location = 0) in vec4 particle;
+// the field particles are colored by, roughly from 0 to 1
+layout(location = 1) in float field;
+// rgb: the color of the particle's material, a: how much of it is used instead of the colormap
+layout(location = 2) in vec4 tint;
+uniform float pointSize;
+// takes world space to clip space
+uniform mat4 camera;
+out float particleValue;
+out vec4 particleTint;
+// the size of a pixel within the point sprite, so its edge can be softened by about a pixel
+out float pointPixel;
 
 void main() {
-    gl_Position = vec4(particlePosition.xy, 0.0, 1.0);
-    particleVelocity = particlePosition.z;
+    gl_Position = camera * vec4(particle.xy, 0.0, 1.0);
+    gl_PointSize = pointSize;
+    particleValue = field;
+    particleTint = tint;
+    pointPixel = 1.0 / pointSize;
 }
@@ -0,0 +1,19 @@
+uniform float radius;
+uniform sampler2D colormap;
+
+in vec2 streakPosition;
+in float streakHalfLength;
+in float particleValue;
+in vec4 particleTint;
+out vec4 FragColor;
+
+void main() {
+    // discard anything outside of a capsule around the streak's center line
+    vec2 nearest = vec2(clamp(streakPosition.x, -streakHalfLength, streakHalfLength), 0.0);
+    if (length(streakPosition - nearest) > radius) {
+        discard;
+    }
+
+    vec3 color = texture(colormap, vec2(particleValue, 0.5)).rgb;
+    FragColor = vec4(mix(color, particleTint.rgb, particleTint.a), 1.0);
+}
@@ -0,0 +1,42 @@
+// xy: position, zw: velocity (both in world space)
+layout(location = 0) in vec4 particle;
+// the field particles are colored by, roughly from 0 to 1
+layout(location = 1) in float field;
+// rgb: the color of the particle's material, a: how much of it is used instead of the colormap
+layout(location = 2) in vec4 tint;
+// the radius of each particle in world units
+uniform float radius;
+// how many seconds of movement each streak covers
+uniform float streakTime;
+// takes world space to clip space
+uniform mat4 camera;
+
+// position within the streak in world units, relative to its center and aligned with its velocity
+out vec2 streakPosition;
+// half the length of the streak's center line in world units
+out float streakHalfLength;
+out float particleValue;
+out vec4 particleTint;
+
+// stretches a quad along the particle's velocity (instanced, one quad per particle)
+void main() {
+    vec2 velocity = particle.zw;
+    float speed = length(velocity);
+    vec2 dir = speed > 0.0 ? velocity / speed : vec2(1.0, 0.0);
+    vec2 perp = vec2(-dir.y, dir.x);
+
+    // the streak trails behind the particle
+    streakHalfLength = speed * streakTime * 0.5;
+    vec2 center = particle.xy - dir * streakHalfLength;
+
+    vec2 corner = vec2(
+        (gl_VertexID == 0 || gl_VertexID == 2) ? -1.0 : 1.0,
+        (gl_VertexID == 0 || gl_VertexID == 1) ? -1.0 : 1.0
+    );
+    streakPosition = vec2(corner.x * (streakHalfLength + radius), corner.y * radius);
+
+    vec2 offset = dir * streakPosition.x + perp * streakPosition.y;
+    gl_Position = camera * vec4(center + offset, 0.0, 1.0);
+    particleValue = field;
+    particleTint = tint;
+}
@@ -1,80 +1,342 @@
+use std::ffi::CString;
 use std::mem::size_of;
 use std::ptr;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use gl::types::*;
+use glam::{Mat4, Vec4};
 
-use super::utils::{compile_shader, link_program};
-use super::world_pos_to_gl_pos;
+use super::colormap::COLORMAP_TEXTURE_UNIT;
+use super::debug;
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{compile_shader, link_program, select_shader, GlslVersion};
 use crate::engine::EngineContext;
 use crate::gl_assert_ok;
 use crate::state::State;
 
+/// Not present in the core profile bindings, but needed for `gl_PointCoord` in GL 2.1
+const POINT_SPRITE: GLenum = 0x8861;
+
+/// How the particles are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleStyle {
+    /// Round dots with soft edges
+    Dots,
+    /// Streaks stretched along each particle's velocity
+    Streaks,
+}
+
+impl ParticleStyle {
+    pub fn next(self) -> ParticleStyle {
+        match self {
+            ParticleStyle::Dots => ParticleStyle::Streaks,
+            ParticleStyle::Streaks => ParticleStyle::Dots,
+        }
+    }
+}
+
+/// Which value particles are colored by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorField {
+    /// How fast each particle is moving
+    Speed,
+    /// How far each particle's density is above or below the target density
+    Density,
+    /// How much pressure each particle is under, regardless of whether it's pushing or pulling
+    Pressure,
+    /// How infected each particle is
+    Infection,
+    /// How warm or cool each particle is
+    Temperature,
+}
+
+impl ColorField {
+    pub fn next(self) -> ColorField {
+        match self {
+            ColorField::Speed => ColorField::Density,
+            ColorField::Density => ColorField::Pressure,
+            ColorField::Pressure => ColorField::Infection,
+            ColorField::Infection => ColorField::Temperature,
+            ColorField::Temperature => ColorField::Speed,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorField::Speed => "speed",
+            ColorField::Density => "density",
+            ColorField::Pressure => "pressure",
+            ColorField::Infection => "infection",
+            ColorField::Temperature => "temperature",
+        }
+    }
+
+    /// The field's value for the given particle, scaled so the interesting range is about 0 to 1
+    fn value(self, state: &State, idx: usize) -> f32 {
+        let target_density = state.target_density(idx);
+        match self {
+            ColorField::Speed => state.velocities[idx].length() / 2.0,
+            // the target density is in the middle, with empty space at 0
+            ColorField::Density => 0.5 + (state.densities[idx] - target_density) / target_density,
+            ColorField::Pressure => {
+                state.pressure(idx).abs() / (target_density * state.settings.pressure_multiplier)
+            }
+            ColorField::Infection => state.infection(idx),
+            // a temperature of 0 is in the middle, with the heaters usually at -1 and 1
+            ColorField::Temperature => 0.5 + state.temperature(idx) * 0.5,
+        }
+    }
+}
+
+/// Draws each particle stretched along its velocity, see `particle_streak.vert`
+struct GlStreaks {
+    vao: u32,
+    program: u32,
+    radius_uniform: i32,
+    camera_uniform: i32,
+}
+
+impl GlStreaks {
+    /// How many seconds of movement each streak covers
+    const STREAK_TIME: f32 = 0.1;
+
+    fn new(version: GlslVersion, vbo: u32) -> Result<GlStreaks> {
+        let vs = compile_shader(version, shader!("particle_streak.vert"), gl::VERTEX_SHADER)?;
+        let fs = compile_shader(
+            version,
+            shader!("particle_streak.frag"),
+            gl::FRAGMENT_SHADER,
+        )?;
+        let program = link_program(vs, fs)?;
+
+        let mut vao = 0;
+        let (radius_uniform, camera_uniform);
+        unsafe {
+            gl::UseProgram(program);
+            radius_uniform = Uniform::F32(1.0).create(program, "radius")?;
+            Uniform::Int(COLORMAP_TEXTURE_UNIT as _).create(program, "colormap")?;
+            Uniform::F32(Self::STREAK_TIME).create(program, "streakTime")?;
+            camera_uniform = Uniform::Mat4(Mat4::IDENTITY).create(program, "camera")?;
+
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            // one quad is drawn per particle
+            for attr in bind_particle_attributes(program)? {
+                gl::VertexAttribDivisor(attr, 1);
+            }
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::PROGRAM, program, "particle streaks");
+        debug::label(gl::VERTEX_ARRAY, vao, "particle streaks");
+
+        Ok(GlStreaks {
+            vao,
+            program,
+            radius_uniform,
+            camera_uniform,
+        })
+    }
+
+    fn draw(&self, ctx: &EngineContext) {
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::BindVertexArray(self.vao);
+
+            gl::Uniform1f(self.radius_uniform, ctx.state.smoothing_radius() / 2.0);
+            Uniform::Mat4(ctx.camera.world_to_clip()).set(self.camera_uniform);
+            gl::DrawArraysInstanced(
+                gl::TRIANGLE_STRIP,
+                0,
+                4,
+                ctx.state.positions.len() as GLsizei,
+            );
+
+            gl_assert_ok!();
+        }
+    }
+}
+
 pub struct GlParticles {
     vao: u32,
     vbo: u32,
+    // how many bytes the vbo's storage has room for
+    vbo_capacity: usize,
+    // the vertices uploaded each frame, kept so they don't need to be allocated again
+    vertices: Vec<f32>,
     program: u32,
+    point_size_uniform: i32,
+    camera_uniform: i32,
+    // not available on legacy versions, since it needs instancing
+    streaks: Option<GlStreaks>,
 }
 
 impl GlParticles {
-    pub fn new() -> Result<GlParticles> {
-        let vs = compile_shader(include_str!("particle.vert"), gl::VERTEX_SHADER)?;
-        let fs = compile_shader(include_str!("particle.frag"), gl::FRAGMENT_SHADER)?;
+    pub fn new(version: GlslVersion) -> Result<GlParticles> {
+        let vs = compile_shader(
+            version,
+            select_shader(
+                version,
+                shader!("particle.vert"),
+                shader!("particle.legacy.vert"),
+            ),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(
+            version,
+            select_shader(
+                version,
+                shader!("particle.frag"),
+                shader!("particle.legacy.frag"),
+            ),
+            gl::FRAGMENT_SHADER,
+        )?;
         let program = link_program(vs, fs)?;
 
         let mut vao = 0;
         let mut vbo = 0;
+        let (point_size_uniform, camera_uniform);
         unsafe {
+            gl::UseProgram(program);
+            point_size_uniform = Uniform::F32(1.0).create(program, "pointSize")?;
+            camera_uniform = Uniform::Mat4(Mat4::IDENTITY).create(program, "camera")?;
+            Uniform::Int(COLORMAP_TEXTURE_UNIT as _).create(program, "colormap")?;
+
+            // point sizes are set in the vertex shader, since `glPointSize` doesn't exist in GLES
+            if !version.is_es() {
+                gl::Enable(gl::PROGRAM_POINT_SIZE);
+            }
+            if version == GlslVersion::Glsl120 {
+                gl::Enable(POINT_SPRITE);
+            }
+
             gl::GenVertexArrays(1, &mut vao);
             gl::BindVertexArray(vao);
 
             gl::GenBuffers(1, &mut vbo);
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            let n_values = 3;
-            gl::VertexAttribPointer(
-                0,
-                n_values,
-                gl::FLOAT,
-                gl::FALSE,
-                n_values * size_of::<GLfloat>() as GLsizei,
-                ptr::null(),
-            );
-            gl::EnableVertexAttribArray(0);
+            bind_particle_attributes(program)?;
             gl_assert_ok!();
         }
 
-        Ok(GlParticles { vao, vbo, program })
+        debug::label(gl::PROGRAM, program, "particles");
+        debug::label(gl::VERTEX_ARRAY, vao, "particles");
+        debug::label(gl::BUFFER, vbo, "particles");
+
+        let streaks = match version.is_legacy() {
+            true => None,
+            false => Some(GlStreaks::new(version, vbo)?),
+        };
+
+        Ok(GlParticles {
+            vao,
+            vbo,
+            vbo_capacity: 0,
+            vertices: vec![],
+            program,
+            point_size_uniform,
+            camera_uniform,
+            streaks,
+        })
     }
 
-    pub fn draw(&self, ctx: &EngineContext) {
-        let points = ctx
-            .state
-            .positions
-            .iter()
-            .zip(&ctx.state.velocities)
-            .flat_map(|(p, v)| {
-                let p = world_pos_to_gl_pos(&ctx.state.bounding_box, p);
-                [p.x, p.y, v.length() / 2.0]
-            })
-            .collect::<Vec<f32>>();
+    pub fn draw(&mut self, ctx: &EngineContext) {
+        self.vertices.clear();
+        self.vertices.extend(
+            ctx.state
+                .interpolated_positions()
+                .zip(&ctx.state.velocities)
+                .enumerate()
+                .flat_map(|(i, (p, v))| {
+                    let value = ctx.color_field.value(ctx.state, i);
+                    // particles of the base fluid are colored by the colormap alone
+                    let tint = ctx
+                        .state
+                        .material(i)
+                        .map_or(Vec4::ZERO, |material| material.color.extend(1.0));
+                    [p.x, p.y, v.x, v.y, value, tint.x, tint.y, tint.z, tint.w]
+                }),
+        );
 
-        unsafe {
-            gl::UseProgram(self.program);
+        let size = self.vertices.len() * size_of::<f32>();
+        if size > self.vbo_capacity {
+            self.vbo_capacity = size.next_power_of_two();
+        }
 
-            gl::BindVertexArray(self.vao);
+        unsafe {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-
+            // orphan the last frame's storage rather than writing over it, so the driver doesn't
+            // have to wait until it's done drawing with it
             gl::BufferData(
                 gl::ARRAY_BUFFER,
-                (points.len() * size_of::<f32>()) as GLsizeiptr,
-                points.as_ptr() as _,
-                gl::STATIC_DRAW,
+                self.vbo_capacity as GLsizeiptr,
+                ptr::null(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BufferSubData(
+                gl::ARRAY_BUFFER,
+                0,
+                size as GLsizeiptr,
+                self.vertices.as_ptr() as _,
             );
+        }
+
+        match (ctx.particle_style, &self.streaks) {
+            (ParticleStyle::Streaks, Some(streaks)) => streaks.draw(ctx),
+            _ => self.draw_dots(ctx),
+        }
+    }
+
+    fn draw_dots(&self, ctx: &EngineContext) {
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::BindVertexArray(self.vao);
 
-            gl::PointSize(ctx.state.smoothing_radius() * State::PIXELS_PER_UNIT);
+            // points are sized in physical pixels, so go by how big the view is on the surface
+            // rather than `State::PIXELS_PER_UNIT` (which is in logical pixels, and doesn't account
+            // for the window being resized or the camera zooming)
+            let pixels_per_unit = ctx.surface_dimensions.width as f32 / ctx.camera.view().w;
+            gl::Uniform1f(
+                self.point_size_uniform,
+                ctx.state.smoothing_radius() * pixels_per_unit,
+            );
+            Uniform::Mat4(ctx.camera.world_to_clip()).set(self.camera_uniform);
             gl::DrawArrays(gl::POINTS, 0, ctx.state.positions.len() as GLsizei);
 
             gl_assert_ok!();
         }
     }
 }
+
+/// Points the particle attributes at the currently bound buffer, which has nine floats per particle:
+/// - `particle`: a `vec4` of its position and velocity, both in world space
+/// - `field`: the value of the `ColorField` it's colored by
+/// - `tint`: a `vec4` of its material's color, and how much that's used instead of the colormap
+unsafe fn bind_particle_attributes(program: u32) -> Result<[GLuint; 3]> {
+    let stride = 9 * size_of::<GLfloat>() as GLsizei;
+    let mut attrs = [0; 3];
+    for (i, (name, n_values, offset)) in [("particle", 4, 0), ("field", 1, 4), ("tint", 4, 5)]
+        .into_iter()
+        .enumerate()
+    {
+        let attr = gl::GetAttribLocation(program, CString::new(name)?.as_ptr());
+        if attr < 0 {
+            return Err(anyhow!("{name} GetAttribLocation -> {attr}"));
+        }
+
+        gl::VertexAttribPointer(
+            attr as _,
+            n_values,
+            gl::FLOAT,
+            gl::FALSE,
+            stride,
+            (offset * size_of::<GLfloat>()) as *const _,
+        );
+        gl::EnableVertexAttribArray(attr as _);
+        attrs[i] = attr as _;
+    }
+
+    Ok(attrs)
+}
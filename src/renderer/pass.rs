@@ -0,0 +1,158 @@
+//! Drawing over the whole of a framebuffer, for effects that work on what's already been drawn.
+
+use std::ffi::CString;
+use std::mem::size_of;
+use std::ptr;
+
+use anyhow::{anyhow, bail, Result};
+use gl::types::*;
+
+use super::debug;
+use super::shaders::shader;
+use super::utils::{compile_shader, link_program, select_shader, GlslVersion};
+use crate::gl_assert_ok;
+
+/// The texture unit passes read their input from
+pub const SOURCE_TEXTURE_UNIT: u32 = 3;
+
+/// Creates a buffer holding a quad that covers the whole screen, as a triangle strip
+pub fn quad_buffer(label: &str) -> u32 {
+    let points: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+
+    let mut vbo = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut vbo);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (points.len() * size_of::<f32>()) as GLsizeiptr,
+            points.as_ptr() as _,
+            gl::STATIC_DRAW,
+        );
+        gl_assert_ok!();
+    }
+
+    debug::label(gl::BUFFER, vbo, label);
+    vbo
+}
+
+/// A framebuffer with a texture to draw into
+pub struct Target {
+    pub fbo: u32,
+    pub texture: u32,
+}
+
+impl Target {
+    pub fn new(label: &str) -> Target {
+        let (mut fbo, mut texture) = (0, 0);
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::GenTextures(1, &mut texture);
+            // unit 0 is left alone, since the glyph texture stays bound to it
+            gl::ActiveTexture(gl::TEXTURE0 + SOURCE_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+
+        debug::label(gl::FRAMEBUFFER, fbo, label);
+        debug::label(gl::TEXTURE, texture, label);
+
+        Target { fbo, texture }
+    }
+
+    /// Reallocates the texture at the given size, and checks the framebuffer can be drawn to
+    pub unsafe fn resize(&self, width: i32, height: i32) -> Result<()> {
+        gl::ActiveTexture(gl::TEXTURE0 + SOURCE_TEXTURE_UNIT);
+        gl::BindTexture(gl::TEXTURE_2D, self.texture);
+        gl::TexImage2D(
+            gl::TEXTURE_2D,
+            0,
+            gl::RGBA as _,
+            width.max(1),
+            height.max(1),
+            0,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            ptr::null(),
+        );
+        gl::ActiveTexture(gl::TEXTURE0);
+
+        gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        gl::FramebufferTexture2D(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            self.texture,
+            0,
+        );
+
+        let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            bail!("framebuffer is incomplete: {status:#x}");
+        }
+
+        Ok(())
+    }
+}
+
+/// A program that's drawn over the whole of its target
+pub struct Pass {
+    pub program: u32,
+    vao: u32,
+}
+
+impl Pass {
+    /// Links the fragment shader with `fullscreen.vert`, and points it at the quad in `vbo` (see
+    /// `quad_buffer`)
+    pub fn new(version: GlslVersion, fragment: &str, vbo: u32, label: &str) -> Result<Pass> {
+        let vs = compile_shader(
+            version,
+            select_shader(
+                version,
+                shader!("fullscreen.vert"),
+                shader!("fullscreen.legacy.vert"),
+            ),
+            gl::VERTEX_SHADER,
+        )?;
+        let fs = compile_shader(version, fragment, gl::FRAGMENT_SHADER)?;
+        let program = link_program(vs, fs)?;
+
+        let mut vao = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut vao);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            let attr = gl::GetAttribLocation(program, CString::new("quadPosition")?.as_ptr());
+            if attr < 0 {
+                return Err(anyhow!("quadPosition GetAttribLocation -> {attr}"));
+            }
+            let n_values = 2;
+            gl::VertexAttribPointer(
+                attr as _,
+                n_values,
+                gl::FLOAT,
+                gl::FALSE,
+                n_values * size_of::<GLfloat>() as GLsizei,
+                ptr::null(),
+            );
+            gl::EnableVertexAttribArray(attr as _);
+            gl_assert_ok!();
+        }
+
+        debug::label(gl::PROGRAM, program, label);
+        debug::label(gl::VERTEX_ARRAY, vao, label);
+
+        Ok(Pass { program, vao })
+    }
+
+    pub unsafe fn draw(&self) {
+        gl::UseProgram(self.program);
+        gl::BindVertexArray(self.vao);
+        gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+    }
+}
@@ -0,0 +1,86 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, TAU};
+
+use anyhow::Result;
+use glam::Vec2;
+
+use super::cursor::GlCursor;
+use super::lines::GlLines;
+use super::utils::GlslVersion;
+use crate::engine::{EngineContext, Interaction};
+
+/// Draws a small icon for each pinned interaction, in the same color as the cursor's ring would be
+pub struct GlPins {
+    lines: GlLines,
+}
+
+impl GlPins {
+    /// How far the icons reach from their centres, in physical pixels
+    const ICON_RADIUS: f32 = 7.0;
+
+    pub fn new(version: GlslVersion) -> Result<GlPins> {
+        Ok(GlPins {
+            lines: GlLines::new(version)?,
+        })
+    }
+
+    pub fn draw(&self, ctx: &EngineContext) {
+        // the icons are the same size on screen however far the camera is zoomed in
+        let size = ctx
+            .camera
+            .screen_len_to_world_len(ctx.surface_dimensions, Vec2::splat(Self::ICON_RADIUS));
+        for pin in &ctx.state.pinned {
+            let points = icon(pin, size);
+            self.lines.draw(&ctx.camera, &points, GlCursor::color(pin));
+        }
+    }
+}
+
+/// Line segments showing what the interaction does: spokes pushing out for repelling, a ring for
+/// sucking in, an arrow for dragging, a diamond for painting, a spiral for swirling and a funnel for
+/// pouring
+fn icon(interaction: &Interaction, size: Vec2) -> Vec<Vec2> {
+    let pos = interaction.pos();
+    // how far out a point is in the direction of the angle (clockwise from the right), as a
+    // fraction of the icon's radius
+    let offset = |angle: f32, scale: f32| Vec2::from_angle(angle) * size * scale;
+
+    match *interaction {
+        Interaction::Repel(_) => (0..8)
+            .map(|i| i as f32 * FRAC_PI_4)
+            .flat_map(|angle| [pos + offset(angle, 0.4), pos + offset(angle, 1.0)])
+            .collect(),
+        Interaction::Suck(_) => (0..12)
+            .flat_map(|i| [i, i + 1])
+            .map(|i| pos + offset(i as f32 / 12.0 * TAU, 1.0))
+            .collect(),
+        Interaction::Drag(_, velocity) => {
+            let angle = velocity.y.atan2(velocity.x);
+            let tip = pos + offset(angle, 1.0);
+            vec![
+                pos - offset(angle, 1.0),
+                tip,
+                tip,
+                tip + offset(angle + FRAC_PI_4 * 3.0, 0.6),
+                tip,
+                tip + offset(angle - FRAC_PI_4 * 3.0, 0.6),
+            ]
+        }
+        Interaction::Paint(_) => (0..4)
+            .flat_map(|i| [i, i + 1])
+            .map(|i| pos + offset(i as f32 * TAU * 0.25, 1.0))
+            .collect(),
+        // one and a half turns, clockwise and outwards like the particles it spins around
+        Interaction::Swirl(_) => (0..16)
+            .flat_map(|i| [i, i + 1])
+            .map(|i| i as f32 / 16.0)
+            .map(|t| pos + offset(t * TAU * 1.5, 0.2 + t * 0.8))
+            .collect(),
+        // a triangle pointing down, with its spout below it
+        Interaction::Pour(..) => {
+            let [left, right, tip] =
+                [FRAC_PI_4 * 5.0, FRAC_PI_4 * 7.0, FRAC_PI_2].map(|angle| pos + offset(angle, 1.0));
+            let spout = pos + offset(FRAC_PI_2, 0.4);
+            vec![left, right, right, spout, spout, left, spout, tip]
+        }
+    }
+}
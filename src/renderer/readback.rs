@@ -0,0 +1,133 @@
+use std::{ptr, slice};
+
+use gl::types::*;
+use winit::dpi::PhysicalSize;
+
+use super::debug::{self, DebugGroup};
+use super::image::{self, Image};
+use crate::gl_assert_ok;
+
+/// A frame that's been asked for, but not handed back yet
+enum Pending {
+    // still being copied into the pixel buffer, which is mapped once it's wanted
+    Buffer(PhysicalSize<u32>),
+    // already read back, when pixel buffers aren't supported
+    Read(Image),
+}
+
+/// Reads frames back without stalling drawing: each is copied into a pixel buffer by the GPU in its
+/// own time, and only mapped once the next one's been started (by which point the copy's usually
+/// done). Without pixel buffers frames are read straight away, but still handed back a frame late so
+/// both ways behave the same
+pub struct GlReadback {
+    // frames are copied into these alternately, if they're supported
+    buffers: Option<[u32; 2]>,
+    // the frame in each buffer, if there is one
+    pending: [Option<Pending>; 2],
+    // how many bytes each buffer was last allocated with
+    capacities: [usize; 2],
+    // which buffer the next frame goes into
+    next: usize,
+}
+
+impl GlReadback {
+    pub fn new() -> GlReadback {
+        let supported = gl::GenBuffers::is_loaded() && gl::MapBufferRange::is_loaded();
+        let buffers = supported.then(|| {
+            let mut buffers = [0; 2];
+            unsafe {
+                gl::GenBuffers(2, buffers.as_mut_ptr());
+                gl_assert_ok!();
+            }
+            debug::label(gl::BUFFER, buffers[0], "readback 0");
+            debug::label(gl::BUFFER, buffers[1], "readback 1");
+            buffers
+        });
+        if buffers.is_none() {
+            tracing::warn!("pixel buffers aren't available, reading back frames will be slower");
+        }
+
+        GlReadback {
+            buffers,
+            pending: [None, None],
+            capacities: [0; 2],
+            next: 0,
+        }
+    }
+
+    /// Starts reading back what's been drawn to the bound framebuffer so far, and returns the frame
+    /// started before it (if there was one)
+    pub fn read_frame(&mut self, dimensions: PhysicalSize<u32>) -> Option<Image> {
+        let _read_group = DebugGroup::push("read frame");
+        let current = self.next;
+        self.next = 1 - self.next;
+
+        self.pending[current] = Some(match self.buffers {
+            Some(buffers) => unsafe {
+                let len = (dimensions.width * dimensions.height * 4) as usize;
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, buffers[current]);
+                if self.capacities[current] < len {
+                    gl::BufferData(
+                        gl::PIXEL_PACK_BUFFER,
+                        len as GLsizeiptr,
+                        ptr::null(),
+                        gl::STREAM_READ,
+                    );
+                    self.capacities[current] = len;
+                }
+                // with a buffer bound this only queues up the copy, rather than waiting for it
+                gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+                gl::ReadPixels(
+                    0,
+                    0,
+                    dimensions.width as _,
+                    dimensions.height as _,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    ptr::null_mut(),
+                );
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                gl_assert_ok!();
+                Pending::Buffer(dimensions)
+            },
+            None => Pending::Read(image::read_pixels(dimensions)),
+        });
+
+        self.take(self.next)
+    }
+
+    /// Returns the last frame started, waiting for it to be read back if need be
+    pub fn finish(&mut self) -> Option<Image> {
+        let _read_group = DebugGroup::push("read frame");
+        self.take(1 - self.next)
+    }
+
+    fn take(&mut self, idx: usize) -> Option<Image> {
+        match self.pending[idx].take()? {
+            Pending::Read(image) => Some(image),
+            Pending::Buffer(dimensions) => {
+                let len = (dimensions.width * dimensions.height * 4) as usize;
+                if len == 0 {
+                    return Some(Image::from_gl_rows(dimensions, &[]));
+                }
+
+                let buffer = self.buffers?[idx];
+                unsafe {
+                    gl::BindBuffer(gl::PIXEL_PACK_BUFFER, buffer);
+                    let mapped = gl::MapBufferRange(
+                        gl::PIXEL_PACK_BUFFER,
+                        0,
+                        len as GLsizeiptr,
+                        gl::MAP_READ_BIT,
+                    );
+                    gl_assert_ok!();
+                    let pixels = slice::from_raw_parts(mapped as *const u8, len);
+                    let image = Image::from_gl_rows(dimensions, pixels);
+                    gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+                    gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                    Some(image)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Watches a directory of shaders for changes, so they can be reloaded while editing them.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use super::shaders;
+
+/// Reads shaders from a directory rather than using the built in ones, and notices when they change
+pub struct ShaderWatcher {
+    // stops watching when dropped
+    _watcher: RecommendedWatcher,
+    changed: Arc<AtomicBool>,
+}
+
+impl ShaderWatcher {
+    /// `on_change` is called (from another thread) whenever anything in `dir` changes
+    pub fn new(dir: PathBuf, on_change: impl Fn() + Send + 'static) -> Result<ShaderWatcher> {
+        let changed = Arc::new(AtomicBool::new(false));
+        let mut watcher = notify::recommended_watcher({
+            let changed = changed.clone();
+            move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                    changed.store(true, Ordering::Relaxed);
+                    on_change();
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("failed to watch shaders: {e}"),
+            }
+        })?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("failed to watch {}", dir.display()))?;
+
+        shaders::set_dir(dir)?;
+
+        Ok(ShaderWatcher {
+            _watcher: watcher,
+            changed,
+        })
+    }
+
+    /// Whether any shaders have changed since this was last called
+    pub fn changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,52 @@
+//! Where shader sources come from: they're built in, unless a `ShaderWatcher` has been created,
+//! after which they're read from its directory so they can be edited without rebuilding.
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// The directory shaders are read from, if they aren't only built in
+static SHADER_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The named shader's source, read from the `ShaderWatcher`'s directory if there is one and it has
+/// the shader in it, otherwise the built in version
+pub fn source(name: &str, built_in: &'static str) -> Cow<'static, str> {
+    let Some(dir) = SHADER_DIR.get() else {
+        return Cow::Borrowed(built_in);
+    };
+
+    let path = dir.join(name);
+    match fs::read_to_string(&path) {
+        Ok(source) => Cow::Owned(source),
+        Err(e) => {
+            if e.kind() != ErrorKind::NotFound {
+                tracing::warn!(
+                    "failed to read {}, using the built in shader: {e}",
+                    path.display()
+                );
+            }
+            Cow::Borrowed(built_in)
+        }
+    }
+}
+
+/// The source of a shader in this directory, see `source`
+macro_rules! shader {
+    ($name:literal) => {
+        $crate::renderer::shaders::source($name, include_str!($name))
+    };
+}
+
+pub(crate) use shader;
+
+/// Reads shaders from `dir` from now on, which can only be set once
+#[cfg(feature = "shader-reload")]
+pub fn set_dir(dir: PathBuf) -> anyhow::Result<()> {
+    if SHADER_DIR.set(dir).is_err() {
+        anyhow::bail!("shaders are already being watched");
+    }
+
+    Ok(())
+}
@@ -4,6 +4,7 @@ use glyph_brush::{BrushAction, BrushError, GlyphBrush, GlyphBrushBuilder, Sectio
 use winit::dpi::PhysicalSize;
 
 use super::glyph::GlGlyphVertex;
+use super::utils::GlslVersion;
 use crate::gl_assert_ok;
 use crate::renderer::glyph::{to_vertex, GlGlyphTexture, GlTextPipe};
 
@@ -11,6 +12,7 @@ const FONT: &[u8] = include_bytes!("gnu-freefont-FreeMono.ttf");
 
 /// A wrapper around `glyph_brush` to expose a simple API for drawing text with GL.
 pub struct GlText {
+    version: GlslVersion,
     max_image_dimension: u32,
     glyph_brush: GlyphBrush<GlGlyphVertex, glyph_brush::Extra, FontRef<'static>>,
     glyph_texture: GlGlyphTexture,
@@ -18,7 +20,7 @@ pub struct GlText {
 }
 
 impl GlText {
-    pub fn new(surface_dimensions: PhysicalSize<u32>) -> Result<GlText> {
+    pub fn new(version: GlslVersion, surface_dimensions: PhysicalSize<u32>) -> Result<GlText> {
         let max_image_dimension = {
             let mut value = 0;
             unsafe { gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut value) };
@@ -27,10 +29,11 @@ impl GlText {
 
         let font = FontRef::try_from_slice(FONT)?;
         let glyph_brush = GlyphBrushBuilder::using_font(font).build();
-        let glyph_texture = GlGlyphTexture::new(glyph_brush.texture_dimensions());
-        let text_pipe = GlTextPipe::new(surface_dimensions)?;
+        let glyph_texture = GlGlyphTexture::new(version, glyph_brush.texture_dimensions());
+        let text_pipe = GlTextPipe::new(version, surface_dimensions)?;
 
         Ok(GlText {
+            version,
             max_image_dimension,
             glyph_brush,
             glyph_texture,
@@ -86,10 +89,10 @@ impl GlText {
                         suggested
                     };
 
-                    eprintln!("Resizing glyph texture -> {new_width}x{new_height}");
+                    tracing::debug!("resizing glyph texture -> {new_width}x{new_height}");
 
                     // Recreate texture as a larger size to fit more
-                    self.glyph_texture = GlGlyphTexture::new((new_width, new_height));
+                    self.glyph_texture = GlGlyphTexture::new(self.version, (new_width, new_height));
                     self.glyph_brush.resize_texture(new_width, new_height);
                 }
             }
@@ -0,0 +1,10 @@
+uniform sampler2D source;
+in vec2 texCoord;
+out vec4 FragColor;
+
+void main() {
+    // the composite isn't blended by its alpha, which is only there for transparent windows: it's
+    // how much of whatever's behind the window the trails cover
+    vec3 color = texture(source, texCoord).rgb;
+    FragColor = vec4(color, max(color.r, max(color.g, color.b)));
+}
@@ -0,0 +1,8 @@
+uniform sampler2D source;
+varying vec2 texCoord;
+
+// see trail_composite.frag
+void main() {
+    vec3 color = texture2D(source, texCoord).rgb;
+    gl_FragColor = vec4(color, max(color.r, max(color.g, color.b)));
+}
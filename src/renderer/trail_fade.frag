@@ -0,0 +1,11 @@
+uniform sampler2D source;
+// how much of the last frame's trails are kept
+uniform float keep;
+in vec2 texCoord;
+out vec4 FragColor;
+
+void main() {
+    // the texture only has 8 bits per channel, so faint trails would round back up to the same
+    // value forever without a nudge down each frame
+    FragColor = vec4(max(texture(source, texCoord).rgb * keep - 1.0 / 255.0, 0.0), 1.0);
+}
@@ -0,0 +1,7 @@
+uniform sampler2D source;
+uniform float keep;
+varying vec2 texCoord;
+
+void main() {
+    gl_FragColor = vec4(max(texture2D(source, texCoord).rgb * keep - 1.0 / 255.0, 0.0), 1.0);
+}
@@ -0,0 +1,164 @@
+use std::time::Instant;
+
+use anyhow::{bail, Result};
+use winit::dpi::PhysicalSize;
+
+use super::debug::DebugGroup;
+use super::pass::{quad_buffer, Pass, Target, SOURCE_TEXTURE_UNIT};
+use super::shaders::shader;
+use super::uniform::Uniform;
+use super::utils::{select_shader, GlslVersion};
+use crate::gl_assert_ok;
+
+/// Leaves fading trails behind the particles: each frame they're drawn over a faded copy of the
+/// last, and the result is added on top of what was drawn before
+pub struct GlTrails {
+    vbo: u32,
+    fade: Pass,
+    composite: Pass,
+    keep_uniform: i32,
+    // drawn into alternately, so the last frame can be read while drawing the next
+    targets: [Target; 2],
+    // which of the targets holds the current frame
+    current: usize,
+    // the size the targets were last allocated at
+    dimensions: PhysicalSize<u32>,
+    // when the last frame was drawn, to fade the trails by how long it's been since
+    last_frame: Option<Instant>,
+    // what to go back to once the particles have been drawn
+    previous_fbo: i32,
+    previous_viewport: [i32; 4],
+}
+
+impl GlTrails {
+    pub fn new(version: GlslVersion) -> Result<GlTrails> {
+        if !gl::GenFramebuffers::is_loaded() {
+            bail!("framebuffer objects are not supported");
+        }
+
+        let vbo = quad_buffer("trails");
+        let fade = Pass::new(
+            version,
+            &select_shader(
+                version,
+                shader!("trail_fade.frag"),
+                shader!("trail_fade.legacy.frag"),
+            ),
+            vbo,
+            "trail fade",
+        )?;
+        let composite = Pass::new(
+            version,
+            &select_shader(
+                version,
+                shader!("trail_composite.frag"),
+                shader!("trail_composite.legacy.frag"),
+            ),
+            vbo,
+            "trail composite",
+        )?;
+
+        let keep_uniform;
+        unsafe {
+            gl::UseProgram(fade.program);
+            Uniform::Int(SOURCE_TEXTURE_UNIT as _).create(fade.program, "source")?;
+            keep_uniform = Uniform::F32(1.0).create(fade.program, "keep")?;
+            gl::UseProgram(composite.program);
+            Uniform::Int(SOURCE_TEXTURE_UNIT as _).create(composite.program, "source")?;
+        }
+
+        Ok(GlTrails {
+            vbo,
+            fade,
+            composite,
+            keep_uniform,
+            targets: [Target::new("trails 0"), Target::new("trails 1")],
+            current: 0,
+            dimensions: PhysicalSize::new(0, 0),
+            last_frame: None,
+            previous_fbo: 0,
+            previous_viewport: [0; 4],
+        })
+    }
+
+    /// Starts drawing into the trails, over the last frame faded by however much of it should be
+    /// left after `trail_length` seconds, until `end` is called
+    pub fn begin(&mut self, dimensions: PhysicalSize<u32>, trail_length: f32) -> Result<()> {
+        let now = Instant::now();
+        // if the trails haven't been drawn in a while (or ever) there's nothing left to fade
+        let elapsed = self
+            .last_frame
+            .map_or(f32::INFINITY, |last| (now - last).as_secs_f32());
+        self.last_frame = Some(now);
+
+        let _fade_group = DebugGroup::push("trail fade");
+        unsafe {
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut self.previous_fbo);
+            gl::GetIntegerv(gl::VIEWPORT, self.previous_viewport.as_mut_ptr());
+
+            // the old trails are stretched if the size changes, so they're just dropped
+            let resized = self.dimensions != dimensions;
+            if resized {
+                let (width, height) = (dimensions.width as i32, dimensions.height as i32);
+                let resized = self
+                    .targets
+                    .iter()
+                    .try_for_each(|target| target.resize(width, height));
+                if let Err(e) = resized {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, self.previous_fbo as _);
+                    return Err(e);
+                }
+
+                self.dimensions = dimensions;
+            }
+
+            let previous = &self.targets[self.current];
+            self.current = 1 - self.current;
+            let current = &self.targets[self.current];
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, current.fbo);
+            gl::Viewport(0, 0, dimensions.width as _, dimensions.height as _);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            if !resized {
+                // blending is additive, so with the target cleared this just writes the faded frame
+                gl::Disable(gl::DEPTH_TEST);
+                gl::UseProgram(self.fade.program);
+                gl::Uniform1f(self.keep_uniform, (-elapsed / trail_length).exp());
+                gl::ActiveTexture(gl::TEXTURE0 + SOURCE_TEXTURE_UNIT);
+                gl::BindTexture(gl::TEXTURE_2D, previous.texture);
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+                self.fade.draw();
+                gl::Enable(gl::DEPTH_TEST);
+            }
+
+            gl_assert_ok!();
+        }
+
+        Ok(())
+    }
+
+    /// Adds the trails (including everything drawn since `begin`) on top of the framebuffer that
+    /// was bound before
+    pub fn end(&self) {
+        let _trails_group = DebugGroup::push("trail composite");
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.previous_fbo as _);
+            let [x, y, w, h] = self.previous_viewport;
+            gl::Viewport(x, y, w, h);
+
+            gl::Disable(gl::DEPTH_TEST);
+            gl::ActiveTexture(gl::TEXTURE0 + SOURCE_TEXTURE_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D, self.targets[self.current].texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            // added as is, the alpha it writes is only for transparent windows
+            gl::BlendFunc(gl::ONE, gl::ONE);
+            self.composite.draw();
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+            gl::Enable(gl::DEPTH_TEST);
+            gl_assert_ok!();
+        }
+    }
+}
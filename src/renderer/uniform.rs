@@ -3,12 +3,15 @@
 use std::ffi::CString;
 
 use anyhow::{anyhow, Result};
+use glam::Mat4;
 
 /// Small helper to create (and set defaults) for uniforms
 pub enum Uniform {
     Vec2(f32, f32),
+    Vec4(f32, f32, f32, f32),
     F32(f32),
     Int(i32),
+    Mat4(Mat4),
 }
 
 impl Uniform {
@@ -19,12 +22,20 @@ impl Uniform {
             return Err(anyhow!(r#"GetUniformLocation("{name}") -> {location}"#));
         }
 
+        self.set(location);
+        Ok(location)
+    }
+
+    /// Sets the uniform at `location` in the program that's in use
+    pub unsafe fn set(self, location: i32) {
         match self {
             Uniform::Vec2(x, y) => gl::Uniform2f(location, x, y),
+            Uniform::Vec4(x, y, z, w) => gl::Uniform4f(location, x, y, z, w),
             Uniform::F32(value) => gl::Uniform1f(location, value),
             Uniform::Int(value) => gl::Uniform1i(location, value),
+            Uniform::Mat4(value) => {
+                gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ref().as_ptr())
+            }
         }
-
-        Ok(location)
     }
 }
@@ -1,4 +1,4 @@
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::ptr;
 
 use anyhow::{anyhow, Result};
@@ -30,12 +30,110 @@ pub fn gl_err_to_str(err: u32) -> &'static str {
     }
 }
 
-pub fn compile_shader(src: &str, ty: GLenum) -> Result<GLuint> {
+/// The dialect of GLSL that shaders are compiled with, which depends on the context that was created
+/// (see `create_window`, which falls back to GLES and then GL 2.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlslVersion {
+    /// OpenGL 3.3+
+    Glsl330,
+    /// OpenGL ES 3.0+
+    Gles300,
+    /// OpenGL 2.1 (and anything else below 3.3)
+    Glsl120,
+    /// OpenGL ES 2.0
+    Gles100,
+}
+
+impl GlslVersion {
+    /// Detects the version from the current context - gl must already be loaded
+    pub fn detect() -> GlslVersion {
+        let version = unsafe {
+            let ptr = gl::GetString(gl::VERSION);
+            if ptr.is_null() {
+                return GlslVersion::Glsl330;
+            }
+            CStr::from_ptr(ptr as _).to_string_lossy().into_owned()
+        };
+
+        // e.g. "4.6 (Core Profile) Mesa 23.1.0", "OpenGL ES 3.2 Mesa 23.1.0" or "2.1 Metal - 83.1"
+        let (is_es, version) = match version.strip_prefix("OpenGL ES") {
+            Some(rest) => (true, rest.trim_start_matches(|c: char| !c.is_ascii_digit())),
+            None => (false, version.as_str()),
+        };
+
+        let mut parts = version
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|part| part.parse::<u32>().ok());
+        let major = parts.next().unwrap_or(0);
+        let minor = parts.next().unwrap_or(0);
+
+        match (is_es, (major, minor) >= (3, 3), major >= 3) {
+            (true, _, true) => GlslVersion::Gles300,
+            (true, _, false) => GlslVersion::Gles100,
+            (false, true, _) => GlslVersion::Glsl330,
+            (false, false, _) => GlslVersion::Glsl120,
+        }
+    }
+
+    pub fn is_es(&self) -> bool {
+        matches!(self, GlslVersion::Gles300 | GlslVersion::Gles100)
+    }
+
+    /// Legacy versions don't support instancing, `in`/`out` qualifiers, `gl_VertexID`, etc, and
+    /// need to use the `.legacy` variants of the shaders
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, GlslVersion::Glsl120 | GlslVersion::Gles100)
+    }
+
+    /// The preamble prepended to every shader's source
+    fn header(&self) -> &'static str {
+        match self {
+            GlslVersion::Glsl330 => "#version 330 core\n",
+            GlslVersion::Gles300 => "#version 300 es\nprecision highp float;\n",
+            GlslVersion::Glsl120 => "#version 120\n",
+            GlslVersion::Gles100 => "#version 100\nprecision mediump float;\n",
+        }
+    }
+}
+
+/// Checks if the current context supports the given extension - gl must already be loaded
+pub fn has_extension(version: GlslVersion, name: &str) -> bool {
+    unsafe {
+        // legacy versions only have the single space separated string
+        if version.is_legacy() {
+            let ptr = gl::GetString(gl::EXTENSIONS);
+            return !ptr.is_null()
+                && CStr::from_ptr(ptr as _)
+                    .to_string_lossy()
+                    .split_whitespace()
+                    .any(|ext| ext == name);
+        }
+
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count as GLuint).any(|i| {
+            let ptr = gl::GetStringi(gl::EXTENSIONS, i);
+            !ptr.is_null() && CStr::from_ptr(ptr as _).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+/// Picks the shader source for the current version: `legacy` is written in GLSL 1.00/1.20 and
+/// `modern` in GLSL 3.30/3.00 ES - neither should contain a `#version` directive
+pub fn select_shader<T>(version: GlslVersion, modern: T, legacy: T) -> T {
+    if version.is_legacy() {
+        legacy
+    } else {
+        modern
+    }
+}
+
+pub fn compile_shader(version: GlslVersion, src: impl AsRef<str>, ty: GLenum) -> Result<GLuint> {
     let shader;
     unsafe {
         shader = gl::CreateShader(ty);
         // Attempt to compile the shader
-        let c_str = CString::new(src.as_bytes())?;
+        let c_str = CString::new(format!("{}{}", version.header(), src.as_ref()))?;
         gl::ShaderSource(shader, 1, &c_str.as_ptr(), ptr::null());
         gl::CompileShader(shader);
 
@@ -0,0 +1,507 @@
+//! A registry of all the tunable simulation parameters.
+//!
+//! Each setting is declared once in the `settings!` invocation below, which generates the
+//! `Settings` struct (read directly by the simulation) as well as a `Setting` descriptor for it in
+//! `Settings::REGISTRY`. Anything that needs to expose settings generically (the CLI, config files,
+//! snapshots, etc) should go through the registry rather than binding each field by hand.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+use anyhow::{anyhow, bail, Context, Result};
+use glam::Vec2;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The value of a single setting
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Bool(bool),
+    // before `F32`, so whole numbers are read as this (`f32` settings accept it too)
+    U32(u32),
+    F32(f32),
+    Vec2(Vec2),
+}
+
+impl Value {
+    /// Parses a string into a value of the same type as `self`
+    pub fn parse_as(&self, s: &str) -> Result<Value> {
+        let s = s.trim();
+        Ok(match self {
+            Value::Bool(_) => Value::Bool(s.parse()?),
+            Value::U32(_) => Value::U32(s.parse()?),
+            Value::F32(_) => Value::F32(s.parse()?),
+            Value::Vec2(_) => Value::Vec2(parse_vec2(s)?),
+        })
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Bool(_) => "bool",
+            Value::U32(_) => "u32",
+            Value::F32(_) => "f32",
+            Value::Vec2(_) => "vec2",
+        }
+    }
+
+    fn in_range(&self, range: &RangeInclusive<f32>) -> bool {
+        match self {
+            Value::Bool(_) => true,
+            Value::U32(v) => range.contains(&(*v as f32)),
+            Value::F32(v) => range.contains(v),
+            Value::Vec2(v) => range.contains(&v.x) && range.contains(&v.y),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::U32(v) => write!(f, "{v}"),
+            Value::F32(v) => write!(f, "{v}"),
+            Value::Vec2(v) => write!(f, "{},{}", v.x, v.y),
+        }
+    }
+}
+
+/// Parses a vector in the form `x,y`
+pub fn parse_vec2(s: &str) -> Result<Vec2> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| anyhow!("expected a value in the form x,y"))?;
+    Ok(Vec2::new(x.trim().parse()?, y.trim().parse()?))
+}
+
+/// Conversion between the types used in `Settings` and `Value`
+pub trait SettingType: Sized {
+    fn into_value(self) -> Value;
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+impl SettingType for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl SettingType for u32 {
+    fn into_value(self) -> Value {
+        Value::U32(self)
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::U32(v) => Some(v),
+            // a config file may well say `4.0`, which is fine, but `3.7` isn't
+            Value::F32(v) if v >= 0.0 && v.fract() == 0.0 => Some(v as u32),
+            _ => None,
+        }
+    }
+}
+
+impl SettingType for f32 {
+    fn into_value(self) -> Value {
+        Value::F32(self)
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::F32(v) => Some(v),
+            Value::U32(v) => Some(v as f32),
+            _ => None,
+        }
+    }
+}
+
+impl SettingType for Vec2 {
+    fn into_value(self) -> Value {
+        Value::Vec2(self)
+    }
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Vec2(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Describes a single setting, and how to read and write it on `Settings`
+pub struct Setting {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The valid range for the value (applied to each component for vectors), which booleans don't
+    /// have
+    pub range: Option<RangeInclusive<f32>>,
+    get: fn(&Settings) -> Value,
+    set: fn(&mut Settings, Value) -> Option<()>,
+}
+
+impl Setting {
+    pub fn get(&self, settings: &Settings) -> Value {
+        (self.get)(settings)
+    }
+
+    pub fn set(&self, settings: &mut Settings, value: Value) -> Result<()> {
+        if let Some(range) = &self.range {
+            if !value.in_range(range) {
+                bail!(
+                    "{} must be within {}..={}, got {value}",
+                    self.name,
+                    range.start(),
+                    range.end()
+                );
+            }
+        }
+
+        (self.set)(settings, value).ok_or_else(|| {
+            anyhow!(
+                "{} expects a {} value, got {value}",
+                self.name,
+                self.default().type_name()
+            )
+        })
+    }
+
+    pub fn default(&self) -> Value {
+        self.get(&Settings::default())
+    }
+}
+
+macro_rules! settings {
+    (@range) => { None };
+    (@range $range:expr) => { Some($range) };
+    ($(
+        $(#[doc = $doc:literal])+
+        $name:ident: $ty:ty = $default:expr $(, $range:expr)?;
+    )*) => {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct Settings {
+            $(
+                $(#[doc = $doc])+
+                pub $name: $ty,
+            )*
+        }
+
+        impl Default for Settings {
+            fn default() -> Settings {
+                Settings {
+                    $($name: $default,)*
+                }
+            }
+        }
+
+        impl Settings {
+            pub const REGISTRY: &'static [Setting] = &[
+                $(
+                    Setting {
+                        name: stringify!($name),
+                        description: concat!($($doc),+),
+                        range: settings!(@range $($range)?),
+                        get: |settings| settings.$name.into_value(),
+                        set: |settings, value| {
+                            settings.$name = <$ty>::from_value(value)?;
+                            Some(())
+                        },
+                    },
+                )*
+            ];
+        }
+    };
+}
+
+settings! {
+    /// How many times per second the simulation is updated (lower rates take larger steps, so
+    /// `pressure_multiplier` is capped lower to keep the fluid from blowing apart)
+    tick_rate: f32 = 30.0, 10.0..=240.0;
+    /// The mass of each particle
+    mass: f32 = 1.0, 0.01..=100.0;
+    /// The density the fluid tries to maintain
+    target_density: f32 = 5.0, 0.01..=100.0;
+    /// The radius within which particles influence each other
+    smoothing_radius: f32 = 0.7, 0.05..=5.0;
+    /// Whether swirls are kept spinning by vorticity confinement, which adds back the rotation
+    /// that's otherwise smoothed away
+    vorticity_confinement: bool = false;
+    /// How strongly vorticity confinement spins swirls up
+    vorticity_strength: f32 = 2.0, 0.0..=50.0;
+    /// How many times each tick the position based solver (`--solver pbf`) moves the particles
+    /// towards the target density: more is stiffer, but slower
+    pbf_iterations: u32 = 4, 1.0..=20.0;
+    /// Softens the position based solver's corrections, so particles with only a few neighbours
+    /// aren't thrown about
+    pbf_relaxation: f32 = 100.0, 0.0..=1000.0;
+    /// How much of each particle's change in velocity the FLIP/PIC solver (`--solver flip`) takes
+    /// from FLIP rather than PIC: higher is livelier and splashier, lower is smoother and calmer
+    flip_ratio: f32 = 0.9, 0.0..=1.0;
+    /// How viscous the fluid is with the grid solver (`--solver grid`), in world units squared per
+    /// second: higher values smear its motion out more quickly
+    grid_viscosity: f32 = 0.001, 0.0..=1.0;
+    /// How far each particle's velocity is blended towards the average of its neighbours' (XSPH),
+    /// which smooths out jitter (0 turns it off)
+    xsph: f32 = 0.1, 0.0..=1.0;
+    /// How strongly overlapping sand grains are pushed apart (see `--material sand`)
+    sand_stiffness: f32 = 100.0, 0.0..=2000.0;
+    /// How much sand grains rub against each other where they touch, as a fraction of how hard
+    /// they're pressed together: higher makes steeper piles
+    sand_friction: f32 = 0.5, 0.0..=2.0;
+    /// Whether neighbouring particles are joined by springs that stretch and give way, making the
+    /// fluid gooey (viscoelastic)
+    goo: bool = false;
+    /// How strongly the goo's springs pull back towards their rest lengths, per second squared
+    goo_stiffness: f32 = 100.0, 0.0..=1000.0;
+    /// How quickly the goo's springs' rest lengths give way once they're stretched or squashed
+    /// past the yield ratio, as the fraction of the excess taken up per second
+    goo_plasticity: f32 = 0.3, 0.0..=10.0;
+    /// How far the goo's springs can be stretched or squashed (as a fraction of their rest
+    /// lengths) before they start to give way
+    goo_yield: f32 = 0.1, 0.0..=1.0;
+    /// Whether the walls are lined with fixed particles that count towards the density of the fluid
+    /// next to them and push back on it, rather than particles just being stopped at the walls
+    boundary_particles: bool = true;
+    /// How much velocity is retained after bouncing off a wall
+    collision_damping: f32 = 0.75, 0.0..=1.0;
+    /// How strongly particles are pushed apart or pulled together to reach the target density,
+    /// up to as strongly as the tick rate can keep stable
+    pressure_multiplier: f32 = 50.0, 0.0..=1000.0;
+    /// The radius of the mouse interaction
+    interaction_radius: f32 = 1.5, 0.0..=20.0;
+    /// The strength of the mouse interaction
+    interaction_strength: f32 = 5.0, 0.0..=100.0;
+    /// Whether pushing and pulling only slows particles moving towards or away from the cursor,
+    /// leaving them free to swirl around it
+    preserve_rotation: bool = false;
+    /// How much of the cursor's velocity is imparted to particles when dragging
+    drag_strength: f32 = 1.0, 0.0..=5.0;
+    /// Gravity, in world units per second squared (y points down)
+    gravity: Vec2 = Vec2::ZERO, -100.0..=100.0;
+    /// How much of the window's change in velocity is transferred to the particles
+    frame_inertia: f32 = 0.5, 0.0..=5.0;
+    /// Whether the force fields from the config file push particles around
+    force_fields: bool = true;
+    /// Whether particles rain down from the top edge
+    rain: bool = false;
+    /// How many raindrops fall per second while it's raining (once there are as many particles as
+    /// there can be, existing ones are moved to make each drop)
+    rain_rate: f32 = 40.0, 0.0..=1000.0;
+    /// How many particles are poured in per second by the pour tool (or a pinned pour), which are
+    /// moved from elsewhere once there are as many as there can be
+    pour_rate: f32 = 60.0, 0.0..=1000.0;
+    /// The size of the bodies dropped into the fluid: the radius of balls, and half the width of
+    /// boxes
+    body_size: f32 = 0.5, 0.1..=4.0;
+    /// How heavy bodies dropped into the fluid are for their size, as a fraction of the fluid's
+    /// density: lighter than 1 floats, and heavier sinks
+    body_density: f32 = 0.5, 0.05..=10.0;
+    /// How quickly bodies are brought to the speed of the fluid around them, as the fraction of
+    /// the difference that's closed per second
+    body_drag: f32 = 4.0, 0.0..=50.0;
+    /// How strongly the fluid is stirred by ambient turbulence, in world units per second squared
+    /// (0 turns it off)
+    turbulence: f32 = 0.0, 0.0..=100.0;
+    /// The size of the turbulence's swirls, in world units
+    turbulence_scale: f32 = 3.0, 0.1..=50.0;
+    /// How quickly the turbulence changes over time
+    turbulence_speed: f32 = 0.2, 0.0..=10.0;
+    /// How far the sponge layer along the left wall reaches in, in world units - sponge layers damp
+    /// velocities more strongly closer to the wall, so waves fade out rather than reflecting back
+    /// (0 turns it off)
+    sponge_left: f32 = 0.0, 0.0..=8.0;
+    /// How far the sponge layer along the right wall reaches in, see `sponge_left`
+    sponge_right: f32 = 0.0, 0.0..=8.0;
+    /// How far the sponge layer along the top wall reaches in, see `sponge_left`
+    sponge_top: f32 = 0.0, 0.0..=4.5;
+    /// How far the sponge layer along the bottom wall reaches in, see `sponge_left`
+    sponge_bottom: f32 = 0.0, 0.0..=4.5;
+    /// How strongly sponge layers damp velocities right at the wall, as a rate per second
+    sponge_strength: f32 = 10.0, 0.0..=100.0;
+    /// How long it takes particles' trails to fade (to about a third), in seconds, while they're
+    /// shown
+    trail_length: f32 = 0.5, 0.01..=10.0;
+    /// How much ink is painted per second, at the middle of the cursor (each color channel holds
+    /// up to 1)
+    ink_rate: f32 = 4.0, 0.0..=100.0;
+    /// How quickly ink fades away, as a rate per second (0 leaves it in the fluid forever)
+    ink_fade: f32 = 0.1, 0.0..=10.0;
+    /// How far the simulated region extends past each edge of the window, in world units, so the
+    /// fluid can flow in from off screen rather than piling up against the window's edges (only
+    /// read when the simulation starts)
+    margin: f32 = 0.0, 0.0..=8.0;
+    /// Whether the simulation keeps running while the window isn't focused, at a lower tick rate
+    /// and frame rate (see `background_tick_rate` and `background_fps`), rather than pausing
+    run_in_background: bool = false;
+    /// How many times per second the simulation is updated while it's running in the background
+    /// (if that's lower than `tick_rate`)
+    background_tick_rate: f32 = 15.0, 10.0..=240.0;
+    /// How many frames per second are drawn while the simulation's running in the background
+    background_fps: f32 = 10.0, 1.0..=240.0;
+    /// Whether infection spreads between neighbouring particles
+    infection: bool = false;
+    /// How quickly infection spreads, as the fraction of a neighbour's infection caught per second
+    infection_rate: f32 = 2.0, 0.0..=100.0;
+    /// Whether particles carry heat: it spreads between neighbours, the heaters from the config
+    /// file warm or cool the particles in them, and warm particles rise
+    heat: bool = false;
+    /// How quickly heat spreads between neighbouring particles, as the fraction of the difference
+    /// from their neighbours' average temperature that's evened out per second
+    heat_diffusion: f32 = 1.0, 0.0..=20.0;
+    /// How quickly particles in a heater are brought to its temperature, as the fraction of the
+    /// difference that's closed per second
+    heating_rate: f32 = 2.0, 0.0..=50.0;
+    /// How much lighter particles get per degree, as a fraction of their weight: warm fluid rises
+    /// against gravity by this much and cool fluid sinks (so without gravity, there's no convection)
+    thermal_expansion: f32 = 0.5, 0.0..=10.0;
+}
+
+impl Settings {
+    /// The settings perturbed by `mutate` (and drifted by the attract mode): those that change how
+    /// the fluid behaves, rather than how it's interacted with
+    pub const MUTABLE: &'static [&'static str] = &[
+        "mass",
+        "target_density",
+        "smoothing_radius",
+        "collision_damping",
+        "pressure_multiplier",
+    ];
+
+    /// How far (as a power of two) `mutate` scales each setting in either direction
+    const MUTATION_OCTAVES: f32 = 0.5;
+
+    pub fn find(name: &str) -> Option<&'static Setting> {
+        Self::REGISTRY.iter().find(|setting| setting.name == name)
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) -> Result<()> {
+        Self::find(name)
+            .ok_or_else(|| anyhow!("unknown setting: {name}"))?
+            .set(self, value)
+    }
+
+    /// Multiplies a numeric setting by `factor`, clamping it to the setting's range
+    pub fn scale(&mut self, name: &str, factor: f32) -> Result<()> {
+        let setting = Self::find(name).ok_or_else(|| anyhow!("unknown setting: {name}"))?;
+        let value = match (setting.get(self), &setting.range) {
+            (Value::F32(v), Some(range)) => {
+                Value::F32((v * factor).clamp(*range.start(), *range.end()))
+            }
+            (Value::U32(v), Some(range)) => Value::U32(
+                (v as f32 * factor)
+                    .round()
+                    .clamp(*range.start(), *range.end()) as u32,
+            ),
+            (value, _) => bail!("{name} is not numeric, got {value}"),
+        };
+
+        setting.set(self, value)
+    }
+
+    /// Randomly scales each of the fluid's settings up or down a little, keeping them within their
+    /// ranges. Returns the previous values, which can be passed to `restore` to undo it
+    pub fn mutate(&mut self, rng: &mut impl Rng) -> BTreeMap<String, Value> {
+        let mut previous = BTreeMap::new();
+        for name in Self::MUTABLE {
+            let setting = Self::find(name).unwrap();
+            let value = setting.get(self);
+            let octaves = rng.gen_range(-Self::MUTATION_OCTAVES..=Self::MUTATION_OCTAVES);
+            self.scale(name, octaves.exp2()).unwrap();
+
+            // keep the values short, so they're easy to read and to copy into a config
+            if let (Value::F32(v), Some(range)) = (setting.get(self), &setting.range) {
+                let rounded = ((v * 100.0).round() / 100.0).max(*range.start());
+                setting.set(self, Value::F32(rounded)).unwrap();
+            }
+
+            previous.insert(name.to_string(), value);
+        }
+
+        previous
+    }
+
+    /// Sets each of the given values, e.g. those from a config file or returned from `mutate`
+    pub fn restore(&mut self, values: &BTreeMap<String, Value>) -> Result<()> {
+        for (name, value) in values {
+            self.set(name, *value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies an assignment in the form `name=value`
+    pub fn assign(&mut self, assignment: &str) -> Result<()> {
+        let (name, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected an assignment in the form name=value"))?;
+        let setting = Self::find(name.trim()).ok_or_else(|| anyhow!("unknown setting: {name}"))?;
+        let value = setting
+            .default()
+            .parse_as(value)
+            .with_context(|| format!("invalid value for {}", setting.name))?;
+        setting.set(self, value)
+    }
+
+    /// All settings as a map of name to value
+    pub fn to_map(&self) -> BTreeMap<String, Value> {
+        Self::REGISTRY
+            .iter()
+            .map(|setting| (setting.name.to_string(), setting.get(self)))
+            .collect()
+    }
+
+    /// Prints a table of all available settings
+    pub fn print_registry() {
+        for setting in Self::REGISTRY {
+            let range = match &setting.range {
+                Some(range) => format!("range: {}..={}", range.start(), range.end()),
+                None => String::new(),
+            };
+            let line = format!(
+                "{:<24} {:<6} default: {:<10} {range}",
+                setting.name,
+                setting.default().type_name(),
+                setting.default().to_string(),
+            );
+            println!("{}", line.trim_end());
+            println!("    {}", setting.description.trim());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_numbers_only_for_integer_settings() {
+        let mut settings = Settings::default();
+        settings.assign("pbf_iterations=7").unwrap();
+        assert_eq!(settings.pbf_iterations, 7);
+        assert!(settings.assign("pbf_iterations=3.7").is_err());
+        assert!(settings.set("pbf_iterations", Value::F32(3.7)).is_err());
+        assert_eq!(settings.pbf_iterations, 7);
+    }
+
+    #[test]
+    fn values_from_json() {
+        // whole numbers are read as integers, whichever type of setting they're for
+        let values: BTreeMap<String, Value> =
+            serde_json::from_str(r#"{ "mass": 2, "pbf_iterations": 5.0, "goo": true }"#).unwrap();
+        let mut settings = Settings::default();
+        settings.restore(&values).unwrap();
+        assert_eq!(settings.mass, 2.0);
+        assert_eq!(settings.pbf_iterations, 5);
+        assert!(settings.goo);
+
+        let values = BTreeMap::from([("pbf_iterations".to_string(), Value::F32(4.5))]);
+        assert!(settings.restore(&values).is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Saving and comparing snapshots of the simulation state.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::rect::Rect;
+use crate::settings::Value;
+use crate::state::State;
+
+pub const SNAPSHOT_EXTENSION: &str = "snapshot";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub ticks: u64,
+    pub bounding_box: Rect,
+    pub parameters: BTreeMap<String, Value>,
+    pub positions: Vec<Vec2>,
+    pub velocities: Vec<Vec2>,
+    /// Which particle each one is, since they're reordered as the simulation runs. Snapshots saved
+    /// before particles had ids don't have them, and have their particles in the order they were
+    /// created in instead
+    #[serde(default)]
+    pub ids: Vec<usize>,
+}
+
+impl Snapshot {
+    pub fn from_state(state: &State) -> Snapshot {
+        Snapshot {
+            ticks: state.ticks,
+            bounding_box: state.bounding_box,
+            parameters: state.settings.to_map(),
+            positions: state.positions.clone(),
+            velocities: state.velocities.clone(),
+            ids: state.ids.clone(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Snapshot> {
+        let path = path.as_ref();
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+
+    /// The id of the particle at the index
+    fn id(&self, idx: usize) -> usize {
+        self.ids.get(idx).copied().unwrap_or(idx)
+    }
+
+    /// Saves the snapshot into the current directory with a timestamped name
+    pub fn save_timestamped(&self) -> Result<PathBuf> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let path = PathBuf::from(format!("plasma-pong-{timestamp}.{SNAPSHOT_EXTENSION}"));
+        self.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// Summary statistics over a set of per-particle distances, keyed by particle id
+struct Stats {
+    mean: f32,
+    rms: f32,
+    median: f32,
+    max: f32,
+    max_id: usize,
+}
+
+impl Stats {
+    fn new(values: &[(usize, f32)]) -> Option<Stats> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let (max_id, max) = values
+            .iter()
+            .copied()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+
+        let mut sorted = values.iter().map(|(_, v)| *v).collect::<Vec<_>>();
+        sorted.sort_by(f32::total_cmp);
+
+        let n = values.len() as f32;
+        Some(Stats {
+            mean: sorted.iter().sum::<f32>() / n,
+            rms: (sorted.iter().map(|v| v * v).sum::<f32>() / n).sqrt(),
+            median: sorted[sorted.len() / 2],
+            max,
+            max_id,
+        })
+    }
+
+    fn describe(&self, label: &str) -> String {
+        format!(
+            "{label}: mean {:.6} rms {:.6} median {:.6} max {:.6} (particle {})",
+            self.mean, self.rms, self.median, self.max, self.max_id
+        )
+    }
+}
+
+/// Loads two snapshots and prints a summary of how they differ. Particles are matched up by their
+/// ids, so it doesn't matter if they've been reordered in between
+pub fn diff(a: &Path, b: &Path) -> Result<()> {
+    let snapshot_a = Snapshot::load(a)?;
+    let snapshot_b = Snapshot::load(b)?;
+
+    println!("a: {} (tick {})", a.display(), snapshot_a.ticks);
+    println!("b: {} (tick {})", b.display(), snapshot_b.ticks);
+    for line in compare(&snapshot_a, &snapshot_b) {
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+/// A summary of how two snapshots differ, a line at a time
+fn compare(snapshot_a: &Snapshot, snapshot_b: &Snapshot) -> Vec<String> {
+    let mut lines = vec![];
+
+    // parameters
+    let mut names = snapshot_a
+        .parameters
+        .keys()
+        .chain(snapshot_b.parameters.keys())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    let mut parameter_diffs = 0;
+    for name in names {
+        let value_a = snapshot_a.parameters.get(name);
+        let value_b = snapshot_b.parameters.get(name);
+        if value_a != value_b {
+            parameter_diffs += 1;
+            let fmt = |v: Option<&Value>| v.map_or("-".to_string(), |v| v.to_string());
+            lines.push(format!(
+                "parameter {name}: {} -> {}",
+                fmt(value_a),
+                fmt(value_b)
+            ));
+        }
+    }
+    if parameter_diffs == 0 {
+        lines.push("parameters: identical".to_string());
+    }
+
+    if snapshot_a.bounding_box != snapshot_b.bounding_box {
+        lines.push(format!(
+            "bounding box: {:?} -> {:?}",
+            snapshot_a.bounding_box, snapshot_b.bounding_box
+        ));
+    }
+
+    // particles, matched up by id
+    let indices_b = (0..snapshot_b.positions.len())
+        .map(|idx| (snapshot_b.id(idx), idx))
+        .collect::<BTreeMap<_, _>>();
+    let pairs = (0..snapshot_a.positions.len())
+        .filter_map(|idx_a| {
+            let id = snapshot_a.id(idx_a);
+            indices_b.get(&id).map(|&idx_b| (id, idx_a, idx_b))
+        })
+        .collect::<Vec<_>>();
+
+    let (count_a, count_b) = (snapshot_a.positions.len(), snapshot_b.positions.len());
+    if count_a != count_b || pairs.len() != count_a {
+        lines.push(format!(
+            "particle count: {count_a} -> {count_b} (comparing the {} in both)",
+            pairs.len()
+        ));
+    } else {
+        lines.push(format!("particle count: {count_a}"));
+    }
+
+    let displacements = pairs
+        .iter()
+        .map(|&(id, a, b)| {
+            let (a, b) = (snapshot_a.positions[a], snapshot_b.positions[b]);
+            (id, a.distance(b))
+        })
+        .collect::<Vec<_>>();
+    let velocity_deltas = pairs
+        .iter()
+        .filter_map(|&(id, a, b)| {
+            let (a, b) = (snapshot_a.velocities.get(a)?, snapshot_b.velocities.get(b)?);
+            Some((id, a.distance(*b)))
+        })
+        .collect::<Vec<_>>();
+
+    match Stats::new(&displacements) {
+        Some(stats) => {
+            lines.push(stats.describe("displacement"));
+            lines.push(match Stats::new(&velocity_deltas) {
+                Some(stats) => stats.describe("velocity delta"),
+                None => "no velocities to compare".to_string(),
+            });
+            let moved = displacements.iter().filter(|(_, d)| *d > 0.0).count();
+            lines.push(format!(
+                "particles that differ: {moved}/{}",
+                displacements.len()
+            ));
+        }
+        None => lines.push("no particles to compare".to_string()),
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(positions: &[Vec2], velocities: &[Vec2]) -> Snapshot {
+        Snapshot {
+            ticks: 0,
+            bounding_box: Rect::new(0.0, 0.0, 16.0, 9.0),
+            parameters: BTreeMap::from([("pressure_multiplier".to_string(), Value::F32(50.0))]),
+            positions: positions.to_vec(),
+            velocities: velocities.to_vec(),
+            ids: (0..positions.len()).collect(),
+        }
+    }
+
+    #[test]
+    fn identical_snapshots() {
+        let positions = [Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)];
+        let a = snapshot(&positions, &[Vec2::ZERO; 2]);
+        let b = snapshot(&positions, &[Vec2::ZERO; 2]);
+
+        let lines = compare(&a, &b);
+        assert!(lines.contains(&"parameters: identical".to_string()));
+        assert!(lines.contains(&"particle count: 2".to_string()));
+        assert!(lines.contains(&"particles that differ: 0/2".to_string()));
+    }
+
+    #[test]
+    fn one_particle_moved() {
+        let a = snapshot(
+            &[Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)],
+            &[Vec2::ZERO; 2],
+        );
+        let b = snapshot(
+            &[Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.5)],
+            &[Vec2::ZERO; 2],
+        );
+
+        let lines = compare(&a, &b);
+        assert!(lines.contains(&"particles that differ: 1/2".to_string()));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("displacement:")
+                && line.ends_with("max 0.500000 (particle 1)")));
+    }
+
+    #[test]
+    fn reordered_particles_are_matched_by_id() {
+        let a = snapshot(
+            &[Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)],
+            &[Vec2::ZERO; 2],
+        );
+        let mut b = snapshot(
+            &[Vec2::new(3.0, 4.0), Vec2::new(1.0, 2.0)],
+            &[Vec2::ZERO; 2],
+        );
+        b.ids = vec![1, 0];
+
+        let lines = compare(&a, &b);
+        assert!(lines.contains(&"particles that differ: 0/2".to_string()));
+    }
+
+    #[test]
+    fn snapshot_without_velocities() {
+        let a = snapshot(&[Vec2::new(1.0, 2.0)], &[]);
+        let b = snapshot(&[Vec2::new(1.0, 2.0)], &[Vec2::X]);
+
+        let lines = compare(&a, &b);
+        assert!(lines.contains(&"no velocities to compare".to_string()));
+        assert!(lines.contains(&"particles that differ: 0/1".to_string()));
+    }
+
+    #[test]
+    fn parameter_changed() {
+        let a = snapshot(&[Vec2::ZERO], &[Vec2::ZERO]);
+        let mut b = snapshot(&[Vec2::ZERO], &[Vec2::ZERO]);
+        b.parameters
+            .insert("pressure_multiplier".to_string(), Value::F32(100.0));
+
+        let lines = compare(&a, &b);
+        assert!(lines.contains(&"parameter pressure_multiplier: 50 -> 100".to_string()));
+        assert!(!lines.contains(&"parameters: identical".to_string()));
+    }
+}
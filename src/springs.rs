@@ -0,0 +1,67 @@
+//! Springs between neighbouring particles, for Clavet et al.'s viscoelastic fluids ("Particle-based
+//! Viscoelastic Fluid Simulation"): they're made between particles as they come close, and their
+//! rest lengths give way when they're stretched or squashed too far, so the fluid stretches and
+//! springs back like goo but still flows if it's pulled far enough. Toggled with Z.
+
+use std::collections::BTreeMap;
+
+/// The springs' rest lengths, keyed by the indices of the two particles they join (lowest first).
+/// They're kept in order so they're always applied in the same order, and the simulation can be
+/// repeated exactly
+#[derive(Default)]
+pub struct Springs {
+    rest_lengths: BTreeMap<(usize, usize), f32>,
+}
+
+impl Springs {
+    pub fn is_empty(&self) -> bool {
+        self.rest_lengths.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.rest_lengths.clear();
+    }
+
+    /// Joins the two particles with a spring at the given rest length, unless they already are
+    pub fn attach(&mut self, a: usize, b: usize, rest_length: f32) {
+        self.rest_lengths
+            .entry((a.min(b), a.max(b)))
+            .or_insert(rest_length);
+    }
+
+    /// Removes any springs attached to the particle, e.g. when it's moved somewhere else entirely
+    pub fn detach(&mut self, idx: usize) {
+        self.rest_lengths.retain(|&(a, b), _| a != idx && b != idx);
+    }
+
+    /// Calls `f` with each spring's particles and rest length, which it can change, and keeps only
+    /// the springs it returns `true` for
+    pub fn retain(&mut self, mut f: impl FnMut(usize, usize, &mut f32) -> bool) {
+        self.rest_lengths
+            .retain(|&(a, b), rest_length| f(a, b, rest_length));
+    }
+
+    /// Each spring's particles and rest length
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, f32)> + '_ {
+        self.rest_lengths
+            .iter()
+            .map(|(&(a, b), &rest_length)| (a, b, rest_length))
+    }
+
+    /// Follows the particles to their new indices, after they've been reordered so the one that
+    /// was at `order[i]` is now at `i`
+    pub fn reorder(&mut self, order: &[usize]) {
+        let mut new_indices = vec![0; order.len()];
+        for (new_idx, &old_idx) in order.iter().enumerate() {
+            new_indices[old_idx] = new_idx;
+        }
+
+        self.rest_lengths = std::mem::take(&mut self.rest_lengths)
+            .into_iter()
+            .map(|((a, b), rest_length)| {
+                let (a, b) = (new_indices[a], new_indices[b]);
+                ((a.min(b), a.max(b)), rest_length)
+            })
+            .collect();
+    }
+}
@@ -0,0 +1,108 @@
+//! Running the simulation headlessly over a range of settings, to see which of them it survives.
+
+use anyhow::Result;
+
+use crate::config::Config;
+use crate::material::Behaviour;
+use crate::settings::{Settings, Value};
+use crate::state::{Solver, State};
+
+const PARTICLE_COUNTS: &[usize] = &[600, 1200, 2400];
+const TICK_RATES: &[f32] = &[15.0, 30.0, 60.0, 120.0];
+const PRESSURE_MULTIPLIERS: &[f32] = &[25.0, 50.0, 100.0, 200.0];
+
+/// Any particle moving faster than this (in world units per second) means the simulation has blown
+/// up, since nothing in the box should ever get close to it
+const MAX_SPEED: f32 = 100.0;
+
+/// How a run ended
+enum Outcome {
+    /// It made it to the end, and this was the fastest any particle went
+    Stable(f32),
+    /// A position or velocity stopped being a number at this tick
+    NaN(u64),
+    /// A particle went faster than `MAX_SPEED` at this tick
+    Exploded(u64, f32),
+}
+
+/// Runs every combination of particle count, tick rate and pressure multiplier for the given
+/// number of ticks (starting from the given settings and config), and prints a table of how each one
+/// went
+pub fn run(
+    settings: &Settings,
+    config: &Config,
+    seed: Option<u64>,
+    solver: Solver,
+    base_behaviour: Behaviour,
+    ticks: u64,
+) -> Result<()> {
+    // the pressure multiplier's capped at low tick rates, so show what it actually ran with
+    println!(
+        "{:>9} {:>9} {:>8} {:>9}  result",
+        "particles", "tick rate", "pressure", "effective"
+    );
+
+    let mut unstable = 0;
+    for &particle_count in PARTICLE_COUNTS {
+        for &tick_rate in TICK_RATES {
+            for &pressure_multiplier in PRESSURE_MULTIPLIERS {
+                let mut settings = settings.clone();
+                settings.set("tick_rate", Value::F32(tick_rate))?;
+                settings.set("pressure_multiplier", Value::F32(pressure_multiplier))?;
+
+                let mut state = State::with_particle_count(settings, seed, particle_count);
+                state.solver = solver;
+                state.base_behaviour = base_behaviour;
+                config.apply(&mut state);
+                let effective = state.effective_pressure_multiplier();
+                let outcome = simulate(state, ticks);
+                if !matches!(outcome, Outcome::Stable(_)) {
+                    unstable += 1;
+                }
+
+                let result = match outcome {
+                    Outcome::Stable(max_speed) => format!("stable (max speed {max_speed:.2})"),
+                    Outcome::NaN(tick) => format!("NaN at tick {tick}"),
+                    Outcome::Exploded(tick, speed) => {
+                        format!("exploded at tick {tick} (speed {speed:.2})")
+                    }
+                };
+
+                println!(
+                    "{particle_count:>9} {tick_rate:>9} {pressure_multiplier:>8} {effective:>9.1}  \
+                     {result}"
+                );
+            }
+        }
+    }
+
+    let runs = PARTICLE_COUNTS.len() * TICK_RATES.len() * PRESSURE_MULTIPLIERS.len();
+    println!("{unstable} of {runs} combinations were unstable after {ticks} ticks");
+    Ok(())
+}
+
+fn simulate(mut state: State, ticks: u64) -> Outcome {
+    let mut max_speed = 0.0f32;
+    for tick in 1..=ticks {
+        state.step(&[]);
+
+        let finite = state.positions.iter().all(|p| p.is_finite())
+            && state.velocities.iter().all(|v| v.is_finite());
+        if !finite {
+            return Outcome::NaN(tick);
+        }
+
+        let speed = state
+            .velocities
+            .iter()
+            .map(|v| v.length())
+            .fold(0.0, f32::max);
+        if speed > MAX_SPEED {
+            return Outcome::Exploded(tick, speed);
+        }
+
+        max_speed = max_speed.max(speed);
+    }
+
+    Outcome::Stable(max_speed)
+}
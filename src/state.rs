@@ -1,197 +1,1005 @@
 //! A particle simulation system, largely inspired by Sebastian Lague's efforts:
 //! https://www.youtube.com/watch?v=rSKMYc1CQHE
 
-use std::f32::consts::PI;
+use std::f32::consts::TAU;
 
-use glam::{IVec2, Vec2};
-use rand::rngs::ThreadRng;
-use rand::{thread_rng, Rng};
+use clap::ValueEnum;
+use glam::{IVec2, Vec2, Vec3};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+use crate::body::{Body, Shape};
+use crate::boundary::Boundary;
+use crate::cell_list::CellList;
 use crate::engine::Interaction;
+use crate::force_field::ForceField;
+use crate::grid::Grid;
+use crate::heater::Heater;
+use crate::ink::Ink;
+#[cfg(feature = "simd")]
+use crate::kernel::{GradientSum, ValueSum};
+use crate::kernel::{Kernel, SmoothingKernel};
+use crate::mac_grid::MacGrid;
+use crate::material::{Behaviour, Material};
+use crate::noise::Noise;
 use crate::rect::Rect;
+use crate::settings::Settings;
+use crate::springs::Springs;
+use crate::user_data::UserData;
+
+/// How the fluid is kept at its target density
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Solver {
+    /// Smoothed particle hydrodynamics: pressure forces push particles apart (or pull them
+    /// together) each tick
+    #[default]
+    Sph,
+    /// Macklin & Müller's position based fluids: particles are moved apart directly, a few times
+    /// each tick, and their velocities follow from how far they went
+    Pbf,
+    /// A FLIP/PIC hybrid: the particles' velocities are made divergence free on a grid, which
+    /// leaves empty cells free to be splashed into, and `flip_ratio` blends between the two
+    Flip,
+    /// Jos Stam's stable fluids: the fluid's velocity is simulated on a grid instead, which carries
+    /// the ink along, and the particles just drift with it
+    Grid,
+}
 
 pub struct State {
-    rng: ThreadRng,
-
+    // if set, the simulation is seeded with this so it can be repeated
+    seed: Option<u64>,
+    rng: StdRng,
+    // drives the ambient turbulence, seeded along with everything else
+    noise: Noise,
+
+    pub settings: Settings,
+    // the region that's simulated, which is `VIEW` plus the margin on each side
     pub bounding_box: Rect,
+    // the gravity to restore when it's toggled back on
+    disabled_gravity: Vec2,
 
-    // particles
+    // particles, there are `particle_count` of them until more are spawned
+    particle_count: usize,
     pub positions: Vec<Vec2>,
+    // the positions as of the previous tick, for interpolating between ticks when rendering
+    pub previous_positions: Vec<Vec2>,
+    // where the particles are headed before pressure is applied, which neighbours are found by
     pub predicted_positions: Vec<Vec2>,
     pub velocities: Vec<Vec2>,
     pub densities: Vec<f32>,
-
-    // (particle_idx, cell_key)
-    spatial_lookup: Vec<(usize, usize)>,
-    start_indices: Vec<usize>,
-
+    // which particle each one is, which stays the same when they're reordered (see
+    // `reorder_particles`) so snapshots can be compared particle by particle
+    pub ids: Vec<usize>,
+    // the id the next particle spawned gets
+    next_id: usize,
+    // any extra values features have attached to each particle, see `USER_DATA_CHANNELS`
+    pub user_data: UserData,
+    // how infected each particle is, from 0 to 1
+    infection_channel: usize,
+    // which material each particle's made of, see `material`
+    material_channel: usize,
+    // how warm each particle is, see `temperature`
+    temperature_channel: usize,
+
+    // dye painted into the fluid, which it carries along
+    pub ink: Ink,
+    // the fluid's velocities while it's simulated on a grid, see `Solver::Grid`
+    grid: Grid,
+    // where the FLIP/PIC solver makes the particles' velocities divergence free, see `Solver::Flip`
+    mac_grid: MacGrid,
+    // interactions pinned in place, which act on the fluid every tick until they're removed
+    pub pinned: Vec<Interaction>,
+    // constant forces over regions of the world, while `settings.force_fields` is on
+    pub force_fields: Vec<ForceField>,
+    // regions that warm or cool the particles in them, while `settings.heat` is on
+    pub heaters: Vec<Heater>,
+    // balls and boxes floating in the fluid, see `add_body`
+    pub bodies: Vec<Body>,
+    // how much each neighbour counts for at each distance
+    pub kernel: SmoothingKernel,
+    pub solver: Solver,
+    // fluids besides the base one, which particles can be made of
+    pub materials: Vec<Material>,
+    // the material new particles are made of (by rain, or poured in by the pour tool): 0 for the
+    // base fluid, or one more than its index in `materials`
+    pub material: usize,
+    // how the base fluid behaves, materials from the config file each have their own
+    pub base_behaviour: Behaviour,
+
+    // for finding each particle's neighbours
+    cell_list: CellList,
+    // fixed particles around the walls, which count as fluid while `settings.boundary_particles`
+    // is on
+    boundary: Boundary,
+    // joining neighbouring particles while `settings.goo` is on
+    springs: Springs,
+
+    pub ticks: u64,
+    // a hash of every tick's positions and velocities so far, see `checksum`
+    checksum: u64,
+    // how much simulated time has passed, in seconds
+    time: f32,
     last_update_offset: f32,
+    // how many raindrops are due to be spawned, the fractional part carries over between ticks
+    rain_accumulator: f32,
+    // whether the last update had to drop time to keep up
+    falling_behind: bool,
+    // whether the simulation's running in the background (while the window isn't focused), when
+    // it's updated at `background_tick_rate`
+    pub background: bool,
 }
 
 const PARTICLE_COUNT: usize = 1200;
+/// The parameters of the 64 bit FNV-1a hash, which the checksum is calculated with
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+/// The most particles there can be, once more have been spawned (e.g. by rain)
+const MAX_PARTICLE_COUNT: usize = 2000;
 impl State {
     pub const PIXELS_PER_UNIT: f32 = 50.0;
 
-    const TICK_RATE: f32 = 30.0;
-    const TICK_DELTA: f32 = 1.0 / Self::TICK_RATE;
+    /// The part of the world that's shown in the window (when the camera's zoomed all the way out)
+    pub const VIEW: Rect = Rect::new(0.0, 0.0, 16.0, 9.0);
+
+    const DEFAULT_GRAVITY: Vec2 = Vec2::new(0.0, 9.8);
+
+    /// The most ticks run in a single update: any time beyond this is dropped, rather than trying
+    /// to catch up on it (which would only make the next update even longer)
+    const MAX_TICKS_PER_UPDATE: u32 = 8;
+
+    /// How often the particles are reordered by cell, so neighbours are close together in memory
+    const REORDER_INTERVAL: u64 = 60;
+
+    /// The names of the extra values each particle has in `user_data`
+    const USER_DATA_CHANNELS: &'static [&'static str] = &["infection", "material", "temperature"];
+
+    /// The most bodies there can be: adding another removes the oldest
+    const MAX_BODY_COUNT: usize = 16;
+
+    /// How fast raindrops are falling when they're spawned, in world units per second
+    const RAIN_SPEED: f32 = 8.0;
+
+    /// How many times per second the color of painted ink goes all the way around the color wheel
+    const INK_HUE_SPEED: f32 = 0.25;
+
+    /// The size of a sand grain, as a fraction of the smoothing radius: grains closer together
+    /// than this are overlapping
+    const GRAIN_SIZE: f32 = 0.5;
 
-    const MASS: f32 = 1.0;
-    const TARGET_DENSITY: f32 = 5.0;
-    const SMOOTHING_RADIUS: f32 = 0.7;
-    const COLLISION_DAMPING: f32 = 0.75;
-    const PRESSURE_MULTIPLIER: f32 = 50.0;
+    /// How strongly the position based solver pushes apart particles that are close together, even
+    /// once they're at the target density, so they don't clump (it's added to their lambdas, see
+    /// `calculate_lambda`)
+    const PBF_TENSILE_STRENGTH: f32 = 0.001;
+    /// The distance (as a fraction of the smoothing radius) at which that push is at its full
+    /// strength
+    const PBF_TENSILE_DISTANCE: f32 = 0.2;
 
-    const INTERACTION_RADIUS: f32 = 1.5;
-    const INTERACTION_STRENGTH: f32 = 5.0;
+    /// The furthest pressure can carry across the fluid in a single tick, in smoothing radii: any
+    /// further and particles are pushed right past each other, and the fluid blows up. Pressure
+    /// travels at the square root of `pressure_multiplier`, so this caps it at low tick rates
+    const MAX_PRESSURE_REACH: f32 = 0.7;
 
     pub fn smoothing_radius(&self) -> f32 {
-        Self::SMOOTHING_RADIUS
+        self.settings.smoothing_radius
     }
 
-    pub fn new() -> State {
-        let bounding_box = Rect::new(0.0, 0.0, 16.0, 9.0);
-        let positions = generate_grid(bounding_box, PARTICLE_COUNT);
-        State {
-            rng: thread_rng(),
+    pub fn tick_delta(&self) -> f32 {
+        let settings = &self.settings;
+        let tick_rate = match self.background {
+            true => settings.background_tick_rate.min(settings.tick_rate),
+            false => settings.tick_rate,
+        };
+        1.0 / tick_rate
+    }
+
+    pub fn new(settings: Settings, seed: Option<u64>) -> State {
+        // fill any margin as densely as the view
+        let bounding_box = simulated_region(&settings);
+        let area_ratio = (bounding_box.w * bounding_box.h) / (Self::VIEW.w * Self::VIEW.h);
+        let particle_count = (PARTICLE_COUNT as f32 * area_ratio).round() as usize;
+        State::with_particle_count(settings, seed, particle_count)
+    }
 
+    /// Like `new`, but starting with a different number of particles
+    pub fn with_particle_count(
+        settings: Settings,
+        seed: Option<u64>,
+        particle_count: usize,
+    ) -> State {
+        let mut rng = create_rng(seed);
+        let bounding_box = simulated_region(&settings);
+        let positions = generate_grid(&mut rng, bounding_box, particle_count);
+        let noise = Noise::new(&mut rng);
+        let user_data = UserData::new(Self::USER_DATA_CHANNELS, particle_count);
+        let mut state = State {
+            seed,
+            rng,
+            noise,
+
+            settings,
             bounding_box,
+            disabled_gravity: Self::DEFAULT_GRAVITY,
 
+            particle_count,
+            previous_positions: positions.clone(),
+            predicted_positions: positions.clone(),
             positions,
-            predicted_positions: vec![Vec2::ZERO; PARTICLE_COUNT],
-            velocities: vec![Vec2::ZERO; PARTICLE_COUNT],
-            densities: vec![0.0; PARTICLE_COUNT],
+            velocities: vec![Vec2::ZERO; particle_count],
+            densities: vec![0.0; particle_count],
+            ids: (0..particle_count).collect(),
+            next_id: particle_count,
+            infection_channel: user_data.channel("infection").unwrap(),
+            material_channel: user_data.channel("material").unwrap(),
+            temperature_channel: user_data.channel("temperature").unwrap(),
+            user_data,
+
+            ink: Ink::new(bounding_box),
+            grid: Grid::new(bounding_box),
+            mac_grid: MacGrid::new(bounding_box),
+            pinned: vec![],
+            force_fields: vec![],
+            heaters: vec![],
+            bodies: vec![],
+            kernel: SmoothingKernel::default(),
+            solver: Solver::default(),
+            materials: vec![],
+            material: 0,
+            base_behaviour: Behaviour::default(),
+
+            cell_list: CellList::new(),
+            boundary: Boundary::new(),
+            springs: Springs::default(),
+
+            ticks: 0,
+            checksum: FNV_OFFSET_BASIS,
+            time: 0.0,
+            last_update_offset: 0.0,
+            rain_accumulator: 0.0,
+            falling_behind: false,
+            background: false,
+        };
+
+        // so neighbours can be queried (e.g. by overlays) before the first tick
+        state.update_spatial_lookup();
+        state
+    }
 
-            spatial_lookup: vec![(0, 0); PARTICLE_COUNT],
-            start_indices: vec![usize::MAX; PARTICLE_COUNT],
+    /// Scatters the particles again and brings them to rest, keeping the current settings. If the
+    /// simulation was seeded, they end up exactly where they started
+    pub fn reset(&mut self) {
+        self.rng = create_rng(self.seed);
+        self.positions = generate_grid(&mut self.rng, self.bounding_box, self.particle_count);
+        self.noise = Noise::new(&mut self.rng);
+        self.previous_positions = self.positions.clone();
+        self.predicted_positions = self.positions.clone();
+        self.velocities = vec![Vec2::ZERO; self.particle_count];
+        self.densities = vec![0.0; self.particle_count];
+        self.ids = (0..self.particle_count).collect();
+        self.next_id = self.particle_count;
+        self.user_data = UserData::new(Self::USER_DATA_CHANNELS, self.particle_count);
+        self.ink.clear();
+        self.grid.clear();
+        self.mac_grid.clear();
+        self.springs.clear();
+        self.bodies.clear();
+        self.pinned.clear();
+
+        self.ticks = 0;
+        self.checksum = FNV_OFFSET_BASIS;
+        self.time = 0.0;
+        self.last_update_offset = 0.0;
+        self.rain_accumulator = 0.0;
+        self.falling_behind = false;
+        self.update_spatial_lookup();
+    }
 
-            last_update_offset: 0.0,
+    pub fn update(&mut self, delta_time: f32, interactions: &[Interaction]) {
+        let tick_delta = self.tick_delta();
+        let mut end = self.last_update_offset + delta_time;
+
+        let max_time = tick_delta * (Self::MAX_TICKS_PER_UPDATE as f32 + 0.5);
+        let falling_behind = end > max_time;
+        if falling_behind {
+            // only warn when it starts, so a slow machine doesn't log every frame
+            let dropped = end - max_time;
+            match self.falling_behind {
+                false => tracing::warn!("simulation fell behind, dropped {dropped:.3}s"),
+                true => tracing::debug!("simulation fell behind, dropped {dropped:.3}s"),
+            }
+            end = max_time;
         }
-    }
+        self.falling_behind = falling_behind;
 
-    pub fn update(&mut self, delta_time: f32, interaction: Option<Interaction>) {
-        let end = self.last_update_offset + delta_time;
-        let mut t = Self::TICK_DELTA;
+        let _update_span = tracing::debug_span!("update").entered();
+        let mut t = tick_delta;
 
         while t < end {
-            self.tick(Self::TICK_DELTA, interaction.as_ref());
-            t += Self::TICK_DELTA;
+            self.tick(tick_delta, interactions);
+            t += tick_delta;
         }
 
-        self.last_update_offset = end % Self::TICK_DELTA;
+        self.last_update_offset = end % tick_delta;
     }
 
-    fn tick(&mut self, delta_time: f32, interaction: Option<&Interaction>) {
-        // apply user input
-        if let Some(interaction) = interaction {
-            let (pos, strength) = match interaction {
-                Interaction::Repel(pos) => (pos, -Self::INTERACTION_STRENGTH),
-                Interaction::Suck(pos) => (pos, Self::INTERACTION_STRENGTH),
-            };
+    /// Advances the simulation by exactly one tick, regardless of how much time has passed
+    pub fn step(&mut self, interactions: &[Interaction]) {
+        self.tick(self.tick_delta(), interactions);
+        // there's nothing to interpolate from while paused, so show the result of the step as is
+        self.previous_positions.copy_from_slice(&self.positions);
+    }
 
-            for i in 0..PARTICLE_COUNT {
-                let interaction_force =
-                    self.interaction_force(*pos, Self::INTERACTION_RADIUS, strength, i);
-                self.velocities[i] += interaction_force;
+    /// How far the simulation is between the previous tick and the current one, from 0 to 1
+    pub fn interpolation(&self) -> f32 {
+        (self.last_update_offset / self.tick_delta()).min(1.0)
+    }
+
+    /// Where particles should be drawn: between their previous and current positions, so motion
+    /// looks smooth regardless of how the tick rate and frame rate line up
+    pub fn interpolated_positions(&self) -> impl Iterator<Item = Vec2> + '_ {
+        let t = self.interpolation();
+        self.previous_positions
+            .iter()
+            .zip(&self.positions)
+            .map(move |(previous, current)| previous.lerp(*current, t))
+    }
+
+    /// Rotates the direction of gravity by the given angle (in radians)
+    pub fn rotate_gravity(&mut self, angle: f32) {
+        self.settings.gravity = Vec2::from_angle(angle).rotate(self.settings.gravity);
+    }
+
+    /// Flips gravity to point in the opposite direction
+    pub fn flip_gravity(&mut self) {
+        self.settings.gravity = -self.settings.gravity;
+    }
+
+    /// Turns gravity off, or back on to whatever it was before it was turned off
+    pub fn toggle_gravity(&mut self) {
+        if self.settings.gravity == Vec2::ZERO {
+            self.settings.gravity = self.disabled_gravity;
+        } else {
+            self.disabled_gravity = std::mem::take(&mut self.settings.gravity);
+        }
+    }
+
+    /// Applies a change in velocity of the frame of reference (i.e., the window being dragged
+    /// around) to all particles - they're left behind by it, so it acts in the opposite direction
+    pub fn shift_frame_velocity(&mut self, delta: Vec2) {
+        let delta = -delta * self.settings.frame_inertia;
+        for velocity in &mut self.velocities {
+            *velocity += delta;
+        }
+    }
+
+    /// Adds a particle made of the material, or if there are already as many as there can be,
+    /// moves a random one
+    fn spawn(&mut self, position: Vec2, velocity: Vec2, material: usize) {
+        let idx = if self.positions.len() < MAX_PARTICLE_COUNT {
+            self.positions.push(position);
+            self.previous_positions.push(position);
+            self.predicted_positions.push(position);
+            self.velocities.push(velocity);
+            self.densities.push(0.0);
+            self.ids.push(0);
+            self.user_data.resize(self.positions.len());
+            self.positions.len() - 1
+        } else {
+            let idx = self.rng.gen_range(0..self.positions.len());
+            self.springs.detach(idx);
+            idx
+        };
+
+        // a new id even when a particle's moved, since it's not the same particle any more
+        self.ids[idx] = self.next_id;
+        self.next_id += 1;
+        self.positions[idx] = position;
+        self.previous_positions[idx] = position;
+        self.predicted_positions[idx] = position;
+        self.velocities[idx] = velocity;
+        self.user_data.clear(idx);
+        self.user_data
+            .set(idx, self.material_channel, material as f32);
+    }
+
+    /// Drops a body in at the position (kept inside the walls), as heavy as `body_density` for its
+    /// size, removing the oldest one if there are already as many as there can be
+    pub fn add_body(&mut self, shape: Shape, position: Vec2) {
+        if self.bodies.len() >= Self::MAX_BODY_COUNT {
+            self.bodies.remove(0);
+        }
+
+        let mut body = Body::new(shape, position, self.settings.body_density);
+        body.collide_with_walls(self.bounding_box, 0.0);
+        self.bodies.push(body);
+    }
+
+    /// Drops particles in along the top edge at the rain rate
+    fn rain(&mut self, delta_time: f32) {
+        self.rain_accumulator += self.settings.rain_rate * delta_time;
+        while self.rain_accumulator >= 1.0 {
+            self.rain_accumulator -= 1.0;
+
+            let x = self.bounding_box.x + self.rng.gen::<f32>() * self.bounding_box.w;
+            let position = Vec2::new(x, self.bounding_box.top());
+            self.spawn(position, Vec2::new(0.0, Self::RAIN_SPEED), self.material);
+        }
+    }
+
+    fn tick(&mut self, delta_time: f32, interactions: &[Interaction]) {
+        let _tick_span = tracing::debug_span!("tick").entered();
+
+        match self.solver {
+            Solver::Sph | Solver::Pbf | Solver::Flip => {
+                self.tick_particles(delta_time, interactions)
             }
+            Solver::Grid => self.tick_grid(delta_time, interactions),
         }
 
-        self.update_spatial_lookup();
+        self.ticks += 1;
+        self.time += delta_time;
+        self.update_checksum();
+    }
+
+    fn tick_particles(&mut self, delta_time: f32, interactions: &[Interaction]) {
+        if self.ticks.is_multiple_of(Self::REORDER_INTERVAL) {
+            self.reorder_particles();
+        }
+
+        self.previous_positions.copy_from_slice(&self.positions);
+
+        if self.settings.rain {
+            self.rain(delta_time);
+        }
+
+        // apply user input and the pinned interactions, each acting on its own
+        let pinned = std::mem::take(&mut self.pinned);
+        for interaction in pinned.iter().chain(interactions) {
+            self.interact(interaction, delta_time);
+        }
+        self.pinned = pinned;
+
+        // apply gravity
+        for i in 0..self.positions.len() {
+            self.velocities[i] += self.settings.gravity * delta_time;
+        }
+
+        // warm particles are lighter, so they float up against gravity, and cool ones sink
+        if self.settings.heat {
+            let buoyancy = -self.settings.gravity * self.settings.thermal_expansion;
+            for i in 0..self.positions.len() {
+                let temperature = self.temperature(i);
+                self.velocities[i] += buoyancy * temperature * delta_time;
+            }
+        }
+
+        // stir with ambient turbulence
+        if self.settings.turbulence > 0.0 {
+            let time = self.time * self.settings.turbulence_speed;
+            for i in 0..self.positions.len() {
+                let pos = self.positions[i] / self.settings.turbulence_scale;
+                let flow = self.noise.curl(pos, time);
+                self.velocities[i] += flow * self.settings.turbulence * delta_time;
+            }
+        }
+
+        // push particles along with any force fields they're in
+        if self.settings.force_fields {
+            for field in &self.force_fields {
+                for i in 0..self.positions.len() {
+                    if field.region.contains(self.positions[i]) {
+                        self.velocities[i] += field.force * delta_time;
+                    }
+                }
+            }
+        }
 
-        // predict next positions
-        for i in 0..PARTICLE_COUNT {
+        // predict next positions, inside the walls since that's where they'll end up (and so no
+        // particle's too far from the others to find any neighbours, which would leave it without
+        // a density to divide its pressure by)
+        let min = Vec2::new(self.bounding_box.left(), self.bounding_box.top());
+        let max = Vec2::new(self.bounding_box.right(), self.bounding_box.bottom());
+        for i in 0..self.positions.len() {
             self.predicted_positions[i] =
-                self.positions[i] + self.velocities[i] * (Vec2::ONE * Self::TICK_DELTA);
+                (self.positions[i] + self.velocities[i] * (Vec2::ONE * delta_time)).clamp(min, max);
         }
 
-        // calculate densities
-        for i in 0..PARTICLE_COUNT {
-            self.densities[i] = self.calculate_density(i);
+        // neighbours are found by where particles are predicted to be, which is where the
+        // densities and pressures are calculated
+        let lookup_span = tracing::debug_span!("spatial lookup").entered();
+        self.update_spatial_lookup();
+        drop(lookup_span);
+
+        match self.solver {
+            Solver::Sph => {
+                // calculate densities
+                let density_span = tracing::debug_span!("density").entered();
+                for i in 0..self.positions.len() {
+                    self.densities[i] = self.calculate_density(i);
+                }
+                drop(density_span);
+
+                // calculate velocities
+                let pressure_span = tracing::debug_span!("pressure").entered();
+                for i in 0..self.positions.len() {
+                    let pressure_force = self.calculate_pressure_force(i);
+                    let pressure_accel = pressure_force / self.densities[i];
+                    self.velocities[i] += pressure_accel * delta_time;
+                }
+                drop(pressure_span);
+            }
+            Solver::Pbf => self.solve_density_constraints(delta_time),
+            Solver::Flip => {
+                // the grid doesn't need the densities, but they're still shown (e.g. in the HUD)
+                for i in 0..self.positions.len() {
+                    self.densities[i] = self.calculate_density(i);
+                }
+
+                let flip_span = tracing::debug_span!("flip").entered();
+                let flip_ratio = self.settings.flip_ratio;
+                self.mac_grid
+                    .solve(&self.positions, &mut self.velocities, flip_ratio);
+                drop(flip_span);
+            }
+            Solver::Grid => unreachable!("the grid doesn't move particles by their density"),
+        }
+
+        // stretch the goo's springs, and pull particles back together with them
+        if self.settings.goo {
+            let goo_span = tracing::debug_span!("goo").entered();
+            self.update_springs(delta_time);
+            self.apply_springs(delta_time);
+            drop(goo_span);
+        } else if !self.springs.is_empty() {
+            self.springs.clear();
         }
 
-        // calculate velocities
-        for i in 0..PARTICLE_COUNT {
-            let pressure_force = self.calculate_pressure_force(i);
-            let pressure_accel = pressure_force / self.densities[i];
-            self.velocities[i] += pressure_accel * delta_time;
+        // sand grains collide with and rub against each other where they touch
+        let sand_span = tracing::debug_span!("sand").entered();
+        self.resolve_grain_contacts(delta_time);
+        drop(sand_span);
+
+        // reinforce the fluid's rotation where it's strongest, so swirls don't die out
+        if self.settings.vorticity_confinement {
+            let vorticity_span = tracing::debug_span!("vorticity").entered();
+            let vorticities = (0..self.positions.len())
+                .map(|i| self.calculate_vorticity(i))
+                .collect::<Vec<_>>();
+            for i in 0..self.positions.len() {
+                let force = self.calculate_vorticity_force(i, &vorticities);
+                self.velocities[i] += force * self.settings.vorticity_strength * delta_time;
+            }
+            drop(vorticity_span);
+        }
+
+        // blend velocities towards their neighbours' (XSPH), so nearby particles move together
+        // rather than jittering against each other
+        if self.settings.xsph > 0.0 {
+            let xsph_span = tracing::debug_span!("xsph").entered();
+            let smoothed = (0..self.positions.len())
+                .map(|i| self.calculate_xsph_velocity(i))
+                .collect();
+            self.velocities = smoothed;
+            drop(xsph_span);
+        }
+
+        // damp velocities in any sponge layers along the walls
+        for i in 0..self.positions.len() {
+            let depth = self.sponge_depth(self.positions[i]);
+            if depth > 0.0 {
+                self.velocities[i] *=
+                    (-self.settings.sponge_strength * depth * depth * delta_time).exp();
+            }
+        }
+
+        if self.settings.infection {
+            self.spread_infection(delta_time);
+        }
+
+        if self.settings.heat {
+            self.update_temperatures(delta_time);
+        }
+
+        // float the bodies in the fluid, and knock them into each other and the walls
+        if !self.bodies.is_empty() {
+            let bodies_span = tracing::debug_span!("bodies").entered();
+            self.update_bodies(delta_time);
+            drop(bodies_span);
         }
 
         // move particles
-        for i in 0..PARTICLE_COUNT {
+        for i in 0..self.positions.len() {
             self.positions[i] += self.velocities[i] * delta_time;
         }
 
+        self.push_particles_out_of_bodies();
         self.resolve_collisions();
-    }
 
-    fn get_neighbours_by_idx(&self, idx: usize) -> Vec<usize> {
-        self.get_neighbours_by_pos(self.positions[idx])
+        // carry the ink along with the fluid
+        if self.ink.is_visible() {
+            let velocities = self
+                .ink
+                .cell_centres()
+                .map(|pos| self.sample_velocity(pos))
+                .collect::<Vec<_>>();
+            let fade = (-self.settings.ink_fade * delta_time).exp();
+            self.ink.advect(&velocities, delta_time, fade);
+        }
     }
 
-    fn get_neighbours_by_pos(&self, world_pos: Vec2) -> Vec<usize> {
-        let center_pos = world_pos_to_cell_pos(world_pos, Self::SMOOTHING_RADIUS);
-        let sqr_radius = Self::SMOOTHING_RADIUS * Self::SMOOTHING_RADIUS;
-
-        const OFFSETS: [IVec2; 9] = [
-            IVec2::new(-1, -1),
-            IVec2::new(0, -1),
-            IVec2::new(1, -1),
-            IVec2::new(-1, 0),
-            IVec2::new(0, 0),
-            IVec2::new(1, 0),
-            IVec2::new(-1, 1),
-            IVec2::new(0, 1),
-            IVec2::new(1, 1),
-        ];
+    /// Steps the fluid on the grid, and moves the particles along with it
+    fn tick_grid(&mut self, delta_time: f32, interactions: &[Interaction]) {
+        self.previous_positions.copy_from_slice(&self.positions);
 
-        let mut neighbours = vec![];
-        for offset in OFFSETS {
-            let cell_key = create_cell_hash(center_pos + offset) % self.spatial_lookup.len();
-            let cell_start_idx = self.start_indices[cell_key];
+        let pinned = std::mem::take(&mut self.pinned);
+        for interaction in pinned.iter().chain(interactions) {
+            self.interact(interaction, delta_time);
+        }
+        self.pinned = pinned;
+
+        // gravity isn't applied, since the grid's always full of fluid: a uniform pull on all of it
+        // is balanced out by the pressure, and does nothing
+        let time = self.time * self.settings.turbulence_speed;
+        let cell_centres = self.grid.cell_centres().collect::<Vec<_>>();
+        for (pos, velocity) in cell_centres.into_iter().zip(self.grid.velocities_mut()) {
+            if self.settings.turbulence > 0.0 {
+                let flow = self.noise.curl(pos / self.settings.turbulence_scale, time);
+                *velocity += flow * self.settings.turbulence * delta_time;
+            }
 
-            for i in cell_start_idx..self.spatial_lookup.len() {
-                if self.spatial_lookup[i].1 != cell_key {
-                    break;
+            if self.settings.force_fields {
+                for field in self.force_fields.iter().filter(|f| f.region.contains(pos)) {
+                    *velocity += field.force * delta_time;
                 }
+            }
+        }
 
-                let (particle_idx, _) = self.spatial_lookup[i];
-                let sqr_dist = (self.positions[particle_idx] - world_pos).length_squared();
+        let grid_span = tracing::debug_span!("grid").entered();
+        self.grid.step(delta_time, self.settings.grid_viscosity);
+        drop(grid_span);
 
-                if sqr_dist <= sqr_radius {
-                    neighbours.push(particle_idx);
-                }
+        if !self.bodies.is_empty() {
+            let bodies_span = tracing::debug_span!("bodies").entered();
+            self.update_bodies(delta_time);
+            drop(bodies_span);
+        }
+
+        // the particles just show where the fluid's going
+        for i in 0..self.positions.len() {
+            self.velocities[i] = self.grid.sample(self.positions[i]);
+            self.positions[i] += self.velocities[i] * delta_time;
+        }
+        self.push_particles_out_of_bodies();
+        self.resolve_collisions();
+        self.predicted_positions.copy_from_slice(&self.positions);
+        self.update_spatial_lookup();
+
+        if self.ink.is_visible() {
+            let fade = (-self.settings.ink_fade * delta_time).exp();
+            self.ink.advect(self.grid.velocities(), delta_time, fade);
+        }
+    }
+
+    /// A hash of the positions and velocities after every tick so far, which two simulations only
+    /// share for as long as they've stayed exactly in step (e.g. to check networked games are still
+    /// in sync)
+    pub fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    /// Folds the current positions and velocities into the checksum (with FNV-1a)
+    fn update_checksum(&mut self) {
+        let values = self.positions.iter().chain(&self.velocities);
+        let bodies = self.bodies.iter().flat_map(|body| {
+            [body.position, body.velocity]
+                .into_iter()
+                .flat_map(|v| [v.x, v.y])
+                .chain([body.angle, body.angular_velocity])
+        });
+        for value in values.flat_map(|v| [v.x, v.y]).chain(bodies) {
+            for byte in value.to_bits().to_le_bytes() {
+                self.checksum = (self.checksum ^ byte as u64).wrapping_mul(FNV_PRIME);
             }
         }
 
-        neighbours
+        tracing::trace!("tick {} checksum {:016x}", self.ticks, self.checksum);
     }
 
-    fn update_spatial_lookup(&mut self) {
-        for i in 0..PARTICLE_COUNT {
-            let cell_pos = world_pos_to_cell_pos(self.positions[i], Self::SMOOTHING_RADIUS);
-            let cell_key = create_cell_hash(cell_pos) % self.spatial_lookup.len();
-            self.spatial_lookup[i] = (i, cell_key);
-            self.start_indices[i] = usize::MAX;
+    /// How deep the position is in the sponge layers, from 0 outside them to 1 at a wall (where
+    /// layers overlap in a corner, the deepest one counts)
+    fn sponge_depth(&self, pos: Vec2) -> f32 {
+        let bounding_box = &self.bounding_box;
+        [
+            (self.settings.sponge_left, pos.x - bounding_box.left()),
+            (self.settings.sponge_right, bounding_box.right() - pos.x),
+            (self.settings.sponge_top, pos.y - bounding_box.top()),
+            (self.settings.sponge_bottom, bounding_box.bottom() - pos.y),
+        ]
+        .into_iter()
+        .filter(|(thickness, _)| *thickness > 0.0)
+        .map(|(thickness, distance)| (1.0 - distance / thickness).clamp(0.0, 1.0))
+        .fold(0.0, f32::max)
+    }
+
+    /// Pushes the fluid within the interaction radius around (the particles, or the grid's cells
+    /// with `Solver::Grid`), or paints ink there
+    fn interact(&mut self, interaction: &Interaction, delta_time: f32) {
+        match *interaction {
+            Interaction::Paint(pos) => return self.paint_ink(pos, delta_time),
+            Interaction::Pour(pos, material) => return self.pour(pos, material, delta_time),
+            _ => {}
         }
 
-        self.spatial_lookup.sort_by_key(|(_, cell_key)| *cell_key);
+        if self.solver == Solver::Grid {
+            let forces = self
+                .grid
+                .cell_centres()
+                .zip(self.grid.velocities())
+                .map(|(pos, velocity)| self.interaction_velocity(interaction, pos, *velocity))
+                .collect::<Vec<_>>();
+            for (velocity, force) in self.grid.velocities_mut().iter_mut().zip(forces) {
+                *velocity += force;
+            }
+            return;
+        }
 
-        for i in 0..self.spatial_lookup.len() {
-            let (_, cell_key) = self.spatial_lookup[i];
-            let prev_cell_key = if i == 0 {
-                usize::MAX
-            } else {
-                let (_, key_prev) = self.spatial_lookup[i - 1];
-                key_prev
-            };
+        for i in 0..self.positions.len() {
+            let (pos, velocity) = (self.positions[i], self.velocities[i]);
+            let interaction_velocity = self.interaction_velocity(interaction, pos, velocity);
+            self.velocities[i] += interaction_velocity;
+        }
+    }
 
-            if cell_key != prev_cell_key {
-                self.start_indices[cell_key] = i;
+    /// The change in velocity the interaction makes to fluid at `pos` that's moving at `velocity`
+    fn interaction_velocity(&self, interaction: &Interaction, pos: Vec2, velocity: Vec2) -> Vec2 {
+        let radius = self.settings.interaction_radius;
+        match interaction {
+            Interaction::Repel(input) => {
+                let strength = -self.settings.interaction_strength;
+                self.interaction_force(*input, radius, strength, pos, velocity)
+            }
+            Interaction::Suck(input) => {
+                let strength = self.settings.interaction_strength;
+                self.interaction_force(*input, radius, strength, pos, velocity)
+            }
+            Interaction::Drag(input, input_velocity) => {
+                let input_velocity = *input_velocity * self.settings.drag_strength;
+                self.drag_force(*input, radius, input_velocity, pos, velocity)
             }
+            Interaction::Swirl(input) => {
+                let strength = self.settings.interaction_strength;
+                self.swirl_force(*input, radius, strength, pos, velocity)
+            }
+            Interaction::Paint(_) | Interaction::Pour(..) => {
+                unreachable!("painting and pouring don't move the fluid")
+            }
+        }
+    }
+
+    /// Spawns particles of the material within half the interaction radius, at the pour rate
+    fn pour(&mut self, pos: Vec2, material: usize, delta_time: f32) {
+        // rounded up or down at random, so fractions of a particle still add up over time
+        let count = (self.settings.pour_rate * delta_time + self.rng.gen::<f32>()) as usize;
+        let radius = self.settings.interaction_radius * 0.5;
+        for _ in 0..count {
+            let angle = self.rng.gen::<f32>() * TAU;
+            let dist = self.rng.gen::<f32>().sqrt() * radius;
+            self.spawn(pos + Vec2::from_angle(angle) * dist, Vec2::ZERO, material);
+        }
+    }
+
+    /// Paints ink within the interaction radius, in a color that slowly cycles around the color
+    /// wheel as time passes
+    fn paint_ink(&mut self, pos: Vec2, delta_time: f32) {
+        let hue = (self.time * Self::INK_HUE_SPEED).fract() * 6.0;
+        // the fully saturated color with the hue, going red, yellow, green, cyan, blue, magenta
+        let color = Vec3::new(
+            (hue - 3.0).abs() - 1.0,
+            2.0 - (hue - 2.0).abs(),
+            2.0 - (hue - 4.0).abs(),
+        )
+        .clamp(Vec3::ZERO, Vec3::ONE);
+        let amount = self.settings.ink_rate * delta_time;
+        self.ink
+            .paint(pos, self.settings.interaction_radius, color, amount);
+    }
+
+    /// Clears any infection, and infects a single random particle
+    pub fn seed_infection(&mut self) {
+        for idx in 0..self.positions.len() {
+            self.user_data.set(idx, self.infection_channel, 0.0);
+        }
+
+        // there's nothing to infect without any particles
+        if self.positions.is_empty() {
+            return;
         }
+
+        let idx = self.rng.gen_range(0..self.positions.len());
+        self.user_data.set(idx, self.infection_channel, 1.0);
+    }
+
+    /// How infected the particle is, from 0 to 1
+    pub fn infection(&self, idx: usize) -> f32 {
+        self.user_data.get(idx, self.infection_channel)
     }
 
-    fn interaction_force(&self, input: Vec2, radius: f32, strength: f32, idx: usize) -> Vec2 {
-        let offset = input - self.positions[idx];
+    /// How warm the particle is: 0 is where every particle starts, and the heaters warm them up
+    /// (above 0) or cool them down (below 0)
+    pub fn temperature(&self, idx: usize) -> f32 {
+        self.user_data.get(idx, self.temperature_channel)
+    }
+
+    /// Evens out each particle's temperature with its neighbours', and brings particles in heaters
+    /// towards the heaters' temperatures
+    fn update_temperatures(&mut self, delta_time: f32) {
+        let diffusion = (self.settings.heat_diffusion * delta_time).min(1.0);
+        let heating = (self.settings.heating_rate * delta_time).min(1.0);
+        let temperatures = (0..self.positions.len())
+            .map(|idx| {
+                let pos = self.predicted_positions[idx];
+                let mut temperature = self.temperature(idx);
+
+                // the average of the neighbours' temperatures, weighted by the smoothing kernel
+                let mut total = 0.0;
+                let mut total_weight = 0.0;
+                for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+                    if other_idx == idx {
+                        continue;
+                    }
+
+                    let dst = self.predicted_positions[other_idx].distance(pos);
+                    let weight = self.kernel.value(dst, self.settings.smoothing_radius);
+                    total += self.temperature(other_idx) * weight;
+                    total_weight += weight;
+                }
+                if total_weight > 0.0 {
+                    temperature += (total / total_weight - temperature) * diffusion;
+                }
+
+                for heater in self.heaters.iter().filter(|h| h.region.contains(pos)) {
+                    temperature += (heater.temperature - temperature) * heating;
+                }
+
+                temperature
+            })
+            .collect::<Vec<_>>();
+
+        for (idx, temperature) in temperatures.into_iter().enumerate() {
+            self.user_data
+                .set(idx, self.temperature_channel, temperature);
+        }
+    }
+
+    /// The material the particle's made of, or `None` for the base fluid
+    pub fn material(&self, idx: usize) -> Option<&Material> {
+        let material = self.user_data.get(idx, self.material_channel) as usize;
+        material.checked_sub(1).and_then(|i| self.materials.get(i))
+    }
+
+    /// What the material (as in `State::material`) is called
+    pub fn material_name(&self, material: usize) -> &str {
+        match material.checked_sub(1).and_then(|i| self.materials.get(i)) {
+            Some(material) => &material.name,
+            None => "base",
+        }
+    }
+
+    /// The density the particle tries to maintain, which depends on what it's made of
+    pub fn target_density(&self, idx: usize) -> f32 {
+        self.material(idx)
+            .map_or(self.settings.target_density, |material| {
+                material.target_density
+            })
+    }
+
+    /// How the particle moves with its neighbours, which depends on what it's made of
+    pub fn behaviour(&self, idx: usize) -> Behaviour {
+        self.material(idx)
+            .map_or(self.base_behaviour, |material| material.behaviour)
+    }
+
+    fn mass(&self, idx: usize) -> f32 {
+        self.material(idx)
+            .map_or(self.settings.mass, |material| material.mass)
+    }
+
+    /// How much the particle counts towards densities, compared with a particle of the base fluid
+    fn relative_mass(&self, idx: usize) -> f32 {
+        self.material(idx)
+            .map_or(1.0, |material| material.mass / self.settings.mass)
+    }
+
+    /// Each particle catches some of the infection of its most infected neighbour
+    fn spread_infection(&mut self, delta_time: f32) {
+        let rate = self.settings.infection_rate * delta_time;
+        let levels = (0..self.positions.len())
+            .map(|idx| {
+                let pos = self.predicted_positions[idx];
+                let exposure = self
+                    .cell_list
+                    .neighbours(&self.predicted_positions, pos)
+                    .map(|other_idx| self.infection(other_idx))
+                    .fold(0.0, f32::max);
+                (self.infection(idx) + exposure * rate).min(1.0)
+            })
+            .collect::<Vec<_>>();
+
+        for (idx, level) in levels.into_iter().enumerate() {
+            self.user_data.set(idx, self.infection_channel, level);
+        }
+    }
+
+    /// The size of the spatial lookup's cells: the smoothing radius as of the last tick
+    pub fn cell_size(&self) -> f32 {
+        self.cell_list.cell_size()
+    }
+
+    /// The spatial lookup's cell containing the given position
+    pub fn cell_pos(&self, world_pos: Vec2) -> IVec2 {
+        self.cell_list.cell_pos(world_pos)
+    }
+
+    /// How many particles are past each edge of the given region: left, right, top and bottom. This
+    /// goes by the spatial lookup, so it's where the particles were headed as of the last tick
+    pub fn count_outside(&self, rect: Rect) -> [usize; 4] {
+        self.cell_list
+            .count_outside(&self.predicted_positions, rect)
+    }
+
+    /// The cells that are queried for the neighbours of the given position
+    pub fn neighbour_cells(&self, world_pos: Vec2) -> [IVec2; 9] {
+        self.cell_list.neighbour_cells(world_pos)
+    }
+
+    /// The closest particle to the given position, if any are within the smoothing radius
+    pub fn nearest_particle(&self, world_pos: Vec2) -> Option<usize> {
+        self.cell_list
+            .neighbours(&self.predicted_positions, world_pos)
+            .min_by(|&a, &b| {
+                let dist_a = (self.positions[a] - world_pos).length_squared();
+                let dist_b = (self.positions[b] - world_pos).length_squared();
+                dist_a.total_cmp(&dist_b)
+            })
+    }
+
+    /// Sorts the particles by the cell they were in as of the last spatial lookup update, which
+    /// makes the neighbour queries much friendlier to the cache. The lookup must be updated again
+    /// before it's next used, since this invalidates its indices
+    fn reorder_particles(&mut self) {
+        let order = self.cell_list.sorted();
+        if order.len() != self.positions.len() {
+            return;
+        }
+
+        reorder(&mut self.positions, order);
+        reorder(&mut self.previous_positions, order);
+        reorder(&mut self.predicted_positions, order);
+        reorder(&mut self.velocities, order);
+        reorder(&mut self.densities, order);
+        reorder(&mut self.ids, order);
+        self.user_data.reorder(order);
+        self.springs.reorder(order);
+    }
+
+    fn update_spatial_lookup(&mut self) {
+        self.cell_list.update(
+            self.bounding_box,
+            self.settings.smoothing_radius,
+            &self.predicted_positions,
+        );
+        self.boundary
+            .update(self.bounding_box, self.settings.smoothing_radius);
+    }
+
+    fn interaction_force(
+        &self,
+        input: Vec2,
+        radius: f32,
+        strength: f32,
+        pos: Vec2,
+        velocity: Vec2,
+    ) -> Vec2 {
+        let offset = input - pos;
         let sqr_dist = offset.length_squared();
 
         // if particle is inside input radius, calculate force towards input point
@@ -205,16 +1013,68 @@ impl State {
 
             // value is 1 when particle is exactly at input point; 0 when at edge of input circle
             let center_t = 1.0 - dist / radius;
-            // calculate the force (velocity is subtracted to slow the particle down)
-            (dir_to_input_point * strength - self.velocities[idx]) * center_t
+            // velocity is subtracted to slow the particle down, either entirely or only towards and
+            // away from the input point so it keeps any swirl around it
+            let damped_velocity = match self.settings.preserve_rotation {
+                true => dir_to_input_point * velocity.dot(dir_to_input_point),
+                false => velocity,
+            };
+            (dir_to_input_point * strength - damped_velocity) * center_t
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    fn drag_force(
+        &self,
+        input: Vec2,
+        radius: f32,
+        input_velocity: Vec2,
+        pos: Vec2,
+        velocity: Vec2,
+    ) -> Vec2 {
+        let sqr_dist = (input - pos).length_squared();
+
+        // if particle is inside input radius, pull its velocity towards the cursor's velocity
+        if sqr_dist < radius * radius {
+            // value is 1 when particle is exactly at input point; 0 when at edge of input circle
+            let center_t = 1.0 - sqr_dist.sqrt() / radius;
+            (input_velocity - velocity) * center_t
+        } else {
+            Vec2::ZERO
+        }
+    }
+
+    fn swirl_force(
+        &self,
+        input: Vec2,
+        radius: f32,
+        strength: f32,
+        pos: Vec2,
+        velocity: Vec2,
+    ) -> Vec2 {
+        let offset = pos - input;
+        let sqr_dist = offset.length_squared();
+
+        // if particle is inside input radius, pull its speed around the input point towards the
+        // strength, leaving it free to move towards or away from it
+        if sqr_dist < radius * radius && sqr_dist > f32::EPSILON {
+            let dist = sqr_dist.sqrt();
+            // clockwise on screen, since y points down
+            let tangent = offset.perp() / dist;
+            // value is 1 when particle is exactly at input point; 0 when at edge of input circle
+            let center_t = 1.0 - dist / radius;
+            tangent * (strength - velocity.dot(tangent)) * center_t
         } else {
             Vec2::ZERO
         }
     }
 
+    #[cfg(not(feature = "simd"))]
     fn calculate_pressure_force(&mut self, idx: usize) -> Vec2 {
         let mut pressure_force = Vec2::ZERO;
-        for other_idx in 0..PARTICLE_COUNT {
+        let pos = self.predicted_positions[idx];
+        for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
             if other_idx == idx {
                 continue;
             }
@@ -228,84 +1088,600 @@ impl State {
             }
             .normalize();
 
-            let slope = smoothing_kernel_derivative(dst, Self::SMOOTHING_RADIUS);
+            let slope = self.kernel.derivative(dst, self.settings.smoothing_radius);
             let density = self.densities[other_idx];
-            let shared_pressure = self.calculate_shared_pressure(density, self.densities[idx]);
-            pressure_force += shared_pressure * dir * slope * Self::MASS / density;
+            let shared_pressure = self.calculate_shared_pressure(other_idx, idx);
+            pressure_force += shared_pressure * dir * slope * self.mass(other_idx) / density;
+        }
+
+        // the walls push back with the particle's own pressure, as if the fluid carried on past
+        // them, but never pull it in so it doesn't stick to them
+        if self.settings.boundary_particles {
+            let pressure = self.pressure(idx).max(0.0);
+            let target = self.settings.target_density;
+            let mass = self.boundary.mass(target) * self.settings.mass;
+            for boundary_pos in self.boundary.neighbours(pos) {
+                let offset = boundary_pos - pos;
+                let dst = offset.length();
+                if dst == 0.0 {
+                    continue;
+                }
+
+                let slope = self.kernel.derivative(dst, self.settings.smoothing_radius);
+                pressure_force += pressure * offset / dst * slope * mass / target;
+            }
         }
 
         pressure_force
     }
 
-    fn convert_density_to_pressure(&self, density: f32) -> f32 {
-        let density_err = density - Self::TARGET_DENSITY;
-        density_err * Self::PRESSURE_MULTIPLIER
+    /// Like the other `calculate_pressure_force`, but evaluating the kernel for four neighbours at
+    /// a time
+    #[cfg(feature = "simd")]
+    fn calculate_pressure_force(&mut self, idx: usize) -> Vec2 {
+        let mut pressure_force = GradientSum::new(self.kernel, self.settings.smoothing_radius);
+        let pos = self.predicted_positions[idx];
+        for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            if other_idx == idx {
+                continue;
+            }
+
+            let offset = self.predicted_positions[other_idx] - self.predicted_positions[idx];
+            let dst = offset.length();
+            let dir = if dst == 0.0 {
+                self.rng.gen::<Vec2>()
+            } else {
+                offset
+            }
+            .normalize();
+
+            let density = self.densities[other_idx];
+            let shared_pressure = self.calculate_shared_pressure(other_idx, idx);
+            pressure_force.add(dst, dir, shared_pressure * self.mass(other_idx) / density);
+        }
+
+        // the walls push back with the particle's own pressure, as if the fluid carried on past
+        // them, but never pull it in so it doesn't stick to them
+        if self.settings.boundary_particles {
+            let pressure = self.pressure(idx).max(0.0);
+            let target = self.settings.target_density;
+            let mass = self.boundary.mass(target) * self.settings.mass;
+            for boundary_pos in self.boundary.neighbours(pos) {
+                let offset = boundary_pos - pos;
+                let dst = offset.length();
+                if dst == 0.0 {
+                    continue;
+                }
+
+                pressure_force.add(dst, offset / dst, pressure * mass / target);
+            }
+        }
+
+        pressure_force.total()
+    }
+
+    /// Moves the predicted positions towards the target density, then sets the velocities to
+    /// however far the particles ended up moving
+    fn solve_density_constraints(&mut self, delta_time: f32) {
+        let _pbf_span = tracing::debug_span!("pbf").entered();
+        let radius = self.settings.smoothing_radius;
+        let tensile_distance = Self::PBF_TENSILE_DISTANCE * radius;
+        let tensile_reference = self.kernel.value(tensile_distance, radius);
+
+        for iteration in 0..self.settings.pbf_iterations {
+            // the neighbours were already found for the first iteration
+            if iteration > 0 {
+                self.update_spatial_lookup();
+            }
+            for i in 0..self.positions.len() {
+                self.densities[i] = self.calculate_density(i);
+            }
+
+            let lambdas = (0..self.positions.len())
+                .map(|i| self.calculate_lambda(i))
+                .collect::<Vec<_>>();
+            for i in 0..self.positions.len() {
+                let delta = self.calculate_position_delta(i, &lambdas, tensile_reference);
+                self.predicted_positions[i] += delta;
+            }
+            for pos in &mut self.predicted_positions {
+                *pos = pos.clamp(
+                    Vec2::new(self.bounding_box.left(), self.bounding_box.top()),
+                    Vec2::new(self.bounding_box.right(), self.bounding_box.bottom()),
+                );
+            }
+        }
+
+        // leave the neighbours and densities as they are where the particles ended up
+        self.update_spatial_lookup();
+        for i in 0..self.positions.len() {
+            self.densities[i] = self.calculate_density(i);
+            self.velocities[i] = (self.predicted_positions[i] - self.positions[i]) / delta_time;
+        }
+    }
+
+    /// How far along its gradient a particle's density constraint should be moved to satisfy it.
+    /// Particles are only ever pushed apart, so the fluid doesn't pull itself into clumps at its
+    /// surface
+    fn calculate_lambda(&self, idx: usize) -> f32 {
+        let target = self.target_density(idx);
+        let constraint = (self.densities[idx] / target - 1.0).max(0.0);
+        if constraint == 0.0 {
+            return 0.0;
+        }
+
+        let pos = self.predicted_positions[idx];
+        let mut own_gradient = Vec2::ZERO;
+        let mut sqr_gradients = 0.0;
+        for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            if other_idx == idx {
+                continue;
+            }
+
+            let gradient = self.kernel_gradient(idx, other_idx) / target;
+            own_gradient += gradient;
+            sqr_gradients += gradient.length_squared();
+        }
+        // the walls can't be moved, so they only add to the particle's own gradient
+        if self.settings.boundary_particles {
+            let mass = self.boundary.mass(self.settings.target_density);
+            for boundary_pos in self.boundary.neighbours(pos) {
+                own_gradient += self.kernel_gradient_between(pos, boundary_pos) * mass / target;
+            }
+        }
+        sqr_gradients += own_gradient.length_squared();
+
+        -constraint / (sqr_gradients + self.settings.pbf_relaxation)
+    }
+
+    /// How far a particle's moved by its own and its neighbours' density constraints
+    fn calculate_position_delta(
+        &self,
+        idx: usize,
+        lambdas: &[f32],
+        tensile_reference: f32,
+    ) -> Vec2 {
+        let radius = self.settings.smoothing_radius;
+        let pos = self.predicted_positions[idx];
+        let mut delta = Vec2::ZERO;
+        for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            if other_idx == idx {
+                continue;
+            }
+
+            // a little extra push between close particles, which keeps them from clumping
+            let dst = self.predicted_positions[other_idx].distance(pos);
+            let tensile = self.kernel.value(dst, radius) / tensile_reference;
+            let correction = -Self::PBF_TENSILE_STRENGTH * tensile.powi(4);
+
+            let lambda = lambdas[idx] + lambdas[other_idx] + correction;
+            delta += self.kernel_gradient(idx, other_idx) * lambda;
+        }
+        // the walls push back as hard as the particle pushes on them
+        if self.settings.boundary_particles {
+            let mass = self.boundary.mass(self.settings.target_density);
+            for boundary_pos in self.boundary.neighbours(pos) {
+                delta += self.kernel_gradient_between(pos, boundary_pos) * mass * lambdas[idx];
+            }
+        }
+
+        delta / self.target_density(idx)
+    }
+
+    /// Joins neighbours that aren't joined yet with springs, lets the springs' rest lengths give
+    /// way where they're stretched or squashed too far, and removes any that have been stretched
+    /// past the smoothing radius
+    fn update_springs(&mut self, delta_time: f32) {
+        let radius = self.settings.smoothing_radius;
+        for idx in 0..self.positions.len() {
+            let pos = self.predicted_positions[idx];
+            for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+                let dst = self.predicted_positions[other_idx].distance(pos);
+                if other_idx > idx && dst < radius {
+                    self.springs.attach(idx, other_idx, dst);
+                }
+            }
+        }
+
+        let positions = &self.predicted_positions;
+        let plasticity = self.settings.goo_plasticity * delta_time;
+        let yield_ratio = self.settings.goo_yield;
+        self.springs.retain(|a, b, rest_length| {
+            let dst = positions[a].distance(positions[b]);
+            let tolerance = yield_ratio * *rest_length;
+            if dst > *rest_length + tolerance {
+                *rest_length += plasticity * (dst - *rest_length - tolerance);
+            } else if dst < *rest_length - tolerance {
+                *rest_length -= plasticity * (*rest_length - tolerance - dst);
+            }
+            *rest_length < radius
+        });
+    }
+
+    /// Pulls (or pushes) the particles joined by each spring towards its rest length, more gently
+    /// the longer the spring is
+    fn apply_springs(&mut self, delta_time: f32) {
+        let radius = self.settings.smoothing_radius;
+        let stiffness = self.settings.goo_stiffness;
+        for (a, b, rest_length) in self.springs.iter() {
+            let offset = self.predicted_positions[b] - self.predicted_positions[a];
+            let dst = offset.length();
+            if dst <= f32::EPSILON {
+                continue;
+            }
+
+            // how far each particle would be moved this tick, as a change in velocity
+            let displacement = offset / dst
+                * (stiffness * delta_time * delta_time)
+                * (1.0 - rest_length / radius)
+                * (rest_length - dst);
+            let change = displacement * 0.5 / delta_time;
+            self.velocities[a] -= change;
+            self.velocities[b] += change;
+        }
+    }
+
+    /// Resolves each pair of touching sand grains in turn, so the velocities each pair ends up
+    /// with are taken into account by the next
+    fn resolve_grain_contacts(&mut self, delta_time: f32) {
+        let mut touching = vec![];
+        for idx in 0..self.positions.len() {
+            if self.behaviour(idx) != Behaviour::Sand {
+                continue;
+            }
+
+            // each pair's only resolved once
+            let pos = self.predicted_positions[idx];
+            touching.clear();
+            touching.extend(
+                self.cell_list
+                    .neighbours(&self.predicted_positions, pos)
+                    .filter(|&other_idx| other_idx > idx)
+                    .filter(|&other_idx| self.behaviour(other_idx) == Behaviour::Sand),
+            );
+            for &other_idx in &touching {
+                let change = self.calculate_grain_contact(idx, other_idx, delta_time);
+                self.velocities[idx] += change;
+                self.velocities[other_idx] -= change;
+            }
+        }
+    }
+
+    /// The change in a sand grain's velocity from touching another one (which has the opposite
+    /// change): overlapping grains are pushed apart like a spring, lose the speed they were
+    /// approaching each other at (so they settle rather than bouncing), and have their sliding past
+    /// each other slowed by friction
+    fn calculate_grain_contact(&self, idx: usize, other_idx: usize, delta_time: f32) -> Vec2 {
+        let grain_size = self.settings.smoothing_radius * Self::GRAIN_SIZE;
+        let offset = self.predicted_positions[idx] - self.predicted_positions[other_idx];
+        let dst = offset.length();
+        if dst >= grain_size || dst <= f32::EPSILON {
+            return Vec2::ZERO;
+        }
+
+        // the normal points away from the other grain, and each grain takes half of the change
+        let normal = offset / dst;
+        let relative_velocity = self.velocities[idx] - self.velocities[other_idx];
+        let approach_speed = relative_velocity.dot(normal).min(0.0);
+        let push = self.settings.sand_stiffness * (grain_size - dst) * delta_time;
+        let normal_change = (push - approach_speed) * 0.5;
+
+        let sliding = relative_velocity - normal * relative_velocity.dot(normal);
+        let friction = normal_change * self.settings.sand_friction;
+        normal * normal_change - (sliding * 0.5).clamp_length_max(friction)
+    }
+
+    /// The smoothing kernel's gradient at a particle, due to one of its neighbours
+    fn kernel_gradient(&self, idx: usize, other_idx: usize) -> Vec2 {
+        let (pos, other_pos) = (
+            self.predicted_positions[idx],
+            self.predicted_positions[other_idx],
+        );
+        self.kernel_gradient_between(pos, other_pos)
+    }
+
+    /// The kernel's gradient at `pos`, for a neighbour at `other_pos`
+    fn kernel_gradient_between(&self, pos: Vec2, other_pos: Vec2) -> Vec2 {
+        let offset = pos - other_pos;
+        let dst = offset.length();
+        if dst == 0.0 {
+            return Vec2::ZERO;
+        }
+
+        offset / dst * self.kernel.derivative(dst, self.settings.smoothing_radius)
+    }
+
+    /// How fast the fluid's spinning around a particle (the curl of its velocity), positive when
+    /// it's clockwise on screen
+    fn calculate_vorticity(&self, idx: usize) -> f32 {
+        let pos = self.predicted_positions[idx];
+        let mut vorticity = 0.0;
+        for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            if other_idx == idx {
+                continue;
+            }
+
+            let relative_velocity = self.velocities[other_idx] - self.velocities[idx];
+            let gradient = self.kernel_gradient(idx, other_idx);
+            let volume = self.mass(other_idx) / self.densities[other_idx];
+            vorticity += relative_velocity.perp_dot(gradient) * volume;
+        }
+
+        vorticity
+    }
+
+    /// Pushes a particle around the nearest concentration of vorticity, in the direction it's
+    /// already spinning
+    fn calculate_vorticity_force(&self, idx: usize, vorticities: &[f32]) -> Vec2 {
+        let pos = self.predicted_positions[idx];
+        // points towards where the fluid is spinning fastest
+        let mut towards_vortex = Vec2::ZERO;
+        for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            if other_idx == idx {
+                continue;
+            }
+
+            let difference = vorticities[other_idx].abs() - vorticities[idx].abs();
+            let gradient = self.kernel_gradient(idx, other_idx);
+            let volume = self.mass(other_idx) / self.densities[other_idx];
+            towards_vortex += gradient * difference * volume;
+        }
+
+        let vorticity = vorticities[idx];
+        let n = towards_vortex.normalize_or_zero();
+        Vec2::new(n.y * vorticity, -n.x * vorticity)
+    }
+
+    fn calculate_xsph_velocity(&self, idx: usize) -> Vec2 {
+        let velocity = self.velocities[idx];
+        let pos = self.predicted_positions[idx];
+        let mut correction = Vec2::ZERO;
+        for other_idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            if other_idx == idx {
+                continue;
+            }
+
+            let dst = self.predicted_positions[other_idx].distance(pos);
+            let weight = self.kernel.value(dst, self.settings.smoothing_radius);
+            // each neighbour counts for its mass over the pair's average density
+            let mean_density = (self.densities[idx] + self.densities[other_idx]) * 0.5;
+            let relative_velocity = self.velocities[other_idx] - velocity;
+            correction += relative_velocity * weight * self.mass(other_idx) / mean_density;
+        }
+
+        velocity + correction * self.settings.xsph
     }
 
-    fn calculate_shared_pressure(&self, density_a: f32, density_b: f32) -> f32 {
-        let pressure_a = self.convert_density_to_pressure(density_a);
-        let pressure_b = self.convert_density_to_pressure(density_b);
-        (pressure_a + pressure_b) / 2.0
+    /// The fluid's velocity at the given position: the average velocity of nearby particles,
+    /// weighted by the smoothing kernel (or the grid's velocity there, with `Solver::Grid`)
+    pub fn sample_velocity(&self, pos: Vec2) -> Vec2 {
+        if self.solver == Solver::Grid {
+            return self.grid.sample(pos);
+        }
+
+        let mut velocity = Vec2::ZERO;
+        let mut total_weight = 0.0;
+        for idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            let dist = (self.predicted_positions[idx] - pos).length();
+            let weight = self.kernel.value(dist, self.settings.smoothing_radius);
+            velocity += self.velocities[idx] * weight;
+            total_weight += weight;
+        }
+
+        if total_weight > 0.0 {
+            velocity / total_weight
+        } else {
+            Vec2::ZERO
+        }
     }
 
+    /// The fluid's density at the given position (counting the boundary particles, while they're on)
+    #[cfg(not(feature = "simd"))]
+    pub fn sample_density(&self, pos: Vec2) -> f32 {
+        let mut density = 0.0;
+        for idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            let dist = (self.predicted_positions[idx] - pos).length();
+            density +=
+                self.kernel.value(dist, self.settings.smoothing_radius) * self.relative_mass(idx);
+        }
+
+        if self.settings.boundary_particles {
+            let mass = self.boundary.mass(self.settings.target_density);
+            for boundary_pos in self.boundary.neighbours(pos) {
+                let dist = boundary_pos.distance(pos);
+                density += self.kernel.value(dist, self.settings.smoothing_radius) * mass;
+            }
+        }
+
+        density
+    }
+
+    /// Like the other `sample_density`, but evaluating the kernel for four neighbours at a time
+    #[cfg(feature = "simd")]
+    pub fn sample_density(&self, pos: Vec2) -> f32 {
+        let mut density = ValueSum::new(self.kernel, self.settings.smoothing_radius);
+        for idx in self.cell_list.neighbours(&self.predicted_positions, pos) {
+            let dist = (self.predicted_positions[idx] - pos).length();
+            density.add(dist, self.relative_mass(idx));
+        }
+
+        if self.settings.boundary_particles {
+            let mass = self.boundary.mass(self.settings.target_density);
+            for boundary_pos in self.boundary.neighbours(pos) {
+                density.add(boundary_pos.distance(pos), mass);
+            }
+        }
+
+        density.total()
+    }
+
+    /// How far the particles' densities were from the target density as of the last tick, on
+    /// average, as a fraction of the target
+    pub fn density_error(&self) -> f32 {
+        if self.densities.is_empty() {
+            return 0.0;
+        }
+
+        let total = (0..self.densities.len())
+            .map(|idx| {
+                let target = self.target_density(idx);
+                (self.densities[idx] - target).abs() / target
+            })
+            .sum::<f32>();
+        total / self.densities.len() as f32
+    }
+
+    /// The pressure on a particle as of the last tick: positive when it's pushing its neighbours
+    /// away, and negative when it's pulling them in
+    pub fn pressure(&self, idx: usize) -> f32 {
+        let density_err = self.densities[idx] - self.target_density(idx);
+        let pressure = density_err * self.effective_pressure_multiplier();
+        // sand grains don't stick together, so they're only ever pushed apart
+        match self.behaviour(idx) {
+            Behaviour::Liquid => pressure,
+            Behaviour::Sand => pressure.max(0.0),
+        }
+    }
+
+    /// `pressure_multiplier`, capped so that pressure can't reach further than
+    /// `MAX_PRESSURE_REACH` in a tick
+    pub fn effective_pressure_multiplier(&self) -> f32 {
+        let max_speed =
+            Self::MAX_PRESSURE_REACH * self.settings.smoothing_radius / self.tick_delta();
+        self.settings.pressure_multiplier.min(max_speed * max_speed)
+    }
+
+    fn calculate_shared_pressure(&self, idx_a: usize, idx_b: usize) -> f32 {
+        (self.pressure(idx_a) + self.pressure(idx_b)) / 2.0
+    }
+
+    /// Stops particles at the walls, bouncing them back off them. The boundary particles (while
+    /// they're on) keep the fluid from getting there, so this only catches what's pushed through
     fn resolve_collisions(&mut self) {
-        for i in 0..PARTICLE_COUNT {
+        for i in 0..self.positions.len() {
             let p = &mut self.positions[i];
             let v = &mut self.velocities[i];
 
             if p.x < self.bounding_box.left() {
                 p.x = self.bounding_box.left();
-                v.x *= v.x.signum() * Self::COLLISION_DAMPING;
+                v.x *= v.x.signum() * self.settings.collision_damping;
             }
             if p.x > self.bounding_box.right() {
                 p.x = self.bounding_box.right();
-                v.x *= -v.x.signum() * Self::COLLISION_DAMPING;
+                v.x *= -v.x.signum() * self.settings.collision_damping;
             }
             if p.y < self.bounding_box.top() {
                 p.y = self.bounding_box.top();
-                v.y *= v.y.signum() * Self::COLLISION_DAMPING;
+                v.y *= v.y.signum() * self.settings.collision_damping;
             }
             if p.y > self.bounding_box.bottom() {
                 p.y = self.bounding_box.bottom();
-                v.y *= -v.y.signum() * Self::COLLISION_DAMPING;
+                v.y *= -v.y.signum() * self.settings.collision_damping;
             }
         }
     }
 
-    fn calculate_density(&self, idx: usize) -> f32 {
-        let mut density = 0.0;
+    /// Lifts each body by the weight of the fluid it's in and drags it along with the fluid around
+    /// it, then moves them all and bounces them off each other and the walls
+    fn update_bodies(&mut self, delta_time: f32) {
+        let gravity = self.settings.gravity;
+        let drag = (self.settings.body_drag * delta_time).min(1.0);
+
+        let mut bodies = std::mem::take(&mut self.bodies);
+        for body in &mut bodies {
+            let points = body.surface_points();
+            let share = 1.0 / points.len() as f32;
+            let mut submerged = 0.0;
+            for point in points {
+                // how deep in the fluid this side of the body is, from 0 out of it to 1. The fluid's
+                // pushed out of the body, so right on its surface only the fluid outside it counts,
+                // and it's only half as dense as the fluid around it
+                let depth = match self.solver {
+                    // the grid's always full of fluid
+                    Solver::Grid => 1.0,
+                    _ => {
+                        let density = self.sample_density(point) * 2.0;
+                        (density / self.settings.target_density).min(1.0)
+                    }
+                };
+                let slip = self.sample_velocity(point) - body.velocity_at(point);
+                body.apply_impulse(slip * depth * drag * share, point);
+                submerged += depth * share;
+            }
 
-        for other_idx in self.get_neighbours_by_idx(idx) {
-            let dist = (self.positions[other_idx] - self.positions[idx]).length();
-            let influence = smoothing_kernel(dist, Self::SMOOTHING_RADIUS);
-            density += influence;
+            let buoyancy = -gravity * submerged / body.density;
+            body.velocity += (gravity + buoyancy) * delta_time;
+            body.integrate(delta_time);
         }
 
-        density
+        let restitution = self.settings.collision_damping;
+        for j in 1..bodies.len() {
+            let (before, after) = bodies.split_at_mut(j);
+            for a in before {
+                Body::collide(a, &mut after[0], restitution);
+            }
+        }
+        for body in &mut bodies {
+            body.collide_with_walls(self.bounding_box, restitution);
+        }
+        self.bodies = bodies;
     }
-}
 
-fn smoothing_kernel(dist: f32, radius: f32) -> f32 {
-    if dist >= radius {
-        return 0.0;
+    /// Moves any particles that have ended up inside a body out to its surface, and stops them
+    /// moving any further into it
+    fn push_particles_out_of_bodies(&mut self) {
+        for body in &self.bodies {
+            let reach = body.shape.bounding_radius();
+            for (pos, velocity) in self.positions.iter_mut().zip(&mut self.velocities) {
+                if pos.distance_squared(body.position) > reach * reach {
+                    continue;
+                }
+
+                let (dist, normal) = body.signed_distance(*pos);
+                if dist >= 0.0 {
+                    continue;
+                }
+
+                *pos -= normal * dist;
+                let approach = (*velocity - body.velocity_at(*pos)).dot(normal);
+                if approach < 0.0 {
+                    *velocity -= normal * approach;
+                }
+            }
+        }
     }
 
-    let volume = (PI * radius.powi(4)) / 6.0;
-    (radius - dist) * (radius - dist) / volume
+    fn calculate_density(&self, idx: usize) -> f32 {
+        self.sample_density(self.predicted_positions[idx])
+    }
+}
+
+/// Rearranges `values` so that the value at `order[i]` ends up at `i`
+fn reorder<T: Copy>(values: &mut Vec<T>, order: &[usize]) {
+    *values = order.iter().map(|&idx| values[idx]).collect();
 }
 
-fn smoothing_kernel_derivative(dist: f32, radius: f32) -> f32 {
-    if dist >= radius {
-        return 0.0;
+fn create_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
     }
+}
 
-    let scale = 12.0 / (radius.powi(4) * PI);
-    (dist - radius) * scale
+/// `State::VIEW` with the margin added to each side
+fn simulated_region(settings: &Settings) -> Rect {
+    let margin = settings.margin;
+    let view = State::VIEW;
+    Rect::new(
+        view.x - margin,
+        view.y - margin,
+        view.w + margin * 2.0,
+        view.h + margin * 2.0,
+    )
 }
 
-fn generate_grid(bounding_box: Rect, n: usize) -> Vec<Vec2> {
+fn generate_grid(rng: &mut impl Rng, bounding_box: Rect, n: usize) -> Vec<Vec2> {
     let mut points = Vec::new();
-    let mut rng = rand::thread_rng();
 
     for _ in 0..n {
         points.push(Vec2::new(
@@ -317,15 +1693,32 @@ fn generate_grid(bounding_box: Rect, n: usize) -> Vec<Vec2> {
     points
 }
 
-fn world_pos_to_cell_pos(world_pos: Vec2, smoothing_radius: f32) -> IVec2 {
-    IVec2::new(
-        (world_pos.x / smoothing_radius).floor() as i32,
-        (world_pos.y / smoothing_radius).floor() as i32,
-    )
-}
-
-fn create_cell_hash(cell_pos: IVec2) -> usize {
-    let a = (cell_pos.x) as usize * 15823;
-    let b = (cell_pos.y) as usize * 9737333;
-    a + b
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reset_matches_new_state() {
+        let seed = Some(7);
+        let new = State::new(Settings::default(), seed);
+        let mut state = State::new(Settings::default(), seed);
+        state.pinned.push(Interaction::Repel(Vec2::new(4.0, 3.0)));
+        for _ in 0..10 {
+            state.step(&[Interaction::Swirl(Vec2::new(8.0, 4.5))]);
+        }
+        assert_ne!(state.positions, new.positions);
+
+        state.reset();
+        assert_eq!(state.positions, new.positions);
+        assert_eq!(state.velocities, new.velocities);
+        assert_eq!(state.ids, new.ids);
+        assert_eq!(state.checksum(), new.checksum());
+        assert!(state.pinned.is_empty());
+
+        // and it carries on the same way too
+        let mut new = new;
+        state.step(&[]);
+        new.step(&[]);
+        assert_eq!(state.positions, new.positions);
+    }
 }
@@ -0,0 +1,33 @@
+use std::time::{Duration, Instant};
+
+/// A short message that's shown on screen for a few seconds
+pub struct Toast {
+    message: String,
+    shown_at: Instant,
+}
+
+impl Toast {
+    const DURATION: Duration = Duration::from_secs(3);
+
+    pub fn new(message: impl Into<String>) -> Toast {
+        let message = message.into();
+        tracing::info!("{message}");
+        Toast {
+            message,
+            shown_at: Instant::now(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// When the toast should stop being shown
+    pub fn deadline(&self) -> Instant {
+        self.shown_at + Self::DURATION
+    }
+
+    pub fn expired(&self) -> bool {
+        Instant::now() >= self.deadline()
+    }
+}
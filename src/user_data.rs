@@ -0,0 +1,53 @@
+//! Extra values for each particle that aren't part of the simulation itself (e.g. whether it's
+//! infected), so features can tag particles without changing the layout of `State` each time.
+//!
+//! The channels are fixed when the state is created, and their values follow the particles
+//! around: they're reordered along with them, and cleared when a particle is respawned.
+
+pub struct UserData {
+    channels: &'static [&'static str],
+    // each particle's values, one after another
+    values: Vec<f32>,
+}
+
+impl UserData {
+    pub fn new(channels: &'static [&'static str], len: usize) -> UserData {
+        UserData {
+            channels,
+            values: vec![0.0; channels.len() * len],
+        }
+    }
+
+    /// The index of the channel with the given name, for use with `get` and `set`
+    pub fn channel(&self, name: &str) -> Option<usize> {
+        self.channels.iter().position(|channel| *channel == name)
+    }
+
+    pub fn get(&self, idx: usize, channel: usize) -> f32 {
+        self.values[idx * self.channels.len() + channel]
+    }
+
+    pub fn set(&mut self, idx: usize, channel: usize, value: f32) {
+        self.values[idx * self.channels.len() + channel] = value;
+    }
+
+    /// Makes room for the given number of particles, new ones start with all their values at 0
+    pub fn resize(&mut self, len: usize) {
+        self.values.resize(self.channels.len() * len, 0.0);
+    }
+
+    /// Sets all of a particle's values back to 0
+    pub fn clear(&mut self, idx: usize) {
+        let n = self.channels.len();
+        self.values[idx * n..(idx + 1) * n].fill(0.0);
+    }
+
+    /// Moves each particle's values from `order[i]` to `i`
+    pub fn reorder(&mut self, order: &[usize]) {
+        let n = self.channels.len();
+        self.values = order
+            .iter()
+            .flat_map(|&idx| self.values[idx * n..(idx + 1) * n].iter().copied())
+            .collect();
+    }
+}
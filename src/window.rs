@@ -5,16 +5,106 @@ use glutin::prelude::*;
 use glutin::surface::{Surface, WindowSurface};
 use glutin_winit::{DisplayBuilder, GlWindow};
 use raw_window_handle::HasRawWindowHandle;
-use winit::dpi::LogicalSize;
+use winit::dpi::{LogicalSize, PhysicalPosition};
 use winit::event_loop::{EventLoop, EventLoopBuilder};
-use winit::window::{Window, WindowBuilder};
+use winit::window::{Window, WindowBuilder, WindowLevel};
 
 const WINDOW_TITLE: &str = "plasma-pong";
 
+/// How the window's opened, see `create_window`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowOptions {
+    /// The size of the window, in logical pixels
+    pub size: LogicalSize<u32>,
+    /// Where the window is put, in physical pixels from the top left of the desktop, or in the
+    /// middle of its monitor if that's `None`
+    pub position: Option<PhysicalPosition<i32>>,
+    /// No decorations, and a config with an alpha channel so whatever's behind it shows through
+    pub transparent: bool,
+    /// Above all other windows
+    pub always_on_top: bool,
+    /// Part of the desktop background, covering its monitor
+    pub wallpaper: bool,
+    /// Avoids anything drivers are likely to trip over: the config with the fewest samples is
+    /// picked (so no MSAA), the context isn't a debug context, and a legacy 2.1 context is tried
+    /// before the others
+    pub safe_mode: bool,
+}
+
 /// Mostly all taken from:
 /// https://github.com/rust-windowing/glutin/blob/master/glutin_examples/src/lib.rs
 pub fn create_window(
+    options: WindowOptions,
+) -> (
+    Window,
+    EventLoop<()>,
+    Display,
+    Surface<WindowSurface>,
+    Option<NotCurrentContext>,
+) {
+    let mut window_builder = WindowBuilder::new()
+        .with_title(WINDOW_TITLE)
+        .with_inner_size(options.size)
+        .with_transparent(options.transparent)
+        .with_decorations(!options.transparent && !options.wallpaper);
+    if options.wallpaper {
+        window_builder = as_desktop(window_builder);
+    }
+    if options.always_on_top {
+        window_builder = window_builder.with_window_level(WindowLevel::AlwaysOnTop);
+    }
+    if let Some(position) = options.position {
+        window_builder = window_builder.with_position(position);
+    }
+
+    let created = create(window_builder, options);
+
+    // the monitor the window is on is only known once it's been created, so it's centred (or made
+    // to cover it) afterwards
+    let window = &created.0;
+    match (options.position, window.current_monitor()) {
+        (_, Some(monitor)) if options.wallpaper => {
+            window.set_outer_position(monitor.position());
+            window.set_inner_size(monitor.size());
+        }
+        (None, Some(monitor)) => {
+            let (monitor_pos, monitor_size) = (monitor.position(), monitor.size());
+            let window_size = window.outer_size();
+            window.set_outer_position(PhysicalPosition::new(
+                monitor_pos.x + (monitor_size.width as i32 - window_size.width as i32) / 2,
+                monitor_pos.y + (monitor_size.height as i32 - window_size.height as i32) / 2,
+            ));
+        }
+        _ => {}
+    }
+
+    created
+}
+
+/// Makes the window part of the desktop background, below everything else
+#[cfg(all(
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+))]
+fn as_desktop(window_builder: WindowBuilder) -> WindowBuilder {
+    use winit::platform::x11::{WindowBuilderExtX11, XWindowType};
+    window_builder.with_x11_window_type(vec![XWindowType::Desktop])
+}
+
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "macos", target_os = "ios", target_os = "android"))
+)))]
+fn as_desktop(window_builder: WindowBuilder) -> WindowBuilder {
+    tracing::warn!("wallpapers are only supported on X11, opening a normal window");
+    window_builder
+}
+
+/// Like `create_window`, but the window is never shown: it's only there for its context, so
+/// anything drawn with it should go to an offscreen framebuffer
+pub fn create_hidden_window(
     window_size: LogicalSize<u32>,
+    safe_mode: bool,
 ) -> (
     Window,
     EventLoop<()>,
@@ -22,18 +112,53 @@ pub fn create_window(
     Surface<WindowSurface>,
     Option<NotCurrentContext>,
 ) {
+    let options = WindowOptions {
+        size: window_size,
+        safe_mode,
+        ..WindowOptions::default()
+    };
+    create(
+        WindowBuilder::new()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(window_size)
+            .with_visible(false),
+        options,
+    )
+}
+
+fn create(
+    window_builder: WindowBuilder,
+    options: WindowOptions,
+) -> (
+    Window,
+    EventLoop<()>,
+    Display,
+    Surface<WindowSurface>,
+    Option<NotCurrentContext>,
+) {
+    let WindowOptions {
+        transparent,
+        safe_mode,
+        ..
+    } = options;
     let event_loop = EventLoopBuilder::new().build();
-    let window_builder = WindowBuilder::new()
-        // .with_position(PhysicalPosition::new(WINDOW_X, WINDOW_Y))
-        .with_title(WINDOW_TITLE)
-        .with_inner_size(window_size);
 
+    // a transparent window needs a config with an alpha channel, others can have any
+    let template = ConfigTemplateBuilder::new().with_transparency(transparent);
     let (window, gl_config) = DisplayBuilder::new()
         .with_window_builder(Some(window_builder))
-        .build(&event_loop, ConfigTemplateBuilder::new(), |targets| {
-            // Find the config with the maximum number of samples
+        .build(&event_loop, template, |targets| {
+            // Find the config with the maximum number of samples, or the fewest in safe mode
             targets
                 .reduce(|curr, next| {
+                    if safe_mode {
+                        return if next.num_samples() < curr.num_samples() {
+                            next
+                        } else {
+                            curr
+                        };
+                    }
+
                     let transparency_check = next.supports_transparency().unwrap_or(false)
                         && !curr.supports_transparency().unwrap_or(false);
 
@@ -62,7 +187,10 @@ pub fn create_window(
     // The context creation part. It can be created before surface and that's how
     // it's expected in multithreaded + multiwindow operation mode, since you
     // can send NotCurrentContext, but not Surface.
-    let context_attributes = ContextAttributesBuilder::new().build(raw_window_handle);
+    // Debug contexts are more likely to report messages through `KHR_debug`.
+    let context_attributes = ContextAttributesBuilder::new()
+        .with_debug(cfg!(debug_assertions) && !safe_mode)
+        .build(raw_window_handle);
 
     // Since glutin by default tries to create OpenGL core context, which may not be
     // present we should try gles.
@@ -76,20 +204,28 @@ pub fn create_window(
         .with_context_api(ContextApi::OpenGl(Some(Version::new(2, 1))))
         .build(raw_window_handle);
 
+    // In safe mode the legacy context goes first, since it's the path with the fewest features
+    let attempts = if safe_mode {
+        [
+            legacy_context_attributes,
+            context_attributes,
+            fallback_context_attributes,
+        ]
+    } else {
+        [
+            context_attributes,
+            fallback_context_attributes,
+            legacy_context_attributes,
+        ]
+    };
+
     // Finally, we can create the gl context
-    let not_current_gl_context: Option<glutin::context::NotCurrentContext> = Some(unsafe {
-        gl_display
-            .create_context(&gl_config, &context_attributes)
-            .unwrap_or_else(|_| {
-                gl_display
-                    .create_context(&gl_config, &fallback_context_attributes)
-                    .unwrap_or_else(|_| {
-                        gl_display
-                            .create_context(&gl_config, &legacy_context_attributes)
-                            .expect("failed to create context")
-                    })
-            })
-    });
+    let not_current_gl_context: Option<glutin::context::NotCurrentContext> = Some(
+        attempts
+            .iter()
+            .find_map(|attrs| unsafe { gl_display.create_context(&gl_config, attrs).ok() })
+            .expect("failed to create context"),
+    );
 
     (
         window,
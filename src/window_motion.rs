@@ -0,0 +1,74 @@
+use std::time::Instant;
+
+use glam::Vec2;
+use winit::dpi::PhysicalPosition;
+
+/// Tracks the velocity of the window on the desktop, so that moving the window can be turned into
+/// momentum for the particles inside it.
+pub struct WindowMotion {
+    last_position: Option<PhysicalPosition<i32>>,
+    last_moved: Instant,
+    velocity: Vec2,
+}
+
+impl WindowMotion {
+    /// If the window hasn't moved for this long, it's considered to have stopped
+    const SETTLE_SECS: f32 = 0.1;
+
+    pub fn new() -> WindowMotion {
+        WindowMotion {
+            last_position: None,
+            last_moved: Instant::now(),
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    /// Call when the window is moved; returns the change in the window's velocity (in pixels/s)
+    pub fn moved(&mut self, position: PhysicalPosition<i32>) -> Vec2 {
+        let elapsed = self.last_moved.elapsed().as_secs_f32();
+        self.last_moved = Instant::now();
+
+        let last_position = match self.last_position.replace(position) {
+            Some(last_position) => last_position,
+            None => return Vec2::ZERO,
+        };
+
+        // the first movement after the window has been still shouldn't be averaged over the
+        // entire time it was sitting still
+        let elapsed = elapsed.min(Self::SETTLE_SECS);
+        if elapsed <= f32::EPSILON {
+            return Vec2::ZERO;
+        }
+
+        let offset = Vec2::new(
+            (position.x - last_position.x) as f32,
+            (position.y - last_position.y) as f32,
+        );
+
+        let velocity = offset / elapsed;
+        let delta = velocity - self.velocity;
+        self.velocity = velocity;
+        delta
+    }
+
+    /// Forgets where the window was and how it was moving, for when it's moved in a way that
+    /// shouldn't jolt the particles (e.g. going fullscreen)
+    pub fn reset(&mut self) {
+        self.last_position = None;
+        self.velocity = Vec2::ZERO;
+    }
+
+    /// Call once per frame; once the window stops moving this returns the change in velocity
+    /// required to bring it to rest
+    pub fn settle(&mut self) -> Vec2 {
+        if self.velocity != Vec2::ZERO
+            && self.last_moved.elapsed().as_secs_f32() > Self::SETTLE_SECS
+        {
+            let delta = -self.velocity;
+            self.velocity = Vec2::ZERO;
+            delta
+        } else {
+            Vec2::ZERO
+        }
+    }
+}